@@ -0,0 +1,206 @@
+// MANDATE: Artboards: named world rectangles with their own background
+#![deny(warnings)]
+
+use crate::layer::LayerId;
+use crate::render::pipeline::InstanceData;
+use crate::shape::ShapeId;
+use glam::{Mat3, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// Artboard ID type.
+/// MANDATE: Type alias for clarity.
+pub type ArtboardId = u64;
+
+/// Maximum artboards per document.
+/// MANDATE: Bounded allocation.
+pub const MAX_ARTBOARDS: usize = 1_000;
+
+/// Background of new artboards: opaque white.
+/// MANDATE: Fixed constant.
+pub const DEFAULT_BACKGROUND: Vec4 = Vec4::ONE;
+
+/// Common artboard sizes, in world units (1 unit = 1 pt = 1 px at 1x).
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtboardPreset {
+    /// iPhone 16 screen, 393 × 852 pt.
+    Iphone,
+    /// ISO A4 portrait, 210 × 297 mm.
+    A4,
+    /// US Letter portrait, 8.5 × 11 in.
+    Letter,
+    /// Full HD screen, 1920 × 1080 px.
+    Hd,
+}
+
+impl ArtboardPreset {
+    /// Every preset, in menu order.
+    /// MANDATE: Fixed table.
+    pub const ALL: [Self; 4] = [Self::Iphone, Self::A4, Self::Letter, Self::Hd];
+
+    /// Width and height in world units.
+    /// MANDATE: ≤60 SLOC.
+    pub fn size(self) -> Vec2 {
+        match self {
+            Self::Iphone => Vec2::new(393.0, 852.0),
+            Self::A4 => Vec2::new(595.28, 841.89),
+            Self::Letter => Vec2::new(612.0, 792.0),
+            Self::Hd => Vec2::new(1920.0, 1080.0),
+        }
+    }
+
+    /// Default name of an artboard made from the preset.
+    /// MANDATE: ≤60 SLOC.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Iphone => "iPhone",
+            Self::A4 => "A4",
+            Self::Letter => "Letter",
+            Self::Hd => "1920×1080",
+        }
+    }
+}
+
+/// Named world rectangle. Layers and shapes are not owned by artboards;
+/// they belong to one by being centred inside it (see `Document::artboard_contents`).
+/// MANDATE: Bounded data structure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artboard {
+    pub id: ArtboardId,
+    pub name: String,
+    /// Minimum (bottom-left) corner in world space.
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Linear RGBA drawn beneath the shapes.
+    #[serde(default = "default_background")]
+    pub background: Vec4,
+    /// Cut member shapes at the artboard edge when drawn and exported.
+    #[serde(default = "default_clip_content")]
+    pub clip_content: bool,
+}
+
+impl Artboard {
+    /// Create artboard with the default background, clipping its content.
+    /// MANDATE: ≤60 SLOC, validated inputs.
+    pub fn new(id: ArtboardId, name: String, position: Vec2, size: Vec2) -> Self {
+        // MANDATE: Input validation
+        assert!(!name.is_empty());
+        assert!(name.len() <= 256);
+        assert!(position.is_finite());
+        assert!(size.is_finite() && size.x > 0.0 && size.y > 0.0);
+
+        Self {
+            id,
+            name,
+            position,
+            size,
+            background: DEFAULT_BACKGROUND,
+            clip_content: true,
+        }
+    }
+
+    /// Maximum (top-right) corner in world space.
+    /// MANDATE: ≤60 SLOC.
+    pub fn max(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    /// Check point lies inside or on the edge.
+    /// MANDATE: ≤60 SLOC.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.cmpge(self.position).all() && point.cmple(self.max()).all()
+    }
+
+    /// World rectangle member shapes are cut to, if the artboard clips content.
+    /// MANDATE: ≤60 SLOC.
+    pub fn clip(&self) -> Option<(Vec2, Vec2)> {
+        self.clip_content.then(|| (self.position, self.max()))
+    }
+
+    /// Background quad as a GPU instance, mapped by view.
+    /// MANDATE: ≤60 SLOC.
+    pub fn to_instance_data(&self, view: Mat3) -> InstanceData {
+        let transform = view * Mat3::from_scale_angle_translation(self.size, 0.0, self.position);
+
+        // MANDATE: Assertions
+        assert!(transform.is_finite());
+
+        let [x, y, z] = [transform.x_axis, transform.y_axis, transform.z_axis];
+        InstanceData {
+            transform_0: [x.x, x.y, x.z, 0.0],
+            transform_1: [y.x, y.y, y.z, 0.0],
+            transform_2: [z.x, z.y, z.z, 0.0],
+            color: self.background.to_array(),
+            clip: InstanceData::NO_CLIP,
+        }
+    }
+}
+
+/// Topmost of `artboards` containing point; later artboards stack above earlier ones.
+/// MANDATE: ≤60 SLOC, bounded by the input length.
+pub fn topmost_at<'a>(
+    artboards: impl IntoIterator<Item = &'a Artboard>,
+    point: Vec2,
+) -> Option<&'a Artboard> {
+    artboards
+        .into_iter()
+        .filter(|artboard| artboard.contains_point(point))
+        .max_by_key(|artboard| artboard.id)
+}
+
+/// Serde default for `Artboard::background`.
+/// MANDATE: ≤60 SLOC.
+fn default_background() -> Vec4 {
+    DEFAULT_BACKGROUND
+}
+
+/// Serde default for `Artboard::clip_content`.
+/// MANDATE: ≤60 SLOC.
+fn default_clip_content() -> bool {
+    true
+}
+
+/// What lies inside an artboard.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ArtboardContents {
+    pub artboard: ArtboardId,
+    /// Layers with at least one shape, all of them members, in render order.
+    pub layers: Vec<LayerId>,
+    /// Shapes centred inside, sorted by ID.
+    pub shapes: Vec<ShapeId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artboard_containment() {
+        let board = Artboard::new(
+            1,
+            "Board".into(),
+            Vec2::new(10.0, 20.0),
+            Vec2::new(100.0, 50.0),
+        );
+        assert_eq!(board.max(), Vec2::new(110.0, 70.0));
+        assert!(board.contains_point(Vec2::new(10.0, 70.0)));
+        assert!(!board.contains_point(Vec2::new(9.9, 30.0)));
+    }
+
+    #[test]
+    fn test_presets_and_defaults() {
+        assert_eq!(ArtboardPreset::Hd.size(), Vec2::new(1920.0, 1080.0));
+        assert!(ArtboardPreset::ALL
+            .iter()
+            .all(|p| p.size().min_element() > 0.0 && !p.label().is_empty()));
+        let preset: ArtboardPreset = serde_json::from_str("\"a4\"").unwrap();
+        assert_eq!(preset, ArtboardPreset::A4);
+
+        // Older JSON without the optional fields gets a white, clipping artboard
+        let json = r#"{"id":3,"name":"A","position":[0,0],"size":[5,5]}"#;
+        let board: Artboard = serde_json::from_str(json).unwrap();
+        assert_eq!(board.background, DEFAULT_BACKGROUND);
+        assert!(board.clip_content);
+    }
+}
@@ -1,7 +1,9 @@
 // MANDATE: Batch operations for transforms
 #![deny(warnings)]
 
+use crate::document::{validate_affine, validate_placement, Document, DocumentError};
 use crate::layer::LayerId;
+use crate::shape::{transform_parts, ShapeId};
 use glam::Mat3;
 use serde::{Deserialize, Serialize};
 
@@ -9,19 +11,44 @@ use serde::{Deserialize, Serialize};
 /// MANDATE: Bounded allocation.
 const MAX_BATCH_SIZE: usize = 10_000;
 
+/// Object a transform update applies to, serialized as
+/// `"target": "Layer" | "Shape"` next to its `"id"`.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "target", content = "id")]
+pub enum TransformTarget {
+    /// Transform replaces the layer transform.
+    Layer(LayerId),
+    /// Transform maps the unit quad into layer space.
+    Shape(ShapeId),
+}
+
 /// Transform update operation.
 /// MANDATE: Bounded data structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransformUpdate {
-    pub id: LayerId,
+    #[serde(flatten)]
+    pub target: TransformTarget,
     pub transform: Mat3,
 }
 
 impl TransformUpdate {
-    /// Create new transform update.
+    /// Create new layer transform update.
     /// MANDATE: ≤60 SLOC.
     pub fn new(id: LayerId, transform: Mat3) -> Self {
-        Self { id, transform }
+        Self {
+            target: TransformTarget::Layer(id),
+            transform,
+        }
+    }
+
+    /// Create new shape transform update.
+    /// MANDATE: ≤60 SLOC.
+    pub fn for_shape(id: ShapeId, transform: Mat3) -> Self {
+        Self {
+            target: TransformTarget::Shape(id),
+            transform,
+        }
     }
 }
 
@@ -164,9 +191,261 @@ impl Default for OpacityBatch {
     }
 }
 
+/// Result of applying a batch.
+/// MANDATE: Deterministic, sorted output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// Layer IDs whose values changed.
+    pub layers: Vec<LayerId>,
+    /// Shape IDs whose values changed.
+    pub shapes: Vec<ShapeId>,
+}
+
+impl BatchReport {
+    /// Sort and deduplicate IDs.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    fn finish(mut self) -> Self {
+        self.layers.sort_unstable();
+        self.layers.dedup();
+        self.shapes.sort_unstable();
+        self.shapes.dedup();
+        self
+    }
+}
+
+/// Apply transform batch; all updates or none.
+/// MANDATE: ≤60 SLOC, validate everything before mutating.
+pub fn apply_transforms(
+    doc: &mut Document,
+    batch: &TransformBatch,
+) -> Result<BatchReport, DocumentError> {
+    // MANDATE: Bounded input (deserialized batches bypass `add`)
+    if batch.len() > MAX_BATCH_SIZE {
+        return Err(DocumentError::CapacityExceeded(
+            "Batch size exceeded".into(),
+        ));
+    }
+    for (i, update) in batch.updates().iter().enumerate() {
+        validate_transform(doc, update).map_err(|err| match err {
+            DocumentError::InvalidValue(msg) => {
+                DocumentError::InvalidValue(format!("updates[{}]: {}", i, msg))
+            }
+            other => other,
+        })?;
+    }
+
+    let mut report = BatchReport::default();
+    for update in batch.updates() {
+        apply_transform(doc, update, &mut report)?;
+    }
+
+    Ok(report.finish())
+}
+
+/// Apply opacity batch; all updates or none.
+/// MANDATE: ≤60 SLOC, validate everything before mutating.
+pub fn apply_opacities(
+    doc: &mut Document,
+    batch: &OpacityBatch,
+) -> Result<BatchReport, DocumentError> {
+    // MANDATE: Bounded input (deserialized batches bypass `add`)
+    if batch.len() > MAX_BATCH_SIZE {
+        return Err(DocumentError::CapacityExceeded(
+            "Batch size exceeded".into(),
+        ));
+    }
+    for (i, update) in batch.updates().iter().enumerate() {
+        if doc.layers().get_layer(update.id).is_none() {
+            return Err(DocumentError::LayerNotFound(update.id));
+        }
+        if !(0.0..=1.0).contains(&update.opacity) {
+            let msg = format!("updates[{}]: opacity out of range", i);
+            return Err(DocumentError::InvalidValue(msg));
+        }
+    }
+
+    let mut report = BatchReport::default();
+    for update in batch.updates() {
+        if doc.layers().get_layer(update.id).map(|l| l.opacity) != Some(update.opacity) {
+            doc.set_layer_opacity(update.id, update.opacity)?;
+            report.layers.push(update.id);
+        }
+    }
+
+    Ok(report.finish())
+}
+
+/// Apply single pre-validated transform update.
+/// MANDATE: ≤60 SLOC, unchanged values are not reported.
+fn apply_transform(
+    doc: &mut Document,
+    update: &TransformUpdate,
+    report: &mut BatchReport,
+) -> Result<(), DocumentError> {
+    match update.target {
+        TransformTarget::Layer(id) => {
+            let current = doc.layers().get_layer(id).map(|l| l.transform);
+            if current != Some(update.transform) {
+                doc.set_layer_transform(id, update.transform)?;
+                report.layers.push(id);
+            }
+        }
+        TransformTarget::Shape(id) => {
+            let current = doc.shape(id).map(|s| s.local_transform());
+            if current != Some(update.transform) {
+                doc.set_shape_transform(id, update.transform)?;
+                report.shapes.push(id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate single transform update against the document.
+/// MANDATE: ≤60 SLOC, no mutation.
+fn validate_transform(doc: &Document, update: &TransformUpdate) -> Result<(), DocumentError> {
+    match update.target {
+        TransformTarget::Layer(id) => {
+            if doc.layers().get_layer(id).is_none() {
+                return Err(DocumentError::LayerNotFound(id));
+            }
+            validate_affine(update.transform)
+        }
+        TransformTarget::Shape(id) => {
+            let shape = doc.shape(id).ok_or(DocumentError::ShapeNotFound(id))?;
+            let placement = transform_parts(update.transform, shape.pivot)
+                .map_err(DocumentError::InvalidValue)?;
+            validate_placement(&placement)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glam::Vec2;
+
+    fn doc_with_shapes(count: usize) -> (Document, LayerId, Vec<ShapeId>) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".to_string()).unwrap();
+        let shapes = (0..count)
+            .map(|i| {
+                let position = Vec2::new(i as f32 * 20.0, 0.0);
+                doc.add_shape(layer, position, Vec2::new(10.0, 10.0))
+                    .unwrap()
+            })
+            .collect();
+        (doc, layer, shapes)
+    }
+
+    #[test]
+    fn test_apply_shape_transforms() {
+        let (mut doc, _, shapes) = doc_with_shapes(3);
+        let mut batch = TransformBatch::new();
+        for id in &shapes {
+            let local = doc.shape(*id).unwrap().local_transform();
+            let moved = Mat3::from_translation(Vec2::new(0.0, 100.0)) * local;
+            batch.add(TransformUpdate::for_shape(*id, moved)).unwrap();
+        }
+
+        let report = apply_transforms(&mut doc, &batch).unwrap();
+
+        assert_eq!(report.shapes, shapes);
+        assert!(doc.query_point(Vec2::new(5.0, 5.0)).is_empty());
+        assert_eq!(doc.query_point(Vec2::new(5.0, 105.0)), vec![shapes[0]]);
+    }
+
+    #[test]
+    fn test_apply_rotated_shape_transform() {
+        let (mut doc, _, shapes) = doc_with_shapes(1);
+        let local = doc.shape(shapes[0]).unwrap().local_transform();
+        let spin = Mat3::from_translation(Vec2::splat(5.0))
+            * Mat3::from_angle(0.5)
+            * Mat3::from_translation(Vec2::splat(-5.0));
+        let mut batch = TransformBatch::new();
+        batch
+            .add(TransformUpdate::for_shape(shapes[0], spin * local))
+            .unwrap();
+
+        apply_transforms(&mut doc, &batch).unwrap();
+
+        let shape = doc.shape(shapes[0]).unwrap();
+        assert!((shape.rotation - 0.5).abs() < 1e-5);
+        assert!(shape.position.abs_diff_eq(Vec2::ZERO, 1e-4));
+    }
+
+    #[test]
+    fn test_apply_transforms_all_or_nothing() {
+        let (mut doc, _, shapes) = doc_with_shapes(2);
+        let mut batch = TransformBatch::new();
+        let moved = Mat3::from_scale_angle_translation(Vec2::ONE, 0.0, Vec2::splat(500.0));
+        batch
+            .add(TransformUpdate::for_shape(shapes[0], moved))
+            .unwrap();
+        batch.add(TransformUpdate::for_shape(999, moved)).unwrap();
+
+        let result = apply_transforms(&mut doc, &batch);
+
+        assert_eq!(result, Err(DocumentError::ShapeNotFound(999)));
+        assert_eq!(doc.shape(shapes[0]).unwrap().position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_apply_layer_transform_reindexes() {
+        let (mut doc, layer, shapes) = doc_with_shapes(1);
+        let mut batch = TransformBatch::new();
+        let offset = Mat3::from_translation(Vec2::new(1000.0, 0.0));
+        batch.add(TransformUpdate::new(layer, offset)).unwrap();
+
+        let report = apply_transforms(&mut doc, &batch).unwrap();
+
+        assert_eq!(report.layers, vec![layer]);
+        assert_eq!(doc.query_point(Vec2::new(1005.0, 5.0)), shapes);
+        assert_eq!(
+            apply_transforms(&mut doc, &batch).unwrap(),
+            BatchReport::default()
+        );
+    }
+
+    #[test]
+    fn test_apply_transforms_rejects_degenerate() {
+        let (mut doc, layer, _) = doc_with_shapes(1);
+        let mut batch = TransformBatch::new();
+        batch.add(TransformUpdate::new(layer, Mat3::ZERO)).unwrap();
+
+        let result = apply_transforms(&mut doc, &batch);
+        assert!(
+            matches!(result, Err(DocumentError::InvalidValue(msg)) if msg.starts_with("updates[0]"))
+        );
+    }
+
+    #[test]
+    fn test_apply_opacities() {
+        let (mut doc, layer, _) = doc_with_shapes(0);
+        let other = doc.add_layer("Other".to_string()).unwrap();
+        let mut batch = OpacityBatch::new();
+        batch.add(OpacityUpdate::new(layer, 0.5).unwrap()).unwrap();
+        batch.add(OpacityUpdate::new(other, 1.0).unwrap()).unwrap();
+
+        let report = apply_opacities(&mut doc, &batch).unwrap();
+
+        assert_eq!(report.layers, vec![layer]);
+        assert_eq!(doc.layers().get_layer(layer).unwrap().opacity, 0.5);
+    }
+
+    #[test]
+    fn test_apply_opacities_missing_layer() {
+        let (mut doc, layer, _) = doc_with_shapes(0);
+        let mut batch = OpacityBatch::new();
+        batch.add(OpacityUpdate::new(layer, 0.5).unwrap()).unwrap();
+        batch.add(OpacityUpdate::new(77, 0.5).unwrap()).unwrap();
+
+        assert_eq!(
+            apply_opacities(&mut doc, &batch),
+            Err(DocumentError::LayerNotFound(77))
+        );
+        assert_eq!(doc.layers().get_layer(layer).unwrap().opacity, 1.0);
+    }
 
     #[test]
     fn test_transform_batch() {
@@ -213,8 +492,18 @@ mod tests {
 
         let updates = batch.updates();
         assert_eq!(updates.len(), 2);
-        assert_eq!(updates[0].id, 1);
-        assert_eq!(updates[1].id, 2);
+        assert_eq!(updates[0].target, TransformTarget::Layer(1));
+        assert_eq!(updates[1].target, TransformTarget::Layer(2));
+    }
+
+    #[test]
+    fn test_transform_update_wire_format() {
+        let update = TransformUpdate::for_shape(7, Mat3::IDENTITY);
+        let json = serde_json::to_value(&update).unwrap();
+        assert_eq!((&json["target"], &json["id"]), (&"Shape".into(), &7.into()));
+
+        let back: TransformUpdate = serde_json::from_value(json).unwrap();
+        assert_eq!(back.target, TransformTarget::Shape(7));
     }
 
     #[test]
@@ -0,0 +1,139 @@
+// MANDATE: Content-addressed binary blobs (image pixel data)
+#![deny(warnings)]
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Blob ID: lowercase hex SHA-256 of the blob bytes.
+/// MANDATE: Type alias for clarity.
+pub type BlobId = String;
+
+/// Maximum size of a single blob.
+/// MANDATE: Bounded allocation.
+pub const MAX_BLOB_BYTES: usize = 64 * 1024 * 1024;
+
+/// Maximum number of blobs per document.
+/// MANDATE: Bounded allocation.
+const MAX_BLOBS: usize = 10_000;
+
+/// Deduplicating store of binary blobs keyed by content hash.
+/// MANDATE: Bounded collection.
+#[derive(Debug, Clone, Default)]
+pub struct BlobStore {
+    blobs: HashMap<BlobId, Vec<u8>>,
+}
+
+impl BlobStore {
+    /// Create empty store.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self {
+            blobs: HashMap::new(),
+        }
+    }
+
+    /// Insert blob, returning its ID; identical bytes are stored once.
+    /// MANDATE: ≤60 SLOC, bounded size and count.
+    pub fn insert(&mut self, bytes: Vec<u8>) -> Result<BlobId, String> {
+        if bytes.is_empty() || bytes.len() > MAX_BLOB_BYTES {
+            return Err(format!("Blob size {} out of range", bytes.len()));
+        }
+
+        let id = hash_blob(&bytes);
+        if self.blobs.contains_key(&id) {
+            return Ok(id);
+        }
+        if self.blobs.len() >= MAX_BLOBS {
+            return Err("Max blobs reached".to_string());
+        }
+
+        self.blobs.insert(id.clone(), bytes);
+
+        // MANDATE: Assertions
+        assert!(self.blobs.len() <= MAX_BLOBS);
+        assert!(is_blob_id(&id));
+
+        Ok(id)
+    }
+
+    /// Get blob bytes.
+    /// MANDATE: ≤60 SLOC.
+    pub fn get(&self, id: &str) -> Option<&[u8]> {
+        self.blobs.get(id).map(Vec::as_slice)
+    }
+
+    /// Check blob presence.
+    /// MANDATE: ≤60 SLOC.
+    pub fn contains(&self, id: &str) -> bool {
+        self.blobs.contains_key(id)
+    }
+
+    /// Count blobs.
+    /// MANDATE: ≤60 SLOC.
+    pub fn len(&self) -> usize {
+        self.blobs.len()
+    }
+
+    /// Check if empty.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+}
+
+/// Hash blob bytes to an ID.
+/// MANDATE: ≤60 SLOC, deterministic.
+pub fn hash_blob(bytes: &[u8]) -> BlobId {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Format digest as lowercase hex.
+/// MANDATE: ≤60 SLOC.
+pub fn to_hex(digest: &[u8]) -> BlobId {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Check that string is a well-formed blob ID.
+/// MANDATE: ≤60 SLOC, guards archive paths built from IDs.
+pub fn is_blob_id(id: &str) -> bool {
+    id.len() == 64
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_deduplicates() {
+        let mut store = BlobStore::new();
+        let a = store.insert(vec![1, 2, 3]).unwrap();
+        let b = store.insert(vec![1, 2, 3]).unwrap();
+        let c = store.insert(vec![4, 5, 6]).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&a), Some(&[1u8, 2, 3][..]));
+    }
+
+    #[test]
+    fn test_hash_is_sha256_hex() {
+        let id = hash_blob(b"abc");
+        assert_eq!(
+            id,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(is_blob_id(&id));
+        assert!(!is_blob_id("../document.json"));
+    }
+
+    #[test]
+    fn test_insert_rejects_empty() {
+        let mut store = BlobStore::new();
+        assert!(store.insert(Vec::new()).is_err());
+        assert!(store.is_empty());
+    }
+}
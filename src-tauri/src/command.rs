@@ -0,0 +1,434 @@
+// MANDATE: Invertible scene commands for undo/redo
+#![deny(warnings)]
+
+use crate::artboard::Artboard;
+use crate::document::{Document, DocumentError};
+use crate::layer::Layer;
+use crate::shape::Shape;
+
+/// Maximum commands in one step.
+/// MANDATE: Bounded collection.
+const MAX_STEP_COMMANDS: usize = 200_000;
+
+/// Invertible scene operation holding full before/after snapshots.
+/// MANDATE: inverse(inverse(c)) == c for every command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    InsertShape(Shape),
+    DeleteShape(Shape),
+    InsertLayer { layer: Layer, shapes: Vec<Shape> },
+    DeleteLayer { layer: Layer, shapes: Vec<Shape> },
+    UpdateShape { before: Shape, after: Shape },
+    UpdateLayer { before: Layer, after: Layer },
+    InsertArtboard(Artboard),
+    DeleteArtboard(Artboard),
+    UpdateArtboard { before: Artboard, after: Artboard },
+}
+
+impl Command {
+    /// Capture layer for deletion together with the shapes on it.
+    /// MANDATE: ≤60 SLOC.
+    pub fn delete_layer(layer: &Layer, shapes: Vec<Shape>) -> Self {
+        // MANDATE: Assertions
+        assert!(shapes.iter().all(|shape| shape.layer_id == layer.id));
+
+        Self::DeleteLayer {
+            layer: layer.clone(),
+            shapes,
+        }
+    }
+
+    /// Apply command to document.
+    /// MANDATE: ≤60 SLOC.
+    pub fn apply(&self, doc: &mut Document) -> Result<(), DocumentError> {
+        match self {
+            Self::InsertShape(shape) => doc.insert_shape(shape.clone()),
+            Self::DeleteShape(shape) => doc.remove_shape(shape.id).map(|_| ()),
+            Self::InsertLayer { layer, shapes } => {
+                doc.insert_layer(layer.clone())?;
+                for shape in shapes {
+                    if let Err(err) = doc.insert_shape(shape.clone()) {
+                        doc.remove_layer(layer.id)?;
+                        return Err(err);
+                    }
+                }
+                Ok(())
+            }
+            Self::DeleteLayer { layer, .. } => doc.remove_layer(layer.id).map(|_| ()),
+            Self::UpdateShape { after, .. } => doc.replace_shape(after.clone()),
+            Self::UpdateLayer { after, .. } => doc.replace_layer(after.clone()),
+            Self::InsertArtboard(artboard) => doc.insert_artboard(artboard.clone()),
+            Self::DeleteArtboard(artboard) => doc.remove_artboard(artboard.id).map(|_| ()),
+            Self::UpdateArtboard { after, .. } => doc.replace_artboard(after.clone()),
+        }
+    }
+
+    /// Command undoing this one.
+    /// MANDATE: ≤60 SLOC.
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::InsertShape(shape) => Self::DeleteShape(shape.clone()),
+            Self::DeleteShape(shape) => Self::InsertShape(shape.clone()),
+            Self::InsertLayer { layer, shapes } => Self::DeleteLayer {
+                layer: layer.clone(),
+                shapes: shapes.clone(),
+            },
+            Self::DeleteLayer { layer, shapes } => Self::InsertLayer {
+                layer: layer.clone(),
+                shapes: shapes.clone(),
+            },
+            Self::UpdateShape { before, after } => Self::UpdateShape {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Self::UpdateLayer { before, after } => Self::UpdateLayer {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Self::InsertArtboard(artboard) => Self::DeleteArtboard(artboard.clone()),
+            Self::DeleteArtboard(artboard) => Self::InsertArtboard(artboard.clone()),
+            Self::UpdateArtboard { before, after } => Self::UpdateArtboard {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+
+    /// Fold next into this command when it continues the same target,
+    /// keeping the original before state; otherwise hand next back.
+    /// MANDATE: ≤60 SLOC, only updates coalesce.
+    pub fn merge(&mut self, next: Command) -> Option<Command> {
+        match (self, next) {
+            (
+                Self::UpdateShape { after, .. },
+                Self::UpdateShape {
+                    before,
+                    after: next,
+                },
+            ) if after.id == before.id => *after = next,
+            (
+                Self::UpdateLayer { after, .. },
+                Self::UpdateLayer {
+                    before,
+                    after: next,
+                },
+            ) if after.id == before.id => *after = next,
+            (
+                Self::UpdateArtboard { after, .. },
+                Self::UpdateArtboard {
+                    before,
+                    after: next,
+                },
+            ) if after.id == before.id => *after = next,
+            (_, next) => return Some(next),
+        }
+        None
+    }
+
+    /// Check if command changes nothing.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Self::UpdateShape { before, after } => before == after,
+            Self::UpdateLayer { before, after } => before == after,
+            Self::UpdateArtboard { before, after } => before == after,
+            _ => false,
+        }
+    }
+
+    /// Number of layer, shape and artboard snapshots held, for memory bounds.
+    /// MANDATE: ≤60 SLOC.
+    pub fn cost(&self) -> usize {
+        match self {
+            Self::InsertShape(_) | Self::DeleteShape(_) => 1,
+            Self::InsertArtboard(_) | Self::DeleteArtboard(_) => 1,
+            Self::InsertLayer { shapes, .. } | Self::DeleteLayer { shapes, .. } => 1 + shapes.len(),
+            Self::UpdateShape { .. } | Self::UpdateLayer { .. } | Self::UpdateArtboard { .. } => 2,
+        }
+    }
+}
+
+/// One undo step: commands applied in order, undone in reverse.
+/// MANDATE: Flat list, so no operation recurses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Step {
+    commands: Vec<Command>,
+}
+
+impl Step {
+    /// Create empty step.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Step of updates for snapshots that differ from the document.
+    /// MANDATE: ≤60 SLOC, unchanged and missing targets skipped.
+    pub fn updates(doc: &Document, layers: Vec<Layer>, shapes: Vec<Shape>) -> Self {
+        let mut commands = Vec::with_capacity(layers.len() + shapes.len());
+
+        for before in layers {
+            if let Some(after) = doc.layers().get_layer(before.id) {
+                if *after != before {
+                    let after = after.clone();
+                    commands.push(Command::UpdateLayer { before, after });
+                }
+            }
+        }
+        for before in shapes {
+            if let Some(after) = doc.shape(before.id) {
+                if *after != before {
+                    let after = after.clone();
+                    commands.push(Command::UpdateShape { before, after });
+                }
+            }
+        }
+
+        Self { commands }
+    }
+
+    /// Append the commands of another step.
+    /// MANDATE: ≤60 SLOC, unchanged when the result would exceed the bound.
+    pub fn extend(&mut self, other: Step) -> Result<(), DocumentError> {
+        check_step_len(self.commands.len() + other.commands.len())?;
+        self.commands.extend(other.commands);
+        Ok(())
+    }
+
+    /// Get commands in application order.
+    /// MANDATE: ≤60 SLOC.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Apply commands in order, undoing the applied prefix on failure.
+    /// If undoing fails too, the document is left partly changed and
+    /// `RollbackFailed` is returned.
+    /// MANDATE: ≤60 SLOC, all-or-nothing unless the rollback fails.
+    pub fn apply(&self, doc: &mut Document) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        check_step_len(self.commands.len())?;
+
+        for (i, command) in self.commands.iter().enumerate() {
+            if let Err(err) = command.apply(doc) {
+                for done in self.commands[..i].iter().rev() {
+                    if let Err(undo_err) = done.inverse().apply(doc) {
+                        return Err(DocumentError::RollbackFailed(format!(
+                            "{} after {}",
+                            undo_err, err
+                        )));
+                    }
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Step undoing this one.
+    /// MANDATE: ≤60 SLOC.
+    pub fn inverse(&self) -> Self {
+        Self {
+            commands: self.commands.iter().rev().map(Command::inverse).collect(),
+        }
+    }
+
+    /// Fold next into this step when it continues every command in order,
+    /// keeping the original before states; otherwise hand next back unchanged.
+    /// MANDATE: ≤60 SLOC, all-or-nothing.
+    pub fn merge(&mut self, next: Step) -> Result<(), Step> {
+        if self.commands.is_empty() || self.commands.len() != next.commands.len() {
+            return Err(next);
+        }
+
+        // Merge into a copy so a mismatch part-way leaves both steps intact
+        let mut merged = self.commands.clone();
+        for (ours, theirs) in merged.iter_mut().zip(&next.commands) {
+            if ours.merge(theirs.clone()).is_some() {
+                return Err(next);
+            }
+        }
+        self.commands = merged;
+        Ok(())
+    }
+
+    /// Check if step changes nothing.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_noop(&self) -> bool {
+        self.commands.iter().all(Command::is_noop)
+    }
+
+    /// Number of snapshots held.
+    /// MANDATE: ≤60 SLOC.
+    pub fn cost(&self) -> usize {
+        self.commands.iter().map(Command::cost).sum()
+    }
+}
+
+/// Check a step of `len` commands fits the bound.
+/// MANDATE: ≤60 SLOC.
+pub fn check_step_len(len: usize) -> Result<(), DocumentError> {
+    if len > MAX_STEP_COMMANDS {
+        return Err(DocumentError::CapacityExceeded("step too large".into()));
+    }
+    Ok(())
+}
+
+impl From<Command> for Step {
+    fn from(command: Command) -> Self {
+        Self {
+            commands: vec![command],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_format::to_json;
+    use glam::Vec2;
+
+    fn setup() -> (Document, Shape) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer 1".to_string()).unwrap();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        let shape = doc.shape(id).unwrap().clone();
+        (doc, shape)
+    }
+
+    fn moved(shape: &Shape, x: f32) -> Shape {
+        let mut after = shape.clone();
+        after.position = Vec2::new(x, 0.0);
+        after
+    }
+
+    #[test]
+    fn test_inverse_round_trips() {
+        let (doc, shape) = setup();
+        let layer = doc.layers().get_layer(shape.layer_id).unwrap();
+        let mut step = Step::from(Command::delete_layer(layer, vec![shape.clone()]));
+        step.extend(Step::from(Command::InsertShape(shape)))
+            .unwrap();
+
+        assert_eq!(step.inverse().inverse(), step);
+        assert_eq!(step.inverse().commands()[0], step.commands()[1].inverse());
+    }
+
+    #[test]
+    fn test_delete_layer_undo_restores() {
+        let (mut doc, shape) = setup();
+        let before = to_json(&doc).unwrap();
+        let layer = doc.layers().get_layer(shape.layer_id).unwrap().clone();
+        let command = Command::delete_layer(&layer, vec![shape.clone()]);
+
+        command.apply(&mut doc).unwrap();
+        assert_eq!(doc.shape_count(), 0);
+        command.inverse().apply(&mut doc).unwrap();
+
+        assert_eq!(to_json(&doc).unwrap(), before);
+        assert_eq!(doc.query_point(Vec2::splat(0.5)), vec![shape.id]);
+    }
+
+    #[test]
+    fn test_updates_skip_unchanged() {
+        let (mut doc, shape) = setup();
+        let layer = doc.layers().get_layer(shape.layer_id).unwrap().clone();
+
+        doc.move_shape(shape.id, Vec2::splat(3.0)).unwrap();
+        let step = Step::updates(&doc, vec![layer], vec![shape.clone()]);
+
+        assert_eq!(step.cost(), 2);
+        assert_eq!(step.commands().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_keeps_first_before() {
+        let (_, a) = setup();
+        let (b, c) = (moved(&a, 1.0), moved(&a, 2.0));
+
+        let mut first = Step::from(Command::UpdateShape {
+            before: a.clone(),
+            after: b.clone(),
+        });
+        let second = Step::from(Command::UpdateShape {
+            before: b,
+            after: c.clone(),
+        });
+        first.merge(second).unwrap();
+
+        let merged = Command::UpdateShape {
+            before: a,
+            after: c,
+        };
+        assert_eq!(first, Step::from(merged));
+        assert_eq!(first.merge(Step::new()), Err(Step::new()));
+    }
+
+    #[test]
+    fn test_merge_mismatch_changes_nothing() {
+        let (_, a) = setup();
+        let mut other = a.clone();
+        other.id += 1;
+        let update = |before: &Shape, x| Command::UpdateShape {
+            before: before.clone(),
+            after: moved(before, x),
+        };
+
+        let mut first = Step::from(update(&a, 1.0));
+        first.extend(update(&other, 1.0).into()).unwrap();
+        let original = first.clone();
+        let mut second = Step::from(update(&a, 2.0));
+        second.extend(Command::InsertShape(other).into()).unwrap();
+
+        assert_eq!(first.merge(second.clone()), Err(second));
+        assert_eq!(first, original);
+    }
+
+    #[test]
+    fn test_extend_rejects_oversized_step() {
+        let (_, shape) = setup();
+        let mut step = Step {
+            commands: vec![Command::InsertShape(shape.clone()); MAX_STEP_COMMANDS],
+        };
+        let result = step.extend(Command::DeleteShape(shape).into());
+        assert!(matches!(result, Err(DocumentError::CapacityExceeded(_))));
+        assert_eq!(step.commands().len(), MAX_STEP_COMMANDS);
+    }
+
+    #[test]
+    fn test_failed_step_rolls_back() {
+        let (mut doc, shape) = setup();
+        let before = to_json(&doc).unwrap();
+
+        let mut step = Step::from(Command::UpdateShape {
+            before: shape.clone(),
+            after: moved(&shape, 5.0),
+        });
+        step.extend(Step::from(Command::InsertShape(shape)))
+            .unwrap();
+
+        assert!(step.apply(&mut doc).is_err());
+        assert_eq!(to_json(&doc).unwrap(), before);
+    }
+
+    #[test]
+    fn test_failed_rollback_is_reported() {
+        let (mut doc, shape) = setup();
+        let mut orphan = shape.clone();
+        orphan.layer_id = 99;
+
+        // Undoing the update would move the shape onto a missing layer
+        let mut step = Step::from(Command::UpdateShape {
+            before: orphan,
+            after: moved(&shape, 5.0),
+        });
+        step.extend(Step::from(Command::InsertShape(shape.clone())))
+            .unwrap();
+
+        assert!(matches!(
+            step.apply(&mut doc),
+            Err(DocumentError::RollbackFailed(_))
+        ));
+        assert_eq!(doc.shape(shape.id).unwrap().position, Vec2::new(5.0, 0.0));
+    }
+}
@@ -1,5 +1,119 @@
 // MANDATE: Tauri commands, <60 SLOC per function
 
+use crate::artboard::{Artboard, ArtboardContents, ArtboardId};
+use crate::batch_ops::{BatchReport, OpacityBatch, TransformBatch};
+use crate::document::Document;
+use crate::export::jpeg::JpegOptions;
+use crate::export::pdf::PdfOptions;
+use crate::export::preset::{ExportPreset, PresetOutput};
+use crate::export::raster::RasterOptions;
+use crate::export::svg::SvgOptions;
+use crate::export::webp::WebpOptions;
+use crate::export::ExportArea;
+use crate::handlers::{
+    self, AddArtboardRequest, AddShapeRequest, ArtboardPresetInfo, CommandError, HitTestResponse,
+    MarqueeRequest, QueryRectRequest, RemoveLayerResponse, SelectRequest, UpdateArtboardRequest,
+    UpdateShapeRequest,
+};
+use crate::history::{History, HistoryStatus};
+use crate::import::ImportReport;
+use crate::layer::{Layer, LayerId};
+use crate::migration::MigrationReport;
+use crate::selection::{Selection, SelectionState};
+use crate::shape::{Shape, ShapeId};
+use crate::transform::{Handle, Modifiers, TransformSession};
+use glam::Vec2;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event emitted with a `SelectionState` payload whenever the selection changes.
+pub const SELECTION_EVENT: &str = "selection-changed";
+
+/// Application state managed by Tauri.
+/// MANDATE: Single writer via mutex; locks taken as document, history, selection, transform.
+#[derive(Default)]
+pub struct AppState {
+    document: Mutex<Document>,
+    history: Mutex<History>,
+    selection: Mutex<Selection>,
+    transform: Mutex<Option<TransformSession>>,
+}
+
+/// Locked document and history.
+pub type Session<'a> = (MutexGuard<'a, Document>, MutexGuard<'a, History>);
+
+impl AppState {
+    /// Lock the document.
+    /// MANDATE: ≤60 SLOC, poisoned lock reported not panicked.
+    pub fn document(&self) -> Result<MutexGuard<'_, Document>, CommandError> {
+        self.document
+            .lock()
+            .map_err(|_| CommandError::StateUnavailable)
+    }
+
+    /// Lock document then history, for edits that record undo steps.
+    /// MANDATE: ≤60 SLOC, fixed lock order.
+    pub fn session(&self) -> Result<Session<'_>, CommandError> {
+        let document = self.document()?;
+        let history = self
+            .history
+            .lock()
+            .map_err(|_| CommandError::StateUnavailable)?;
+        Ok((document, history))
+    }
+
+    /// Lock the selection; callers already holding the document lock it first.
+    /// MANDATE: ≤60 SLOC, poisoned lock reported not panicked.
+    pub fn selection(&self) -> Result<MutexGuard<'_, Selection>, CommandError> {
+        self.selection
+            .lock()
+            .map_err(|_| CommandError::StateUnavailable)
+    }
+
+    /// Lock the active transform session slot.
+    /// MANDATE: ≤60 SLOC, poisoned lock reported not panicked.
+    pub fn transform(&self) -> Result<MutexGuard<'_, Option<TransformSession>>, CommandError> {
+        self.transform
+            .lock()
+            .map_err(|_| CommandError::StateUnavailable)
+    }
+
+    /// Clone the document and selection, so slow work such as GPU export
+    /// runs without holding the locks.
+    /// MANDATE: ≤60 SLOC, fixed lock order, locks released on return.
+    pub fn snapshot(&self) -> Result<(Document, Selection), CommandError> {
+        let doc = self.document()?;
+        let selection = self.selection()?;
+        Ok((doc.clone(), selection.clone()))
+    }
+
+    /// Drop the active transform session, whose start states belong to the
+    /// scene before an undo, redo or open.
+    /// MANDATE: ≤60 SLOC.
+    fn cancel_transform(&self) -> Result<(), CommandError> {
+        self.transform()?.take();
+        Ok(())
+    }
+
+    /// Drop deleted objects from the selection, notifying the frontend on change.
+    /// MANDATE: ≤60 SLOC.
+    fn prune_selection(&self, app: &AppHandle, doc: &Document) -> Result<(), CommandError> {
+        let mut selection = self.selection()?;
+        if selection.prune(doc) {
+            notify_selection(app, doc, &selection);
+        }
+        Ok(())
+    }
+}
+
+/// Emit selection change event.
+/// MANDATE: ≤60 SLOC, best effort: the frontend can re-query with get_selection.
+fn notify_selection(app: &AppHandle, doc: &Document, selection: &Selection) {
+    // The edit already succeeded, so a lost event is dropped rather than
+    // failing the command
+    let _ = app.emit(SELECTION_EVENT, selection.state(doc));
+}
+
 /// Get application version.
 #[tauri::command]
 pub fn get_version() -> String {
@@ -8,15 +122,467 @@ pub fn get_version() -> String {
 
 /// Initialize canvas dimensions.
 #[tauri::command]
-pub fn init_canvas(width: u32, height: u32) -> Result<String, String> {
+pub fn init_canvas(width: u32, height: u32) -> Result<String, CommandError> {
     // MANDATE: Input validation
     if width == 0 || height == 0 {
-        return Err("Invalid dimensions".into());
+        return Err(CommandError::InvalidInput("Invalid dimensions".into()));
     }
     if width > 4096 || height > 4096 {
-        return Err("Dimensions too large".into());
+        return Err(CommandError::InvalidInput("Dimensions too large".into()));
     }
-    
+
     println!("Canvas initialized: {}x{}", width, height);
     Ok(format!("{}x{}", width, height))
-}
\ No newline at end of file
+}
+
+/// Add shape to layer.
+#[tauri::command]
+pub fn add_shape(
+    state: State<'_, AppState>,
+    request: AddShapeRequest,
+) -> Result<Shape, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::add_shape(&mut doc, &mut history, request)
+}
+
+/// Remove shape.
+#[tauri::command]
+pub fn remove_shape(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: ShapeId,
+) -> Result<Shape, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let shape = handlers::remove_shape(&mut doc, &mut history, id)?;
+    state.prune_selection(&app, &doc)?;
+    Ok(shape)
+}
+
+/// Update shape fields.
+#[tauri::command]
+pub fn update_shape(
+    state: State<'_, AppState>,
+    request: UpdateShapeRequest,
+) -> Result<Shape, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::update_shape(&mut doc, &mut history, request)
+}
+
+/// Add layer.
+#[tauri::command]
+pub fn add_layer(state: State<'_, AppState>, name: String) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::add_layer(&mut doc, &mut history, name)
+}
+
+/// Remove layer and its shapes.
+#[tauri::command]
+pub fn remove_layer(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: LayerId,
+) -> Result<RemoveLayerResponse, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let response = handlers::remove_layer(&mut doc, &mut history, id)?;
+    state.prune_selection(&app, &doc)?;
+    Ok(response)
+}
+
+/// Add empty group.
+#[tauri::command]
+pub fn add_group(state: State<'_, AppState>, name: String) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::add_group(&mut doc, &mut history, name)
+}
+
+/// Move layer into group or to top level.
+#[tauri::command]
+pub fn move_layer(
+    state: State<'_, AppState>,
+    id: LayerId,
+    parent: Option<LayerId>,
+    index: Option<usize>,
+) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::move_layer(&mut doc, &mut history, id, parent, index)
+}
+
+/// Dissolve group, keeping its children.
+#[tauri::command]
+pub fn ungroup_layer(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: LayerId,
+) -> Result<Vec<Layer>, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let children = handlers::ungroup_layer(&mut doc, &mut history, id)?;
+    state.prune_selection(&app, &doc)?;
+    Ok(children)
+}
+
+/// Reorder layer.
+#[tauri::command]
+pub fn reorder_layer(
+    state: State<'_, AppState>,
+    id: LayerId,
+    z_order: i32,
+) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::reorder_layer(&mut doc, &mut history, id, z_order)
+}
+
+/// Set layer visibility.
+#[tauri::command]
+pub fn set_layer_visibility(
+    state: State<'_, AppState>,
+    id: LayerId,
+    visible: bool,
+) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::set_visibility(&mut doc, &mut history, id, visible)
+}
+
+/// Set layer opacity.
+#[tauri::command]
+pub fn set_layer_opacity(
+    state: State<'_, AppState>,
+    id: LayerId,
+    opacity: f32,
+) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::set_opacity(&mut doc, &mut history, id, opacity)
+}
+
+/// Lock or unlock layer.
+#[tauri::command]
+pub fn set_layer_locked(
+    state: State<'_, AppState>,
+    id: LayerId,
+    locked: bool,
+) -> Result<Layer, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::set_locked(&mut doc, &mut history, id, locked)
+}
+
+/// Hit-test world point.
+#[tauri::command]
+pub fn hit_test(state: State<'_, AppState>, point: Vec2) -> Result<HitTestResponse, CommandError> {
+    handlers::hit_test(&*state.document()?, point)
+}
+
+/// Query shapes in world rectangle.
+#[tauri::command]
+pub fn query_rect(
+    state: State<'_, AppState>,
+    request: QueryRectRequest,
+) -> Result<Vec<ShapeId>, CommandError> {
+    handlers::query_rect(&*state.document()?, request)
+}
+
+/// Add artboard of a given size or from a preset.
+#[tauri::command]
+pub fn add_artboard(
+    state: State<'_, AppState>,
+    request: AddArtboardRequest,
+) -> Result<Artboard, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::add_artboard(&mut doc, &mut history, request)
+}
+
+/// Update artboard fields.
+#[tauri::command]
+pub fn update_artboard(
+    state: State<'_, AppState>,
+    request: UpdateArtboardRequest,
+) -> Result<Artboard, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::update_artboard(&mut doc, &mut history, request)
+}
+
+/// Remove artboard, keeping its contents.
+#[tauri::command]
+pub fn remove_artboard(
+    state: State<'_, AppState>,
+    id: ArtboardId,
+) -> Result<Artboard, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::remove_artboard(&mut doc, &mut history, id)
+}
+
+/// List artboards in stacking order.
+#[tauri::command]
+pub fn list_artboards(state: State<'_, AppState>) -> Result<Vec<Artboard>, CommandError> {
+    Ok(handlers::list_artboards(&*state.document()?))
+}
+
+/// Artboard presets, for the new-artboard menu.
+#[tauri::command]
+pub fn list_artboard_presets() -> Vec<ArtboardPresetInfo> {
+    handlers::list_artboard_presets()
+}
+
+/// Topmost artboard under world point.
+#[tauri::command]
+pub fn artboard_at(
+    state: State<'_, AppState>,
+    point: Vec2,
+) -> Result<Option<ArtboardId>, CommandError> {
+    handlers::artboard_at(&*state.document()?, point)
+}
+
+/// Layers and shapes inside artboard.
+#[tauri::command]
+pub fn artboard_contents(
+    state: State<'_, AppState>,
+    id: ArtboardId,
+) -> Result<ArtboardContents, CommandError> {
+    handlers::artboard_contents(&*state.document()?, id)
+}
+
+/// Select shapes with a marquee rectangle.
+#[tauri::command]
+pub fn marquee_select(
+    state: State<'_, AppState>,
+    request: MarqueeRequest,
+) -> Result<Vec<ShapeId>, CommandError> {
+    handlers::marquee_select(&*state.document()?, request)
+}
+
+/// Edit selection.
+#[tauri::command]
+pub fn select(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    request: SelectRequest,
+) -> Result<SelectionState, CommandError> {
+    let doc = state.document()?;
+    let mut selection = state.selection()?;
+    if handlers::select(&doc, &mut selection, request)? {
+        notify_selection(&app, &doc, &selection);
+    }
+    Ok(selection.state(&doc))
+}
+
+/// Get current selection and its bounds.
+#[tauri::command]
+pub fn get_selection(state: State<'_, AppState>) -> Result<SelectionState, CommandError> {
+    let doc = state.document()?;
+    let selection = state.selection()?;
+    Ok(selection.state(&doc))
+}
+
+/// Apply transform batch atomically.
+#[tauri::command]
+pub fn update_transforms(
+    state: State<'_, AppState>,
+    batch: TransformBatch,
+    coalesce: Option<bool>,
+) -> Result<BatchReport, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::update_transforms(&mut doc, &mut history, &batch, coalesce.unwrap_or(false))
+}
+
+/// Apply layer opacity batch atomically.
+#[tauri::command]
+pub fn update_opacities(
+    state: State<'_, AppState>,
+    batch: OpacityBatch,
+    coalesce: Option<bool>,
+) -> Result<BatchReport, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::update_opacities(&mut doc, &mut history, &batch, coalesce.unwrap_or(false))
+}
+
+/// Start dragging a transform handle over the selection.
+#[tauri::command]
+pub fn begin_transform(
+    state: State<'_, AppState>,
+    handle: Handle,
+    start: Vec2,
+) -> Result<(), CommandError> {
+    let (doc, mut history) = state.session()?;
+    let selection = state.selection()?;
+    let session = handlers::begin_transform(&doc, &mut history, &selection, handle, start)?;
+    *state.transform()? = Some(session);
+    Ok(())
+}
+
+/// Apply one drag frame of the active transform session.
+#[tauri::command]
+pub fn drag_transform(
+    state: State<'_, AppState>,
+    current: Vec2,
+    modifiers: Option<Modifiers>,
+) -> Result<BatchReport, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let transform = state.transform()?;
+    let session = transform
+        .as_ref()
+        .ok_or_else(|| CommandError::InvalidInput("no transform in progress".into()))?;
+    let modifiers = modifiers.unwrap_or_default();
+    handlers::drag_transform(&mut doc, &mut history, session, current, modifiers)
+}
+
+/// Finish the active transform session.
+#[tauri::command]
+pub fn end_transform(app: AppHandle, state: State<'_, AppState>) -> Result<(), CommandError> {
+    let (doc, mut history) = state.session()?;
+    let selection = state.selection()?;
+    if state.transform()?.take().is_some() {
+        history.seal();
+        notify_selection(&app, &doc, &selection);
+    }
+    Ok(())
+}
+
+/// Undo last step.
+#[tauri::command]
+pub fn undo(app: AppHandle, state: State<'_, AppState>) -> Result<HistoryStatus, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let status = handlers::undo(&mut doc, &mut history)?;
+    state.prune_selection(&app, &doc)?;
+    state.cancel_transform()?;
+    Ok(status)
+}
+
+/// Redo last undone step.
+#[tauri::command]
+pub fn redo(app: AppHandle, state: State<'_, AppState>) -> Result<HistoryStatus, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let status = handlers::redo(&mut doc, &mut history)?;
+    state.prune_selection(&app, &doc)?;
+    state.cancel_transform()?;
+    Ok(status)
+}
+
+/// Save document as .simple JSON.
+#[tauri::command]
+pub fn save_document(state: State<'_, AppState>, path: String) -> Result<(), CommandError> {
+    handlers::save_document(&*state.document()?, &path)
+}
+
+/// Export the whole canvas, a region or the selection as PNG, off the main
+/// thread and without holding the state locks.
+#[tauri::command(async)]
+pub fn export_png(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<RasterOptions>,
+) -> Result<(), CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_png(&doc, &selection, &path, &options.unwrap_or_default())
+}
+
+/// Export the whole canvas, a region or the selection as JPEG.
+#[tauri::command(async)]
+pub fn export_jpeg(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<JpegOptions>,
+) -> Result<(), CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_jpeg(&doc, &selection, &path, &options.unwrap_or_default())
+}
+
+/// Export the whole canvas, a region or the selection as WebP.
+#[tauri::command(async)]
+pub fn export_webp(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<WebpOptions>,
+) -> Result<(), CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_webp(&doc, &selection, &path, &options.unwrap_or_default())
+}
+
+/// Export one area several ways in one call, e.g. web, @2x and print.
+#[tauri::command(async)]
+pub fn export_presets(
+    state: State<'_, AppState>,
+    dir: String,
+    stem: String,
+    area: Option<ExportArea>,
+    presets: Option<Vec<ExportPreset>>,
+) -> Result<Vec<PresetOutput>, CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_presets(
+        &doc,
+        &selection,
+        &dir,
+        &stem,
+        area.unwrap_or_default(),
+        presets,
+    )
+}
+
+/// Built-in export presets, for the export dialog.
+#[tauri::command]
+pub fn list_export_presets() -> Vec<ExportPreset> {
+    crate::export::preset::builtin_presets()
+}
+
+/// Export the document as SVG, framing the requested area.
+#[tauri::command(async)]
+pub fn export_svg(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<SvgOptions>,
+) -> Result<(), CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_svg(&doc, &selection, &path, &options.unwrap_or_default())
+}
+
+/// Export the requested area as a vector PDF page.
+#[tauri::command(async)]
+pub fn export_pdf(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<PdfOptions>,
+) -> Result<(), CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_pdf(&doc, &selection, &path, &options.unwrap_or_default())
+}
+
+/// Export each artboard with every preset, one file set per artboard.
+#[tauri::command(async)]
+pub fn export_artboards(
+    state: State<'_, AppState>,
+    dir: String,
+    presets: Option<Vec<ExportPreset>>,
+) -> Result<Vec<PresetOutput>, CommandError> {
+    let (doc, selection) = state.snapshot()?;
+    handlers::export_artboards(&doc, &selection, &dir, presets)
+}
+
+/// Export every artboard as one page of a PDF.
+#[tauri::command(async)]
+pub fn export_artboards_pdf(state: State<'_, AppState>, path: String) -> Result<(), CommandError> {
+    let doc = state.document()?.clone();
+    handlers::export_artboards_pdf(&doc, &path)
+}
+
+/// Import SVG rects and groups as new layers, reporting skipped content.
+#[tauri::command]
+pub fn import_svg(state: State<'_, AppState>, path: String) -> Result<ImportReport, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    handlers::import_svg(&mut doc, &mut history, &path)
+}
+
+/// Open .simple JSON document, replacing the current one, and report the
+/// migration steps and warnings for older files.
+#[tauri::command]
+pub fn open_document(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<MigrationReport, CommandError> {
+    let (mut doc, mut history) = state.session()?;
+    let report = handlers::open_document(&mut doc, &mut history, &path)?;
+
+    let mut selection = state.selection()?;
+    if selection.clear() {
+        notify_selection(&app, &doc, &selection);
+    }
+    state.cancel_transform()?;
+    Ok(report)
+}
@@ -0,0 +1,287 @@
+// MANDATE: Hybrid .simple zip container (document.json + content-addressed blobs)
+#![deny(warnings)]
+
+use crate::blob_store::{is_blob_id, to_hex, BlobId, BlobStore, MAX_BLOB_BYTES};
+use crate::document::Document;
+use crate::file_format::{self, io_error, FormatError, MAX_FILE_BYTES};
+use crate::migration::MigrationReport;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, Write};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Archive entry holding the master JSON.
+pub const DOCUMENT_ENTRY: &str = "document.json";
+
+/// Archive directory holding blobs named by their hash.
+pub const BLOB_DIR: &str = "blobs/";
+
+/// Local file header signature starting every zip archive.
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+
+/// Streaming chunk size.
+/// MANDATE: Bounded buffer.
+const COPY_CHUNK: usize = 64 * 1024;
+
+/// Check whether file prefix is a zip container.
+/// MANDATE: ≤60 SLOC.
+pub fn is_container(prefix: &[u8]) -> bool {
+    prefix.starts_with(&ZIP_MAGIC)
+}
+
+/// Write document and its referenced blobs as a zip container.
+/// MANDATE: ≤60 SLOC, each blob stored once under its hash.
+pub fn write_container<W: Write + Seek>(doc: &Document, writer: W) -> Result<(), FormatError> {
+    let json = file_format::to_json(doc)?;
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(writer);
+
+    zip.start_file(DOCUMENT_ENTRY, options)
+        .map_err(archive_error)?;
+    zip.write_all(json.as_bytes()).map_err(io_error)?;
+
+    for id in doc.referenced_blobs() {
+        let bytes = doc
+            .blobs()
+            .get(&id)
+            .ok_or_else(|| FormatError::MissingBlob(id.clone()))?;
+
+        zip.start_file(blob_path(&id), options)
+            .map_err(archive_error)?;
+        for chunk in bytes.chunks(COPY_CHUNK) {
+            zip.write_all(chunk).map_err(io_error)?;
+        }
+    }
+
+    let mut inner = zip.finish().map_err(archive_error)?;
+    inner.flush().map_err(io_error)
+}
+
+/// Read document and its referenced blobs from a zip container.
+/// MANDATE: ≤60 SLOC, entries streamed one at a time, blobs verified.
+pub fn read_container<R: Read + Seek>(
+    reader: R,
+) -> Result<(Document, MigrationReport), FormatError> {
+    let mut archive = ZipArchive::new(reader).map_err(archive_error)?;
+    let text = read_document_entry(&mut archive)?;
+    let (file, report) = file_format::parse_file(&text)?;
+
+    let mut ids: Vec<BlobId> = file
+        .shapes
+        .iter()
+        .filter_map(|s| s.texture.clone())
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut blobs = BlobStore::new();
+    for id in &ids {
+        let mut bytes = Vec::new();
+        stream_blob(&mut archive, id, &mut bytes)?;
+        let stored = blobs.insert(bytes).map_err(FormatError::Archive)?;
+
+        // MANDATE: Assertions - stream_blob verified the hash
+        assert_eq!(&stored, id);
+    }
+
+    Ok((file.into_document(blobs)?, report))
+}
+
+/// Stream one blob from a container into writer without loading the archive.
+/// Output must be discarded if this returns an error.
+/// MANDATE: ≤60 SLOC.
+pub fn copy_blob<R: Read + Seek, W: Write>(
+    reader: R,
+    id: &str,
+    out: &mut W,
+) -> Result<u64, FormatError> {
+    let mut archive = ZipArchive::new(reader).map_err(archive_error)?;
+    stream_blob(&mut archive, id, out)
+}
+
+/// Read master JSON entry.
+/// MANDATE: ≤60 SLOC, bounded read.
+fn read_document_entry<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<String, FormatError> {
+    let entry = archive.by_name(DOCUMENT_ENTRY).map_err(|e| match e {
+        ZipError::FileNotFound => FormatError::Archive(format!("missing {}", DOCUMENT_ENTRY)),
+        other => archive_error(other),
+    })?;
+    if entry.size() > MAX_FILE_BYTES {
+        return Err(FormatError::TooLarge(entry.size()));
+    }
+
+    let mut text = String::new();
+    entry
+        .take(MAX_FILE_BYTES)
+        .read_to_string(&mut text)
+        .map_err(|e| FormatError::Archive(e.to_string()))?;
+    Ok(text)
+}
+
+/// Copy blob entry to sink in chunks while hashing it.
+/// MANDATE: ≤60 SLOC, loop bounded by MAX_BLOB_BYTES.
+fn stream_blob<R: Read + Seek, W: Write>(
+    archive: &mut ZipArchive<R>,
+    id: &str,
+    sink: &mut W,
+) -> Result<u64, FormatError> {
+    if !is_blob_id(id) {
+        return Err(FormatError::MissingBlob(id.to_string()));
+    }
+    let entry = archive.by_name(&blob_path(id)).map_err(|e| match e {
+        ZipError::FileNotFound => FormatError::MissingBlob(id.to_string()),
+        other => archive_error(other),
+    })?;
+
+    let mut limited = entry.take(MAX_BLOB_BYTES as u64 + 1);
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0u8; COPY_CHUNK];
+    let mut total: u64 = 0;
+
+    loop {
+        let read = limited
+            .read(&mut chunk)
+            .map_err(|_| FormatError::CorruptBlob(id.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        total += read as u64;
+        if total > MAX_BLOB_BYTES as u64 {
+            return Err(FormatError::CorruptBlob(id.to_string()));
+        }
+        hasher.update(&chunk[..read]);
+        sink.write_all(&chunk[..read]).map_err(io_error)?;
+    }
+
+    if to_hex(&hasher.finalize()) != id {
+        return Err(FormatError::CorruptBlob(id.to_string()));
+    }
+    Ok(total)
+}
+
+/// Archive path of blob.
+/// MANDATE: ≤60 SLOC.
+fn blob_path(id: &str) -> String {
+    // MANDATE: Assertions - IDs never escape the blob directory
+    assert!(is_blob_id(id));
+    format!("{}{}", BLOB_DIR, id)
+}
+
+/// Convert zip error.
+/// MANDATE: ≤60 SLOC.
+fn archive_error(err: ZipError) -> FormatError {
+    FormatError::Archive(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_store::hash_blob;
+    use crate::file_format::{open_document, save_document, to_json};
+    use glam::Vec2;
+    use std::io::Cursor;
+
+    const PIXELS: [u8; 16] = [
+        255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255,
+    ];
+
+    fn textured_document() -> Document {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Images".to_string()).unwrap();
+        let blob = doc.add_blob(PIXELS.to_vec()).unwrap();
+        for i in 0..2 {
+            let position = Vec2::new(i as f32 * 10.0, 0.0);
+            let id = doc.add_shape(layer, position, Vec2::splat(8.0)).unwrap();
+            doc.set_shape_texture(id, Some(blob.clone())).unwrap();
+        }
+        doc
+    }
+
+    fn to_archive(doc: &Document) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        write_container(doc, &mut buffer).unwrap();
+        buffer.into_inner()
+    }
+
+    fn archive_with(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_round_trip_deduplicates_blobs() {
+        let doc = textured_document();
+        let bytes = to_archive(&doc);
+        assert!(is_container(&bytes));
+
+        let archive = ZipArchive::new(Cursor::new(bytes.clone())).unwrap();
+        let blob_entries = archive
+            .file_names()
+            .filter(|n| n.starts_with(BLOB_DIR))
+            .count();
+        assert_eq!(blob_entries, 1);
+
+        let (reopened, _) = read_container(Cursor::new(bytes)).unwrap();
+        assert_eq!(to_json(&reopened).unwrap(), to_json(&doc).unwrap());
+        assert_eq!(reopened.blobs().get(&hash_blob(&PIXELS)), Some(&PIXELS[..]));
+    }
+
+    #[test]
+    fn test_missing_blob_detected() {
+        let json = to_json(&textured_document()).unwrap();
+        let bytes = archive_with(&[(DOCUMENT_ENTRY, json.as_bytes())]);
+
+        let result = read_container(Cursor::new(bytes));
+        assert_eq!(
+            result.err(),
+            Some(FormatError::MissingBlob(hash_blob(&PIXELS)))
+        );
+    }
+
+    #[test]
+    fn test_corrupt_blob_detected() {
+        let json = to_json(&textured_document()).unwrap();
+        let path = blob_path(&hash_blob(&PIXELS));
+        let bytes = archive_with(&[(DOCUMENT_ENTRY, json.as_bytes()), (&path, b"tampered")]);
+
+        let result = read_container(Cursor::new(bytes));
+        assert_eq!(
+            result.err(),
+            Some(FormatError::CorruptBlob(hash_blob(&PIXELS)))
+        );
+    }
+
+    #[test]
+    fn test_copy_blob_streams_single_entry() {
+        let bytes = to_archive(&textured_document());
+        let mut out = Vec::new();
+
+        let copied = copy_blob(Cursor::new(bytes), &hash_blob(&PIXELS), &mut out).unwrap();
+
+        assert_eq!(copied, PIXELS.len() as u64);
+        assert_eq!(out, PIXELS);
+    }
+
+    #[test]
+    fn test_open_detects_container() {
+        let path = std::env::temp_dir().join(format!("sd-container-{}.simple", std::process::id()));
+        let doc = textured_document();
+
+        save_document(&doc, &path).unwrap();
+        let mut magic = [0u8; 4];
+        std::fs::File::open(&path)
+            .unwrap()
+            .read_exact(&mut magic)
+            .unwrap();
+        let (reopened, _) = open_document(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(is_container(&magic));
+        assert_eq!(reopened.referenced_blobs(), doc.referenced_blobs());
+    }
+}
@@ -0,0 +1,274 @@
+// MANDATE: Dirty rectangle tracking for incremental redraw
+#![deny(warnings)]
+
+use crate::document::{Document, DocumentError};
+use crate::layer::LayerId;
+use crate::selection::Bounds;
+use crate::spatial_index::ShapeId;
+use glam::Vec2;
+
+/// Most dirty regions kept per frame before regions are merged.
+/// MANDATE: Bounded collection.
+pub const MAX_DIRTY_REGIONS: usize = 16;
+
+/// World-space areas that changed since the last frame.
+/// Mark each changed object before and after the edit so both its old and
+/// new areas are redrawn; overlapping areas merge into one region.
+/// MANDATE: Bounded collection, regions never overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirtyTracker {
+    regions: Vec<Bounds>,
+    limit: usize,
+}
+
+impl Default for DirtyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirtyTracker {
+    /// Create tracker holding up to `MAX_DIRTY_REGIONS` regions.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self {
+            regions: Vec::with_capacity(MAX_DIRTY_REGIONS),
+            limit: MAX_DIRTY_REGIONS,
+        }
+    }
+
+    /// Create tracker holding up to `limit` regions.
+    /// MANDATE: ≤60 SLOC, limit within 1..=MAX_DIRTY_REGIONS.
+    pub fn with_limit(limit: usize) -> Result<Self, DocumentError> {
+        // MANDATE: Input validation
+        if limit == 0 || limit > MAX_DIRTY_REGIONS {
+            return Err(DocumentError::InvalidValue(format!(
+                "dirty region limit must be 1..={}",
+                MAX_DIRTY_REGIONS
+            )));
+        }
+
+        Ok(Self {
+            regions: Vec::with_capacity(limit),
+            limit,
+        })
+    }
+
+    /// Dirty regions of the current frame.
+    /// MANDATE: ≤60 SLOC.
+    pub fn regions(&self) -> &[Bounds] {
+        &self.regions
+    }
+
+    /// Check nothing changed this frame.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Mark world-space rectangle dirty.
+    /// MANDATE: ≤60 SLOC, bounded merge loop.
+    pub fn mark_rect(&mut self, min: Vec2, max: Vec2) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y {
+            return Err(DocumentError::InvalidValue("invalid dirty rect".into()));
+        }
+
+        let mut rect = Bounds { min, max };
+        // Each pass absorbs one region, so the loop ends within len + 1 passes
+        for _ in 0..=self.regions.len() {
+            let absorbed = match self.regions.iter().position(|r| overlaps(r, &rect)) {
+                Some(index) => index,
+                None if self.regions.len() < self.limit => break,
+                None => self.cheapest(&rect),
+            };
+            rect = union(&self.regions.swap_remove(absorbed), &rect);
+        }
+        self.regions.push(rect);
+
+        // MANDATE: Assertions
+        assert!(self.regions.len() <= self.limit);
+        Ok(())
+    }
+
+    /// Mark shape's current world bounds dirty.
+    /// MANDATE: ≤60 SLOC.
+    pub fn mark_shape(&mut self, doc: &Document, id: ShapeId) -> Result<(), DocumentError> {
+        let (min, max) = doc
+            .world_bounds(id)
+            .ok_or(DocumentError::ShapeNotFound(id))?;
+        self.mark_rect(min, max)
+    }
+
+    /// Mark current world bounds of every shape under layer dirty.
+    /// MANDATE: ≤60 SLOC, bounded by the layer subtree.
+    pub fn mark_layer(&mut self, doc: &Document, id: LayerId) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        if doc.layers().get_layer(id).is_none() {
+            return Err(DocumentError::LayerNotFound(id));
+        }
+
+        for shape in doc.shapes_in_subtree(id) {
+            self.mark_shape(doc, shape)?;
+        }
+        Ok(())
+    }
+
+    /// Shapes intersecting any dirty region, via the spatial index, sorted by ID.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn dirty_shapes(&self, doc: &Document) -> Vec<ShapeId> {
+        let mut ids: Vec<ShapeId> = self
+            .regions
+            .iter()
+            .flat_map(|region| doc.query_rect(region.min, region.max))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        // MANDATE: Assertions
+        assert!(ids.len() <= doc.shape_count());
+        ids
+    }
+
+    /// End the frame, returning its regions and starting clean.
+    /// MANDATE: ≤60 SLOC.
+    pub fn take(&mut self) -> Vec<Bounds> {
+        let regions = std::mem::take(&mut self.regions);
+
+        // MANDATE: Assertions
+        assert!(self.regions.is_empty());
+        regions
+    }
+
+    /// Index of the region whose union with rect grows the dirty area least.
+    /// MANDATE: ≤60 SLOC, caller ensures regions exist.
+    fn cheapest(&self, rect: &Bounds) -> usize {
+        // MANDATE: Assertions
+        assert!(!self.regions.is_empty());
+
+        let growth = |region: &Bounds| area(&union(region, rect)) - area(region);
+        let mut best = 0;
+        for (index, region) in self.regions.iter().enumerate() {
+            if growth(region) < growth(&self.regions[best]) {
+                best = index;
+            }
+        }
+        best
+    }
+}
+
+/// Check two rectangles share any area or edge.
+/// MANDATE: ≤60 SLOC.
+fn overlaps(a: &Bounds, b: &Bounds) -> bool {
+    a.min.x <= b.max.x && b.min.x <= a.max.x && a.min.y <= b.max.y && b.min.y <= a.max.y
+}
+
+/// Smallest rectangle covering both.
+/// MANDATE: ≤60 SLOC.
+fn union(a: &Bounds, b: &Bounds) -> Bounds {
+    Bounds {
+        min: a.min.min(b.min),
+        max: a.max.max(b.max),
+    }
+}
+
+/// Area of rectangle.
+/// MANDATE: ≤60 SLOC.
+fn area(rect: &Bounds) -> f32 {
+    let size = rect.max - rect.min;
+    size.x * size.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Document, LayerId, ShapeId) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let shape = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .unwrap();
+        (doc, layer, shape)
+    }
+
+    fn rect(min: (f32, f32), max: (f32, f32)) -> Bounds {
+        Bounds {
+            min: Vec2::new(min.0, min.1),
+            max: Vec2::new(max.0, max.1),
+        }
+    }
+
+    #[test]
+    fn test_move_marks_before_and_after() {
+        let (mut doc, layer, id) = setup();
+        let other = doc
+            .add_shape(layer, Vec2::new(500.0, 0.0), Vec2::ONE)
+            .unwrap();
+        let mut tracker = DirtyTracker::new();
+
+        tracker.mark_shape(&doc, id).unwrap();
+        doc.move_shape(id, Vec2::new(100.0, 0.0)).unwrap();
+        tracker.mark_shape(&doc, id).unwrap();
+
+        assert_eq!(
+            tracker.regions(),
+            &[
+                rect((0.0, 0.0), (10.0, 10.0)),
+                rect((100.0, 0.0), (110.0, 10.0))
+            ]
+        );
+        assert_eq!(tracker.dirty_shapes(&doc), vec![id]);
+        assert!(!tracker.dirty_shapes(&doc).contains(&other));
+    }
+
+    #[test]
+    fn test_overlapping_move_merges() {
+        let (mut doc, _, id) = setup();
+        let mut tracker = DirtyTracker::new();
+
+        tracker.mark_shape(&doc, id).unwrap();
+        doc.move_shape(id, Vec2::new(5.0, 2.0)).unwrap();
+        tracker.mark_shape(&doc, id).unwrap();
+
+        assert_eq!(tracker.regions(), &[rect((0.0, 0.0), (15.0, 12.0))]);
+        assert_eq!(tracker.take().len(), 1);
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn test_limit_merges_cheapest() {
+        let mut tracker = DirtyTracker::with_limit(2).unwrap();
+        tracker.mark_rect(Vec2::ZERO, Vec2::ONE).unwrap();
+        tracker
+            .mark_rect(Vec2::splat(100.0), Vec2::splat(101.0))
+            .unwrap();
+        tracker
+            .mark_rect(Vec2::splat(3.0), Vec2::splat(4.0))
+            .unwrap();
+
+        let mut regions = tracker.take();
+        regions.sort_by(|a, b| a.min.x.total_cmp(&b.min.x));
+        assert_eq!(
+            regions,
+            vec![
+                rect((0.0, 0.0), (4.0, 4.0)),
+                rect((100.0, 100.0), (101.0, 101.0))
+            ]
+        );
+        assert!(DirtyTracker::with_limit(0).is_err());
+    }
+
+    #[test]
+    fn test_mark_layer_covers_subtree() {
+        let (mut doc, layer, id) = setup();
+        let group = doc.add_group("Group".into()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+        let mut tracker = DirtyTracker::new();
+
+        tracker.mark_layer(&doc, group).unwrap();
+        assert_eq!(tracker.dirty_shapes(&doc), vec![id]);
+        assert!(tracker.mark_layer(&doc, 999).is_err());
+        assert!(tracker.mark_rect(Vec2::ONE, Vec2::ZERO).is_err());
+    }
+}
@@ -0,0 +1,1437 @@
+// MANDATE: Scene document owning layers, shapes, artboards and spatial index
+#![deny(warnings)]
+
+use crate::artboard::{topmost_at, Artboard, ArtboardContents, ArtboardId, MAX_ARTBOARDS};
+use crate::blob_store::{BlobId, BlobStore};
+use crate::layer::{Layer, LayerId};
+use crate::layer_tree::LayerTree;
+use crate::shape::{transform_parts, MaterialType, Placement, Shape, ShapeId, MAX_SKEW};
+use crate::spatial_index::{EntryKind, SpatialEntry, SpatialIndex};
+use glam::{Mat3, Vec2, Vec4};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum shapes per document.
+/// MANDATE: Bounded allocation, matches SpatialIndex::build limit.
+const MAX_SHAPES: usize = 100_000;
+
+/// Maximum layer and artboard name length.
+/// MANDATE: Bounded input, matches Layer::new limit.
+const MAX_NAME_LEN: usize = 256;
+
+/// Z-order bound for layers and shapes.
+/// MANDATE: Bounded value, matches Layer::set_z_order limit.
+const MAX_Z_ORDER: i32 = 10_000;
+
+/// Largest absolute coordinate or extent, in world units.
+/// MANDATE: Bounded value, keeps composed transforms far from f32 overflow.
+pub const MAX_COORDINATE: f32 = 1e7;
+
+/// Largest scale factor along either axis of a layer transform.
+/// MANDATE: Bounded value, paired with the determinant check as lower bound.
+pub const MAX_SCALE: f32 = 1e4;
+
+/// Document errors.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum DocumentError {
+    #[error("Layer not found: {0}")]
+    LayerNotFound(LayerId),
+    #[error("Shape not found: {0}")]
+    ShapeNotFound(ShapeId),
+    #[error("Artboard not found: {0}")]
+    ArtboardNotFound(ArtboardId),
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+    #[error("Capacity exceeded: {0}")]
+    CapacityExceeded(String),
+    /// Undoing a failed step failed too, leaving the document partly changed.
+    #[error("Rollback failed: {0}")]
+    RollbackFailed(String),
+}
+
+/// Authoritative scene: layers, shapes, artboards and their spatial index.
+/// MANDATE: Every mutation keeps all of them consistent.
+#[derive(Clone)]
+pub struct Document {
+    layers: LayerTree,
+    shapes: HashMap<ShapeId, Shape>,
+    index: SpatialIndex,
+    entries: HashMap<ShapeId, SpatialEntry>,
+    artboards: HashMap<ArtboardId, Artboard>,
+    blobs: BlobStore,
+    next_shape_id: ShapeId,
+    next_artboard_id: ArtboardId,
+}
+
+impl Document {
+    /// Create empty document.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self {
+            layers: LayerTree::new(),
+            shapes: HashMap::new(),
+            index: SpatialIndex::new(),
+            entries: HashMap::new(),
+            artboards: HashMap::new(),
+            blobs: BlobStore::new(),
+            next_shape_id: 1,
+            next_artboard_id: 1,
+        }
+    }
+
+    /// Create empty document holding existing blobs.
+    /// MANDATE: ≤60 SLOC, used when loading containers.
+    pub fn with_blobs(blobs: BlobStore) -> Self {
+        Self {
+            blobs,
+            ..Self::new()
+        }
+    }
+
+    /// Get layer tree.
+    /// MANDATE: ≤60 SLOC, read-only access.
+    pub fn layers(&self) -> &LayerTree {
+        &self.layers
+    }
+
+    /// Get mutable layer.
+    /// MANDATE: ≤60 SLOC, private so transform changes go through reindexing.
+    fn layer_mut(&mut self, id: LayerId) -> Result<&mut Layer, DocumentError> {
+        self.layers
+            .get_layer_mut(id)
+            .ok_or(DocumentError::LayerNotFound(id))
+    }
+
+    /// Add new layer.
+    /// MANDATE: ≤60 SLOC, validated name.
+    pub fn add_layer(&mut self, name: String) -> Result<LayerId, DocumentError> {
+        // MANDATE: Input validation (Layer::new asserts these)
+        validate_name(&name, "layer")?;
+        self.layers
+            .add_layer(name)
+            .map_err(DocumentError::CapacityExceeded)
+    }
+
+    /// Add new empty group.
+    /// MANDATE: ≤60 SLOC, validated name.
+    pub fn add_group(&mut self, name: String) -> Result<LayerId, DocumentError> {
+        // MANDATE: Input validation (Layer::new asserts these)
+        validate_name(&name, "layer")?;
+        self.layers
+            .add_group(name)
+            .map_err(DocumentError::CapacityExceeded)
+    }
+
+    /// Remove layer, its descendants and every shape on them.
+    /// MANDATE: ≤60 SLOC, cascading delete.
+    pub fn remove_layer(&mut self, id: LayerId) -> Result<Vec<ShapeId>, DocumentError> {
+        if self.layers.get_layer(id).is_none() {
+            return Err(DocumentError::LayerNotFound(id));
+        }
+
+        let removed = self.shapes_in_subtree(id);
+        for shape_id in &removed {
+            self.remove_shape(*shape_id)?;
+        }
+
+        let layer_removed = self.layers.remove_layer(id);
+
+        // MANDATE: Assertions
+        assert!(layer_removed);
+        assert!(self
+            .shapes
+            .values()
+            .all(|s| self.layers.get_layer(s.layer_id).is_some()));
+
+        Ok(removed)
+    }
+
+    /// Move layer into group, or to top level with None, above its new siblings.
+    /// MANDATE: ≤60 SLOC, moved shapes reindexed.
+    pub fn set_layer_parent(
+        &mut self,
+        id: LayerId,
+        parent: Option<LayerId>,
+    ) -> Result<(), DocumentError> {
+        if self.layers.get_layer(id).is_none() {
+            return Err(DocumentError::LayerNotFound(id));
+        }
+        if let Some(parent_id) = parent.filter(|p| self.layers.get_layer(*p).is_none()) {
+            return Err(DocumentError::LayerNotFound(parent_id));
+        }
+
+        self.layers
+            .set_parent(id, parent)
+            .map_err(DocumentError::InvalidValue)?;
+        self.reindex_layer(id);
+        Ok(())
+    }
+
+    /// Move layer to index among its siblings (0 = bottom).
+    /// MANDATE: ≤60 SLOC.
+    pub fn reorder_sibling(&mut self, id: LayerId, index: usize) -> Result<(), DocumentError> {
+        if self.layers.get_layer(id).is_none() {
+            return Err(DocumentError::LayerNotFound(id));
+        }
+        self.layers
+            .reorder_sibling(id, index)
+            .map_err(DocumentError::InvalidValue)
+    }
+
+    /// Dissolve group, keeping its children where they appear on screen.
+    /// Fails without changes if a composed child transform would be invalid.
+    /// MANDATE: ≤60 SLOC, returns the former children.
+    pub fn ungroup(&mut self, id: LayerId) -> Result<Vec<LayerId>, DocumentError> {
+        let group = self
+            .layers
+            .get_layer(id)
+            .ok_or(DocumentError::LayerNotFound(id))?;
+        for child in self.layers.children(Some(id)) {
+            validate_affine(group.transform * child.transform)?;
+        }
+
+        let children = self
+            .layers
+            .ungroup(id)
+            .map_err(DocumentError::InvalidValue)?;
+        for child in &children {
+            self.reindex_layer(*child);
+        }
+
+        // MANDATE: Assertions
+        assert!(self.layers.get_layer(id).is_none());
+        Ok(children)
+    }
+
+    /// Insert existing layer keeping its ID; its parent must already exist.
+    /// MANDATE: ≤60 SLOC, validated fields.
+    pub fn insert_layer(&mut self, layer: Layer) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        validate_name(&layer.name, "layer")?;
+        validate_opacity(layer.opacity)?;
+        validate_z_order(layer.z_order)?;
+        validate_affine(layer.transform)?;
+
+        self.layers
+            .insert_layer(layer)
+            .map_err(DocumentError::InvalidValue)
+    }
+
+    /// Set layer visibility.
+    /// MANDATE: ≤60 SLOC.
+    pub fn set_layer_visible(&mut self, id: LayerId, visible: bool) -> Result<(), DocumentError> {
+        self.layer_mut(id)?.set_visible(visible);
+        Ok(())
+    }
+
+    /// Lock or unlock layer against picking and selection.
+    /// MANDATE: ≤60 SLOC.
+    pub fn set_layer_locked(&mut self, id: LayerId, locked: bool) -> Result<(), DocumentError> {
+        self.layer_mut(id)?.locked = locked;
+        Ok(())
+    }
+
+    /// Set layer opacity.
+    /// MANDATE: ≤60 SLOC, validated opacity.
+    pub fn set_layer_opacity(&mut self, id: LayerId, opacity: f32) -> Result<(), DocumentError> {
+        // MANDATE: Input validation (Layer::set_opacity asserts this)
+        validate_opacity(opacity)?;
+        self.layer_mut(id)?.set_opacity(opacity);
+        Ok(())
+    }
+
+    /// Set layer z-order.
+    /// MANDATE: ≤60 SLOC, bounded value.
+    pub fn set_layer_z_order(&mut self, id: LayerId, z_order: i32) -> Result<(), DocumentError> {
+        // MANDATE: Input validation (Layer::set_z_order asserts this)
+        validate_z_order(z_order)?;
+        self.layer_mut(id)?.set_z_order(z_order);
+        Ok(())
+    }
+
+    /// Set layer transform and reindex its shapes.
+    /// MANDATE: ≤60 SLOC, validated affine matrix.
+    pub fn set_layer_transform(
+        &mut self,
+        id: LayerId,
+        transform: Mat3,
+    ) -> Result<(), DocumentError> {
+        validate_affine(transform)?;
+        self.layer_mut(id)?.set_transform(transform);
+        self.reindex_layer(id);
+        Ok(())
+    }
+
+    /// Replace every field of existing layer.
+    /// MANDATE: ≤60 SLOC, validated fields, used to apply undo snapshots.
+    pub fn replace_layer(&mut self, layer: Layer) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        validate_name(&layer.name, "layer")?;
+        validate_opacity(layer.opacity)?;
+        validate_z_order(layer.z_order)?;
+        validate_affine(layer.transform)?;
+
+        let id = layer.id;
+        let current = self.layer_mut(id)?;
+        if current.is_group != layer.is_group {
+            return Err(DocumentError::InvalidValue("layer kind is fixed".into()));
+        }
+        let reparented = current.parent != layer.parent;
+        if reparented {
+            // Snapshot carries its own z-order, so siblings are not renumbered
+            self.layers
+                .validate_parent(id, layer.parent)
+                .map_err(DocumentError::InvalidValue)?;
+        }
+
+        let current = self.layer_mut(id)?;
+        let moved = reparented || current.transform != layer.transform;
+        *current = layer;
+
+        if moved {
+            self.reindex_layer(id);
+        }
+        Ok(())
+    }
+
+    /// Add shape to layer.
+    /// MANDATE: ≤60 SLOC, bounded capacity, validated inputs.
+    pub fn add_shape(
+        &mut self,
+        layer_id: LayerId,
+        position: Vec2,
+        size: Vec2,
+    ) -> Result<ShapeId, DocumentError> {
+        self.validate_host(layer_id)?;
+        if self.shapes.len() >= MAX_SHAPES {
+            return Err(DocumentError::CapacityExceeded("Max shapes reached".into()));
+        }
+        validate_position(position)?;
+        validate_size(size)?;
+
+        let id = self.next_shape_id;
+        self.next_shape_id += 1;
+
+        let shape = Shape::new(id, layer_id, position, size);
+        self.shapes.insert(id, shape);
+        self.reindex_shape(id);
+
+        // MANDATE: Assertions
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+
+        Ok(id)
+    }
+
+    /// Insert existing shape keeping its ID.
+    /// MANDATE: ≤60 SLOC, bounded capacity, validated fields.
+    pub fn insert_shape(&mut self, shape: Shape) -> Result<(), DocumentError> {
+        self.validate_host(shape.layer_id)?;
+        if self.shapes.len() >= MAX_SHAPES {
+            return Err(DocumentError::CapacityExceeded("Max shapes reached".into()));
+        }
+        if self.shapes.contains_key(&shape.id) || shape.id == ShapeId::MAX {
+            return Err(DocumentError::InvalidValue(format!(
+                "shape ID {}",
+                shape.id
+            )));
+        }
+        validate_position(shape.position)?;
+        validate_size(shape.size)?;
+        validate_color(shape.color)?;
+        validate_z_order(shape.z_order)?;
+        validate_orientation(&shape)?;
+        self.validate_texture(shape.texture.as_deref())?;
+
+        let id = shape.id;
+        self.next_shape_id = self.next_shape_id.max(id + 1);
+        self.shapes.insert(id, shape);
+        self.reindex_shape(id);
+
+        // MANDATE: Assertions
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+        Ok(())
+    }
+
+    /// Replace every field of existing shape.
+    /// MANDATE: ≤60 SLOC, validated fields, used to apply undo snapshots.
+    pub fn replace_shape(&mut self, shape: Shape) -> Result<(), DocumentError> {
+        self.validate_host(shape.layer_id)?;
+        validate_position(shape.position)?;
+        validate_size(shape.size)?;
+        validate_color(shape.color)?;
+        validate_z_order(shape.z_order)?;
+        validate_orientation(&shape)?;
+        self.validate_texture(shape.texture.as_deref())?;
+
+        self.modify_shape(shape.id, |current| *current = shape)
+    }
+
+    /// Remove shape.
+    /// MANDATE: ≤60 SLOC.
+    pub fn remove_shape(&mut self, id: ShapeId) -> Result<Shape, DocumentError> {
+        let shape = self
+            .shapes
+            .remove(&id)
+            .ok_or(DocumentError::ShapeNotFound(id))?;
+
+        let entry = self.entries.remove(&id);
+        let removed = entry.is_some_and(|entry| self.index.remove_entry(&entry));
+
+        // MANDATE: Assertions
+        assert!(removed);
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+
+        Ok(shape)
+    }
+
+    /// Move shape to new position.
+    /// MANDATE: ≤60 SLOC, index refreshed.
+    pub fn move_shape(&mut self, id: ShapeId, position: Vec2) -> Result<(), DocumentError> {
+        validate_position(position)?;
+        self.modify_shape(id, |shape| shape.position = position)
+    }
+
+    /// Resize shape.
+    /// MANDATE: ≤60 SLOC, index refreshed.
+    pub fn resize_shape(&mut self, id: ShapeId, size: Vec2) -> Result<(), DocumentError> {
+        validate_size(size)?;
+        self.modify_shape(id, |shape| shape.size = size)
+    }
+
+    /// Set shape color.
+    /// MANDATE: ≤60 SLOC, validated color.
+    pub fn set_shape_color(&mut self, id: ShapeId, color: Vec4) -> Result<(), DocumentError> {
+        // MANDATE: Input validation (Shape::set_color asserts this)
+        validate_color(color)?;
+        self.modify_shape(id, |shape| shape.set_color(color))
+    }
+
+    /// Set shape transform mapping the unit quad to layer space.
+    /// MANDATE: ≤60 SLOC, validated decomposition about the shape's pivot.
+    pub fn set_shape_transform(
+        &mut self,
+        id: ShapeId,
+        transform: Mat3,
+    ) -> Result<(), DocumentError> {
+        let pivot = self
+            .shapes
+            .get(&id)
+            .ok_or(DocumentError::ShapeNotFound(id))?
+            .pivot;
+        let placement = transform_parts(transform, pivot).map_err(DocumentError::InvalidValue)?;
+        validate_placement(&placement)?;
+
+        self.modify_shape(id, |shape| place(shape, placement))
+    }
+
+    /// Set shape rotation about its pivot, wrapped into [-PI, PI].
+    /// MANDATE: ≤60 SLOC, validated angle.
+    pub fn set_shape_rotation(&mut self, id: ShapeId, rotation: f32) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        if !rotation.is_finite() {
+            return Err(DocumentError::InvalidValue("rotation not finite".into()));
+        }
+        let wrapped = wrap_angle(rotation);
+        self.modify_shape(id, |shape| shape.rotation = wrapped)
+    }
+
+    /// Set shape skew angle.
+    /// MANDATE: ≤60 SLOC, validated angle.
+    pub fn set_shape_skew(&mut self, id: ShapeId, skew: f32) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        validate_skew(skew)?;
+        self.modify_shape(id, |shape| shape.skew = skew)
+    }
+
+    /// Move shape pivot without moving the shape on screen.
+    /// MANDATE: ≤60 SLOC, position recomputed for the new pivot.
+    pub fn set_shape_pivot(&mut self, id: ShapeId, pivot: Vec2) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        validate_pivot(pivot)?;
+        let transform = self
+            .shapes
+            .get(&id)
+            .ok_or(DocumentError::ShapeNotFound(id))?
+            .local_transform();
+        let placement = transform_parts(transform, pivot).map_err(DocumentError::InvalidValue)?;
+        validate_placement(&placement)?;
+
+        self.modify_shape(id, |shape| {
+            shape.pivot = pivot;
+            place(shape, placement);
+        })
+    }
+
+    /// Set shape material.
+    /// MANDATE: ≤60 SLOC.
+    pub fn set_shape_material(
+        &mut self,
+        id: ShapeId,
+        material: MaterialType,
+    ) -> Result<(), DocumentError> {
+        self.modify_shape(id, |shape| shape.material = material)
+    }
+
+    /// Set shape texture; textured shapes use the textured material.
+    /// MANDATE: ≤60 SLOC, blob must exist.
+    pub fn set_shape_texture(
+        &mut self,
+        id: ShapeId,
+        texture: Option<BlobId>,
+    ) -> Result<(), DocumentError> {
+        self.validate_texture(texture.as_deref())?;
+
+        self.modify_shape(id, |shape| {
+            shape.material = match texture {
+                Some(_) => MaterialType::Textured,
+                None => MaterialType::SolidColor,
+            };
+            shape.texture = texture;
+        })
+    }
+
+    /// Set shape z-order within its layer.
+    /// MANDATE: ≤60 SLOC, bounded value.
+    pub fn set_shape_z_order(&mut self, id: ShapeId, z_order: i32) -> Result<(), DocumentError> {
+        // MANDATE: Input validation
+        validate_z_order(z_order)?;
+        self.modify_shape(id, |shape| shape.z_order = z_order)
+    }
+
+    /// Get shape by ID.
+    /// MANDATE: ≤60 SLOC.
+    pub fn shape(&self, id: ShapeId) -> Option<&Shape> {
+        self.shapes.get(&id)
+    }
+
+    /// Store image bytes, deduplicated by content hash.
+    /// MANDATE: ≤60 SLOC.
+    pub fn add_blob(&mut self, bytes: Vec<u8>) -> Result<BlobId, DocumentError> {
+        self.blobs
+            .insert(bytes)
+            .map_err(DocumentError::CapacityExceeded)
+    }
+
+    /// Get blob store.
+    /// MANDATE: ≤60 SLOC, read-only access.
+    pub fn blobs(&self) -> &BlobStore {
+        &self.blobs
+    }
+
+    /// Blob IDs referenced by shapes, sorted and unique.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn referenced_blobs(&self) -> Vec<BlobId> {
+        let mut ids: Vec<BlobId> = self
+            .shapes
+            .values()
+            .filter_map(|shape| shape.texture.clone())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Get all shapes sorted by ID.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn all_shapes(&self) -> Vec<&Shape> {
+        let mut shapes: Vec<&Shape> = self.shapes.values().collect();
+        shapes.sort_by_key(|shape| shape.id);
+        shapes
+    }
+
+    /// IDs of shapes on layer or any of its descendants, sorted.
+    /// MANDATE: ≤60 SLOC, single pass over the shapes.
+    pub fn shapes_in_subtree(&self, id: LayerId) -> Vec<ShapeId> {
+        let mut subtree: HashSet<LayerId> = self.layers.descendants(id).into_iter().collect();
+        subtree.insert(id);
+        let mut ids: Vec<ShapeId> = self
+            .shapes
+            .values()
+            .filter(|s| subtree.contains(&s.layer_id))
+            .map(|s| s.id)
+            .collect();
+        ids.sort_unstable();
+
+        // MANDATE: Assertions
+        assert!(ids.len() <= self.shapes.len());
+        ids
+    }
+
+    /// Get shapes on layer sorted by z-order.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn shapes_in_layer(&self, layer_id: LayerId) -> Vec<&Shape> {
+        let mut shapes: Vec<&Shape> = self
+            .shapes
+            .values()
+            .filter(|s| s.layer_id == layer_id)
+            .collect();
+
+        // MANDATE: Deterministic sort by z_order then ID
+        shapes.sort_by(|a, b| a.z_order.cmp(&b.z_order).then_with(|| a.id.cmp(&b.id)));
+        shapes
+    }
+
+    /// Query shapes in rectangle.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn query_rect(&self, min: Vec2, max: Vec2) -> Vec<ShapeId> {
+        let mut ids = self.index.query_rect(min, max);
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Query shapes at point.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn query_point(&self, point: Vec2) -> Vec<ShapeId> {
+        let mut ids = self.index.query_point(point);
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Query visible shapes whose bounds contain point, topmost first.
+    /// Broad phase only: `selection::hit_test` applies exact shape tests.
+    /// MANDATE: ≤60 SLOC, deterministic stacking order.
+    pub fn hit_test(&self, point: Vec2) -> Vec<ShapeId> {
+        let mut hits: Vec<&Shape> = self
+            .index
+            .query_point(point)
+            .iter()
+            .filter_map(|id| self.shapes.get(id))
+            .filter(|s| self.layers.is_visible(s.layer_id))
+            .collect();
+
+        // MANDATE: Deterministic sort, reverse of render order
+        let ranks = self.layer_ranks();
+        hits.sort_by_key(|s| std::cmp::Reverse(stacking_key(&ranks, s)));
+        hits.iter().map(|s| s.id).collect()
+    }
+
+    /// Position of every layer in depth-first render order.
+    /// MANDATE: ≤60 SLOC, computed once per query.
+    pub fn layer_ranks(&self) -> HashMap<LayerId, usize> {
+        let ranks: HashMap<LayerId, usize> = self
+            .layers
+            .render_order()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, id)| (id, rank))
+            .collect();
+
+        // MANDATE: Assertions
+        assert_eq!(ranks.len(), self.layers.len());
+        ranks
+    }
+
+    /// Add artboard with the default background, clipping its content.
+    /// MANDATE: ≤60 SLOC, bounded capacity, validated inputs.
+    pub fn add_artboard(
+        &mut self,
+        name: String,
+        position: Vec2,
+        size: Vec2,
+    ) -> Result<ArtboardId, DocumentError> {
+        // MANDATE: Input validation (Artboard::new asserts these)
+        validate_name(&name, "artboard")?;
+        validate_position(position)?;
+        validate_size(size)?;
+        if self.artboards.len() >= MAX_ARTBOARDS {
+            return Err(DocumentError::CapacityExceeded(
+                "Max artboards reached".into(),
+            ));
+        }
+
+        let id = self.next_artboard_id;
+        self.next_artboard_id += 1;
+        let artboard = Artboard::new(id, name, position, size);
+        self.index.insert(artboard_entry(&artboard));
+        self.artboards.insert(id, artboard);
+
+        // MANDATE: Assertions
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+        Ok(id)
+    }
+
+    /// Insert existing artboard keeping its ID.
+    /// MANDATE: ≤60 SLOC, bounded capacity, validated fields.
+    pub fn insert_artboard(&mut self, artboard: Artboard) -> Result<(), DocumentError> {
+        validate_artboard(&artboard)?;
+        if self.artboards.len() >= MAX_ARTBOARDS {
+            return Err(DocumentError::CapacityExceeded(
+                "Max artboards reached".into(),
+            ));
+        }
+        if self.artboards.contains_key(&artboard.id) || artboard.id == ArtboardId::MAX {
+            return Err(DocumentError::InvalidValue(format!(
+                "artboard ID {}",
+                artboard.id
+            )));
+        }
+
+        self.next_artboard_id = self.next_artboard_id.max(artboard.id + 1);
+        self.index.insert(artboard_entry(&artboard));
+        self.artboards.insert(artboard.id, artboard);
+
+        // MANDATE: Assertions
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+        Ok(())
+    }
+
+    /// Replace every field of existing artboard.
+    /// MANDATE: ≤60 SLOC, validated fields, index refreshed when the rect moves.
+    pub fn replace_artboard(&mut self, artboard: Artboard) -> Result<(), DocumentError> {
+        validate_artboard(&artboard)?;
+        let current = self
+            .artboards
+            .get_mut(&artboard.id)
+            .ok_or(DocumentError::ArtboardNotFound(artboard.id))?;
+
+        let old_entry = artboard_entry(current);
+        let new_entry = artboard_entry(&artboard);
+        *current = artboard;
+        if old_entry != new_entry {
+            let removed = self.index.remove_entry(&old_entry);
+            // MANDATE: Assertions
+            assert!(removed);
+            self.index.insert(new_entry);
+        }
+        Ok(())
+    }
+
+    /// Remove artboard; the layers and shapes inside it stay.
+    /// MANDATE: ≤60 SLOC.
+    pub fn remove_artboard(&mut self, id: ArtboardId) -> Result<Artboard, DocumentError> {
+        let artboard = self
+            .artboards
+            .remove(&id)
+            .ok_or(DocumentError::ArtboardNotFound(id))?;
+        let removed = self.index.remove_entry(&artboard_entry(&artboard));
+
+        // MANDATE: Assertions
+        assert!(removed);
+        assert_eq!(self.shapes.len() + self.artboards.len(), self.index.len());
+        Ok(artboard)
+    }
+
+    /// Get artboard by ID.
+    /// MANDATE: ≤60 SLOC.
+    pub fn artboard(&self, id: ArtboardId) -> Option<&Artboard> {
+        self.artboards.get(&id)
+    }
+
+    /// Get all artboards sorted by ID, which is also their stacking order.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn artboards(&self) -> Vec<&Artboard> {
+        let mut artboards: Vec<&Artboard> = self.artboards.values().collect();
+        artboards.sort_by_key(|artboard| artboard.id);
+        artboards
+    }
+
+    /// Count artboards.
+    /// MANDATE: ≤60 SLOC.
+    pub fn artboard_count(&self) -> usize {
+        self.artboards.len()
+    }
+
+    /// Query artboards overlapping rectangle.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn query_artboards(&self, min: Vec2, max: Vec2) -> Vec<ArtboardId> {
+        let mut ids = self.index.query_rect_kind(min, max, EntryKind::Artboard);
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Topmost artboard containing point; later artboards stack above earlier ones.
+    /// MANDATE: ≤60 SLOC.
+    pub fn artboard_at(&self, point: Vec2) -> Option<ArtboardId> {
+        let candidates = self
+            .index
+            .query_point_kind(point, EntryKind::Artboard)
+            .into_iter()
+            .filter_map(|id| self.artboards.get(&id));
+        topmost_at(candidates, point).map(|artboard| artboard.id)
+    }
+
+    /// Artboard a shape belongs to: the topmost one containing the centre of
+    /// its world bounds. Members may cross the edge, where a clipping artboard
+    /// cuts them.
+    /// MANDATE: ≤60 SLOC.
+    pub fn shape_artboard(&self, id: ShapeId) -> Option<ArtboardId> {
+        let (min, max) = self.world_bounds(id)?;
+        self.artboard_at((min + max) * 0.5)
+    }
+
+    /// World rectangle a shape is clipped to, if its artboard clips content.
+    /// MANDATE: ≤60 SLOC.
+    pub fn shape_clip(&self, id: ShapeId) -> Option<(Vec2, Vec2)> {
+        self.artboards.get(&self.shape_artboard(id)?)?.clip()
+    }
+
+    /// Shapes belonging to artboard, and layers whose shapes all belong to it.
+    /// MANDATE: ≤60 SLOC, bounded by the shape and layer counts.
+    pub fn artboard_contents(&self, id: ArtboardId) -> Result<ArtboardContents, DocumentError> {
+        let artboard = self
+            .artboards
+            .get(&id)
+            .ok_or(DocumentError::ArtboardNotFound(id))?;
+        let mut shapes: Vec<ShapeId> = self
+            .index
+            .query_rect(artboard.position, artboard.max())
+            .into_iter()
+            .filter(|shape| self.shape_artboard(*shape) == Some(id))
+            .collect();
+        shapes.sort_unstable();
+
+        // One pass over the shapes, crediting each to its layer and ancestors
+        let inside: HashSet<ShapeId> = shapes.iter().copied().collect();
+        let mut populated = HashSet::new();
+        let mut outside = HashSet::new();
+        for shape in self.shapes.values() {
+            let mut chain = self.layers.ancestors(shape.layer_id);
+            chain.push(shape.layer_id);
+            if inside.contains(&shape.id) {
+                populated.extend(chain);
+            } else {
+                outside.extend(chain);
+            }
+        }
+        let layers: Vec<LayerId> = self
+            .layers
+            .render_order()
+            .into_iter()
+            .filter(|layer| populated.contains(layer) && !outside.contains(layer))
+            .collect();
+
+        // MANDATE: Assertions
+        assert!(shapes.len() <= self.shapes.len());
+        Ok(ArtboardContents {
+            artboard: id,
+            layers,
+            shapes,
+        })
+    }
+
+    /// Count shapes.
+    /// MANDATE: ≤60 SLOC.
+    pub fn shape_count(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// Check layer exists and can hold shapes.
+    /// MANDATE: ≤60 SLOC, groups hold layers only.
+    fn validate_host(&self, layer_id: LayerId) -> Result<(), DocumentError> {
+        match self.layers.get_layer(layer_id) {
+            None => Err(DocumentError::LayerNotFound(layer_id)),
+            Some(layer) if layer.is_group => Err(DocumentError::InvalidValue(format!(
+                "layer {} is a group",
+                layer_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Check texture reference resolves.
+    /// MANDATE: ≤60 SLOC.
+    fn validate_texture(&self, texture: Option<&str>) -> Result<(), DocumentError> {
+        match texture {
+            Some(id) if !self.blobs.contains(id) => {
+                Err(DocumentError::InvalidValue(format!("missing blob {}", id)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// World-space bounds of shape as indexed.
+    /// MANDATE: ≤60 SLOC.
+    pub fn world_bounds(&self, id: ShapeId) -> Option<(Vec2, Vec2)> {
+        self.entries.get(&id).map(|entry| (entry.min, entry.max))
+    }
+
+    /// Apply mutation to shape and refresh its index entry.
+    /// MANDATE: ≤60 SLOC.
+    fn modify_shape<F>(&mut self, id: ShapeId, apply: F) -> Result<(), DocumentError>
+    where
+        F: FnOnce(&mut Shape),
+    {
+        let shape = self
+            .shapes
+            .get_mut(&id)
+            .ok_or(DocumentError::ShapeNotFound(id))?;
+
+        apply(shape);
+        self.reindex_shape(id);
+        Ok(())
+    }
+
+    /// Reindex every shape on layer and its descendants after a transform changed.
+    /// MANDATE: ≤60 SLOC.
+    fn reindex_layer(&mut self, id: LayerId) {
+        for shape_id in self.shapes_in_subtree(id) {
+            self.reindex_shape(shape_id);
+        }
+
+        // MANDATE: Assertions
+        assert_eq!(self.entries.len() + self.artboards.len(), self.index.len());
+    }
+
+    /// Replace the index entry of shape with its current world bounds.
+    /// MANDATE: ≤60 SLOC, single point of index maintenance.
+    fn reindex_shape(&mut self, id: ShapeId) {
+        let Some(shape) = self.shapes.get(&id) else {
+            return;
+        };
+        let layer_transform = self.layers.world_transform(shape.layer_id);
+        let entry = world_entry(shape, layer_transform);
+
+        if self.entries.get(&id) == Some(&entry) {
+            return;
+        }
+        if let Some(old_entry) = self.entries.insert(id, entry) {
+            let removed = self.index.remove_entry(&old_entry);
+            // MANDATE: Assertions
+            assert!(removed);
+        }
+        self.index.insert(entry);
+
+        assert_eq!(self.entries.len() + self.artboards.len(), self.index.len());
+    }
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stacking key: layer render rank, shape z-order, shape ID.
+/// MANDATE: ≤60 SLOC, total order over shapes.
+pub fn stacking_key(ranks: &HashMap<LayerId, usize>, shape: &Shape) -> (usize, i32, ShapeId) {
+    let rank = ranks.get(&shape.layer_id).copied().unwrap_or(usize::MAX);
+    (rank, shape.z_order, shape.id)
+}
+
+/// Build world-space spatial entry for artboard.
+/// MANDATE: ≤60 SLOC.
+fn artboard_entry(artboard: &Artboard) -> SpatialEntry {
+    SpatialEntry::with_kind(
+        artboard.id,
+        EntryKind::Artboard,
+        artboard.position,
+        artboard.max(),
+    )
+}
+
+/// Build world-space spatial entry for shape.
+/// MANDATE: ≤60 SLOC, AABB of the transformed corners.
+fn world_entry(shape: &Shape, layer_transform: Mat3) -> SpatialEntry {
+    let (min, max) = shape.world_bounds(layer_transform);
+    SpatialEntry::new(shape.id, min, max)
+}
+
+/// Copy decomposed placement into shape.
+/// MANDATE: ≤60 SLOC.
+fn place(shape: &mut Shape, placement: Placement) {
+    shape.position = placement.position;
+    shape.size = placement.size;
+    shape.rotation = placement.rotation;
+    shape.skew = placement.skew;
+}
+
+/// Wrap angle into [-PI, PI].
+/// MANDATE: ≤60 SLOC, no loops.
+fn wrap_angle(angle: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    let wrapped = (angle + PI).rem_euclid(TAU) - PI;
+
+    // MANDATE: Assertions
+    assert!((-PI..=PI).contains(&wrapped));
+    wrapped
+}
+
+/// Validate affine 2D matrix.
+/// MANDATE: ≤60 SLOC, finite, invertible, last row (0, 0, 1), bounded
+/// scale and translation.
+pub fn validate_affine(transform: Mat3) -> Result<(), DocumentError> {
+    let row = transform.row(2);
+    if !transform.is_finite() || row != glam::Vec3::Z {
+        return Err(DocumentError::InvalidValue("transform not affine".into()));
+    }
+    if transform.determinant().abs() <= f32::EPSILON {
+        return Err(DocumentError::InvalidValue(
+            "transform not invertible".into(),
+        ));
+    }
+    let scale = Vec2::new(
+        transform.x_axis.truncate().length(),
+        transform.y_axis.truncate().length(),
+    );
+    if scale.max_element() > MAX_SCALE {
+        return Err(DocumentError::InvalidValue(
+            "transform scale too large".into(),
+        ));
+    }
+    validate_position(transform.z_axis.truncate())
+}
+
+/// Validate artboard fields.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_artboard(artboard: &Artboard) -> Result<(), DocumentError> {
+    validate_name(&artboard.name, "artboard")?;
+    validate_position(artboard.position)?;
+    validate_size(artboard.size)?;
+    validate_color(artboard.background)
+}
+
+/// Validate shape position.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_position(position: Vec2) -> Result<(), DocumentError> {
+    if !position.is_finite() {
+        return Err(DocumentError::InvalidValue("position not finite".into()));
+    }
+    if position.abs().max_element() > MAX_COORDINATE {
+        return Err(DocumentError::InvalidValue("position out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate placement decomposed from a shape transform.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_placement(placement: &Placement) -> Result<(), DocumentError> {
+    validate_position(placement.position)?;
+    validate_size(placement.size)
+}
+
+/// Validate name of a layer or artboard, labelled by entity in the error.
+/// MANDATE: ≤60 SLOC, non-empty and bounded.
+pub fn validate_name(name: &str, entity: &str) -> Result<(), DocumentError> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(DocumentError::InvalidValue(format!("{entity} name length")));
+    }
+    Ok(())
+}
+
+/// Validate opacity.
+/// MANDATE: ≤60 SLOC, in [0, 1].
+pub fn validate_opacity(opacity: f32) -> Result<(), DocumentError> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(DocumentError::InvalidValue("opacity out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate RGBA color.
+/// MANDATE: ≤60 SLOC, components in [0, 1].
+pub fn validate_color(color: Vec4) -> Result<(), DocumentError> {
+    if !color.to_array().iter().all(|c| (0.0..=1.0).contains(c)) {
+        return Err(DocumentError::InvalidValue("color out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate z-order bound.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_z_order(z_order: i32) -> Result<(), DocumentError> {
+    if !(-MAX_Z_ORDER..=MAX_Z_ORDER).contains(&z_order) {
+        return Err(DocumentError::InvalidValue("z-order out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate shape rotation, skew and pivot.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_orientation(shape: &Shape) -> Result<(), DocumentError> {
+    validate_rotation(shape.rotation)?;
+    validate_skew(shape.skew)?;
+    validate_pivot(shape.pivot)
+}
+
+/// Validate rotation angle.
+/// MANDATE: ≤60 SLOC, within [-PI, PI].
+pub fn validate_rotation(rotation: f32) -> Result<(), DocumentError> {
+    let half_turn = std::f32::consts::PI;
+    if !(-half_turn..=half_turn).contains(&rotation) {
+        return Err(DocumentError::InvalidValue("rotation out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate skew angle.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_skew(skew: f32) -> Result<(), DocumentError> {
+    if !(-MAX_SKEW..=MAX_SKEW).contains(&skew) {
+        return Err(DocumentError::InvalidValue("skew out of range".into()));
+    }
+    Ok(())
+}
+
+/// Validate pivot.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_pivot(pivot: Vec2) -> Result<(), DocumentError> {
+    if !pivot.is_finite() {
+        return Err(DocumentError::InvalidValue("pivot not finite".into()));
+    }
+    Ok(())
+}
+
+/// Validate shape size.
+/// MANDATE: ≤60 SLOC.
+pub fn validate_size(size: Vec2) -> Result<(), DocumentError> {
+    if !size.is_finite() || size.x <= 0.0 || size.y <= 0.0 {
+        return Err(DocumentError::InvalidValue("size must be positive".into()));
+    }
+    if size.max_element() > MAX_COORDINATE {
+        return Err(DocumentError::InvalidValue("size out of range".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_with_layer() -> (Document, LayerId) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer 1".to_string()).unwrap();
+        (doc, layer)
+    }
+
+    #[test]
+    fn test_add_shape_indexes() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .unwrap();
+
+        assert_eq!(doc.shape_count(), 1);
+        assert_eq!(doc.query_point(Vec2::new(5.0, 5.0)), vec![id]);
+    }
+
+    #[test]
+    fn test_add_shape_missing_layer() {
+        let mut doc = Document::new();
+        let result = doc.add_shape(99, Vec2::ZERO, Vec2::ONE);
+        assert_eq!(result, Err(DocumentError::LayerNotFound(99)));
+    }
+
+    #[test]
+    fn test_add_shape_invalid_size() {
+        let (mut doc, layer) = doc_with_layer();
+        assert!(doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(0.0, 1.0))
+            .is_err());
+        assert!(doc.add_shape(layer, Vec2::NAN, Vec2::ONE).is_err());
+        assert_eq!(doc.shape_count(), 0);
+    }
+
+    #[test]
+    fn test_rejects_overflowing_magnitudes() {
+        let (mut doc, layer) = doc_with_layer();
+        assert!(doc.add_shape(layer, Vec2::splat(1e8), Vec2::ONE).is_err());
+        assert!(doc.add_shape(layer, Vec2::ZERO, Vec2::splat(1e20)).is_err());
+        let huge = Mat3::from_scale(Vec2::splat(1e20));
+        assert!(doc.set_layer_transform(layer, huge).is_err());
+        let far = Mat3::from_translation(Vec2::splat(1e8));
+        assert!(doc.set_layer_transform(layer, far).is_err());
+
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        let stretched = Mat3::from_scale(Vec2::new(1e8, 1.0));
+        assert!(doc.set_shape_transform(id, stretched).is_err());
+        assert_eq!(doc.shape(id).unwrap().size, Vec2::ONE);
+    }
+
+    #[test]
+    fn test_move_shape_reindexes() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .unwrap();
+
+        doc.move_shape(id, Vec2::new(100.0, 100.0)).unwrap();
+
+        assert!(doc.query_point(Vec2::new(5.0, 5.0)).is_empty());
+        assert_eq!(doc.query_point(Vec2::new(105.0, 105.0)), vec![id]);
+    }
+
+    #[test]
+    fn test_resize_shape_reindexes() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .unwrap();
+
+        doc.resize_shape(id, Vec2::new(50.0, 50.0)).unwrap();
+
+        assert_eq!(doc.query_point(Vec2::new(40.0, 40.0)), vec![id]);
+        assert!(doc.resize_shape(id, Vec2::new(-1.0, 5.0)).is_err());
+    }
+
+    #[test]
+    fn test_remove_shape() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+
+        let shape = doc.remove_shape(id).unwrap();
+        assert_eq!(shape.id, id);
+        assert!(doc.query_point(Vec2::new(0.5, 0.5)).is_empty());
+        assert!(matches!(
+            doc.remove_shape(id),
+            Err(DocumentError::ShapeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_layer_cascades() {
+        let (mut doc, layer1) = doc_with_layer();
+        let layer2 = doc.add_layer("Layer 2".to_string()).unwrap();
+        let a = doc.add_shape(layer1, Vec2::ZERO, Vec2::ONE).unwrap();
+        let b = doc.add_shape(layer1, Vec2::ZERO, Vec2::ONE).unwrap();
+        let c = doc.add_shape(layer2, Vec2::ZERO, Vec2::ONE).unwrap();
+
+        let removed = doc.remove_layer(layer1).unwrap();
+
+        assert_eq!(removed, vec![a, b]);
+        assert_eq!(doc.shape_count(), 1);
+        assert_eq!(doc.query_point(Vec2::new(0.5, 0.5)), vec![c]);
+        assert!(doc.layers().get_layer(layer1).is_none());
+    }
+
+    #[test]
+    fn test_set_shape_color_validates() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+
+        doc.set_shape_color(id, Vec4::new(1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        assert_eq!(doc.shape(id).unwrap().color, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        assert!(doc.set_shape_color(id, Vec4::splat(2.0)).is_err());
+    }
+
+    #[test]
+    fn test_layer_setters_validate() {
+        let (mut doc, layer) = doc_with_layer();
+
+        doc.set_layer_opacity(layer, 0.5).unwrap();
+        doc.set_layer_z_order(layer, 3).unwrap();
+        assert!(doc.set_layer_opacity(layer, 1.5).is_err());
+        assert!(doc.set_layer_z_order(layer, 20_000).is_err());
+        assert_eq!(
+            doc.set_layer_visible(42, false),
+            Err(DocumentError::LayerNotFound(42))
+        );
+
+        let stored = doc.layers().get_layer(layer).unwrap();
+        assert_eq!(stored.opacity, 0.5);
+        assert_eq!(stored.z_order, 3);
+    }
+
+    #[test]
+    fn test_hit_test_topmost_first() {
+        let (mut doc, bottom) = doc_with_layer();
+        let top = doc.add_layer("Top".to_string()).unwrap();
+        doc.set_layer_z_order(top, 5).unwrap();
+
+        let a = doc.add_shape(top, Vec2::ZERO, Vec2::ONE).unwrap();
+        let b = doc.add_shape(bottom, Vec2::ZERO, Vec2::ONE).unwrap();
+        let c = doc.add_shape(bottom, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_shape_z_order(b, 1).unwrap();
+
+        assert_eq!(doc.hit_test(Vec2::new(0.5, 0.5)), vec![a, b, c]);
+
+        doc.set_layer_visible(top, false).unwrap();
+        assert_eq!(doc.hit_test(Vec2::new(0.5, 0.5)), vec![b, c]);
+    }
+
+    #[test]
+    fn test_group_transform_reindexes_children() {
+        let (mut doc, layer) = doc_with_layer();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+
+        let shift = Mat3::from_translation(Vec2::new(100.0, 0.0));
+        doc.set_layer_transform(group, shift).unwrap();
+
+        assert_eq!(doc.query_point(Vec2::new(100.5, 0.5)), vec![id]);
+        assert!(doc.query_point(Vec2::new(0.5, 0.5)).is_empty());
+        assert!(doc.add_shape(group, Vec2::ZERO, Vec2::ONE).is_err());
+    }
+
+    #[test]
+    fn test_hit_test_follows_group_order() {
+        let (mut doc, inside) = doc_with_layer();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        let above = doc.add_layer("Above".to_string()).unwrap();
+        doc.set_layer_parent(inside, Some(group)).unwrap();
+        doc.set_layer_z_order(inside, 9_000).unwrap();
+
+        let a = doc.add_shape(inside, Vec2::ZERO, Vec2::ONE).unwrap();
+        let b = doc.add_shape(above, Vec2::ZERO, Vec2::ONE).unwrap();
+        assert_eq!(doc.hit_test(Vec2::splat(0.5)), vec![b, a]);
+
+        doc.set_layer_visible(group, false).unwrap();
+        assert_eq!(doc.hit_test(Vec2::splat(0.5)), vec![b]);
+    }
+
+    #[test]
+    fn test_shapes_in_subtree_covers_descendants() {
+        let (mut doc, layer) = doc_with_layer();
+        let other = doc.add_layer("Other".to_string()).unwrap();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        let a = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.add_shape(other, Vec2::ZERO, Vec2::ONE).unwrap();
+        let b = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+
+        assert_eq!(doc.shapes_in_subtree(group), vec![a, b]);
+        assert_eq!(doc.shapes_in_subtree(layer), vec![a, b]);
+    }
+
+    #[test]
+    fn test_remove_group_cascades() {
+        let (mut doc, layer) = doc_with_layer();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+
+        assert_eq!(doc.remove_layer(group).unwrap(), vec![id]);
+        assert!(doc.layers().is_empty());
+        assert_eq!(doc.shape_count(), 0);
+    }
+
+    #[test]
+    fn test_ungroup_rejects_invalid_composed_transform() {
+        let (mut doc, layer) = doc_with_layer();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+        let scale = Mat3::from_scale(Vec2::splat(1e4));
+        doc.set_layer_transform(group, scale).unwrap();
+        doc.set_layer_transform(layer, scale).unwrap();
+
+        assert!(doc.ungroup(group).is_err());
+        let child = doc.layers().get_layer(layer).unwrap();
+        assert_eq!((child.parent, child.transform), (Some(group), scale));
+    }
+
+    #[test]
+    fn test_rotated_shape_indexes_rotated_bounds() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .unwrap();
+
+        doc.set_shape_rotation(id, std::f32::consts::FRAC_PI_4)
+            .unwrap();
+        let (min, max) = doc.world_bounds(id).unwrap();
+
+        assert!(min.x < -2.0 && max.x > 12.0);
+        assert_eq!(doc.query_point(Vec2::new(-1.5, 5.0)), vec![id]);
+        assert!(doc.set_shape_rotation(id, f32::NAN).is_err());
+        assert!(doc.set_shape_skew(id, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_pivot_change_keeps_placement() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 4.0))
+            .unwrap();
+        doc.set_shape_rotation(id, 1.0).unwrap();
+        let before = doc.shape(id).unwrap().local_transform();
+
+        doc.set_shape_pivot(id, Vec2::ZERO).unwrap();
+        let after = doc.shape(id).unwrap().local_transform();
+
+        assert!(after.abs_diff_eq(before, 1e-4));
+        assert_ne!(doc.shape(id).unwrap().position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_rotation_wraps() {
+        let (mut doc, layer) = doc_with_layer();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+
+        doc.set_shape_rotation(id, 3.0 * std::f32::consts::PI / 2.0)
+            .unwrap();
+        let rotation = doc.shape(id).unwrap().rotation;
+        assert!((rotation + std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_artboards_share_index_with_shapes() {
+        let (mut doc, layer) = doc_with_layer();
+        let shape = doc.add_shape(layer, Vec2::ZERO, Vec2::ONE).unwrap();
+        let phone = doc
+            .add_artboard("Phone".into(), Vec2::new(-10.0, -10.0), Vec2::splat(100.0))
+            .unwrap();
+        let inner = doc
+            .add_artboard("Inner".into(), Vec2::splat(50.0), Vec2::splat(20.0))
+            .unwrap();
+
+        // Shape queries are unaffected; artboards have their own
+        assert_eq!(doc.query_point(Vec2::splat(0.5)), vec![shape]);
+        assert_eq!(
+            doc.query_artboards(Vec2::ZERO, Vec2::splat(60.0)),
+            vec![phone, inner]
+        );
+        assert_eq!(doc.artboard_at(Vec2::splat(55.0)), Some(inner));
+        assert_eq!(doc.artboard_at(Vec2::splat(5.0)), Some(phone));
+        assert_eq!(doc.artboard_at(Vec2::splat(500.0)), None);
+
+        let mut moved = doc.artboard(inner).unwrap().clone();
+        moved.position = Vec2::splat(1000.0);
+        doc.replace_artboard(moved).unwrap();
+        assert_eq!(doc.artboard_at(Vec2::splat(55.0)), Some(phone));
+        assert_eq!(doc.artboard_at(Vec2::splat(1010.0)), Some(inner));
+
+        doc.remove_artboard(phone).unwrap();
+        assert_eq!(doc.artboard_at(Vec2::splat(5.0)), None);
+        assert_eq!(doc.shape_count(), 1);
+        assert!(doc
+            .add_artboard("Bad".into(), Vec2::ZERO, Vec2::ZERO)
+            .is_err());
+        assert_eq!(
+            doc.add_artboard(String::new(), Vec2::ZERO, Vec2::ONE),
+            Err(DocumentError::InvalidValue("artboard name length".into()))
+        );
+        assert_eq!(
+            doc.remove_artboard(phone),
+            Err(DocumentError::ArtboardNotFound(phone))
+        );
+    }
+
+    #[test]
+    fn test_artboard_contents_by_containment() {
+        let (mut doc, inside) = doc_with_layer();
+        let straddling = doc.add_layer("Straddling".to_string()).unwrap();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        doc.set_layer_parent(inside, Some(group)).unwrap();
+        let board = doc
+            .add_artboard("Board".into(), Vec2::ZERO, Vec2::splat(100.0))
+            .unwrap();
+
+        let a = doc.add_shape(inside, Vec2::splat(10.0), Vec2::ONE).unwrap();
+        let b = doc
+            .add_shape(straddling, Vec2::splat(20.0), Vec2::ONE)
+            .unwrap();
+        let c = doc
+            .add_shape(straddling, Vec2::splat(99.0), Vec2::splat(5.0))
+            .unwrap();
+        // Crosses the edge but is centred inside
+        let d = doc
+            .add_shape(inside, Vec2::new(96.0, 50.0), Vec2::splat(6.0))
+            .unwrap();
+
+        let contents = doc.artboard_contents(board).unwrap();
+        assert_eq!(contents.shapes, vec![a, b, d]);
+        assert_eq!(contents.layers, vec![group, inside]);
+        assert_eq!(doc.shape_artboard(c), None);
+        assert_eq!(doc.shape_clip(d), Some((Vec2::ZERO, Vec2::splat(100.0))));
+
+        // Moving the centre past the edge takes a shape out of the artboard
+        doc.set_shape_rotation(a, 1.0).unwrap();
+        doc.move_shape(a, Vec2::new(-0.6, 10.0)).unwrap();
+        assert_eq!(doc.shape_artboard(a), None);
+
+        let mut unclipped = doc.artboard(board).unwrap().clone();
+        unclipped.clip_content = false;
+        doc.replace_artboard(unclipped).unwrap();
+        assert_eq!(doc.shape_clip(d), None);
+    }
+}
@@ -0,0 +1,185 @@
+// MANDATE: Built-in sRGB ICC profile for embedding in exported files
+#![deny(warnings)]
+
+/// Entries in the sampled sRGB tone curve.
+/// MANDATE: Fixed table size.
+const CURVE_POINTS: usize = 1024;
+
+/// D50 illuminant of the profile connection space.
+/// MANDATE: Fixed constant (ICC.1 7.2.16).
+const D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// sRGB white point, D65.
+/// MANDATE: Fixed constant.
+const D65: [f32; 3] = [0.9505, 1.0, 1.089];
+
+/// sRGB primaries adapted to D50 (Bradford), columns of the RGB-to-XYZ matrix.
+/// MANDATE: Fixed constant.
+const PRIMARIES: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.222_504_5, 0.013_932_2],
+    [0.385_064_9, 0.716_878_6, 0.097_104_5],
+    [0.143_080_4, 0.060_616_9, 0.714_173_3],
+];
+
+/// Profile description, also used as the PDF output condition.
+/// MANDATE: Fixed text.
+pub const SRGB_DESCRIPTION: &str = "sRGB IEC61966-2.1";
+
+/// ICC v2.1 display profile for sRGB: D50-adapted primaries, D65 white
+/// point and a sampled sRGB tone curve shared by all three channels.
+/// MANDATE: ≤60 SLOC, same bytes on every call.
+pub fn srgb_profile() -> Vec<u8> {
+    let curve = curve_tag();
+    let tags: [(&[u8; 4], Vec<u8>); 7] = [
+        (b"desc", desc_tag(SRGB_DESCRIPTION)),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(D65)),
+        (b"rXYZ", xyz_tag(PRIMARIES[0])),
+        (b"gXYZ", xyz_tag(PRIMARIES[1])),
+        (b"bXYZ", xyz_tag(PRIMARIES[2])),
+        (b"rTRC", curve),
+    ];
+
+    // Header, then the tag table; gTRC and bTRC point at the rTRC data
+    let table_len = 4 + 12 * (tags.len() + 2);
+    let mut data = Vec::new();
+    let mut table = Vec::new();
+    let mut curve_entry = (0, 0);
+    for (signature, bytes) in &tags {
+        let offset = (128 + table_len + data.len()) as u32;
+        table.push((**signature, offset, bytes.len() as u32));
+        if *signature == b"rTRC" {
+            curve_entry = (offset, bytes.len() as u32);
+        }
+        data.extend_from_slice(bytes);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    table.push((*b"gTRC", curve_entry.0, curve_entry.1));
+    table.push((*b"bTRC", curve_entry.0, curve_entry.1));
+
+    let size = 128 + table_len + data.len();
+    let mut profile = header(size as u32);
+    profile.extend_from_slice(&(table.len() as u32).to_be_bytes());
+    for (signature, offset, len) in &table {
+        profile.extend_from_slice(signature);
+        profile.extend_from_slice(&offset.to_be_bytes());
+        profile.extend_from_slice(&len.to_be_bytes());
+    }
+    profile.extend_from_slice(&data);
+
+    // MANDATE: Assertions
+    assert_eq!(profile.len(), size);
+    assert_eq!(profile.len() % 4, 0);
+    profile
+}
+
+/// 128-byte profile header.
+/// MANDATE: ≤60 SLOC, fixed creation date so output is deterministic.
+fn header(size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(128);
+    header.extend_from_slice(&size.to_be_bytes());
+    header.extend_from_slice(&[0; 4]); // preferred CMM
+    header.extend_from_slice(&[2, 0x10, 0, 0]); // version 2.1
+    header.extend_from_slice(b"mntrRGB XYZ ");
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        header.extend_from_slice(&field.to_be_bytes());
+    }
+    header.extend_from_slice(b"acsp");
+    header.extend_from_slice(&[0; 28]); // platform, flags, device, attributes, intent
+    header.extend_from_slice(&xyz_number(D50));
+    header.resize(128, 0);
+
+    // MANDATE: Assertions
+    assert_eq!(&header[36..40], b"acsp");
+    assert_eq!(header.len(), 128);
+    header
+}
+
+/// `XYZType` tag data.
+/// MANDATE: ≤60 SLOC.
+fn xyz_tag(xyz: [f32; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    tag.extend_from_slice(&xyz_number(xyz));
+    tag
+}
+
+/// Three s15Fixed16 numbers.
+/// MANDATE: ≤60 SLOC.
+fn xyz_number(xyz: [f32; 3]) -> Vec<u8> {
+    xyz.iter()
+        .flat_map(|v| ((f64::from(*v) * 65536.0).round() as i32).to_be_bytes())
+        .collect()
+}
+
+/// `curveType` tag sampling the sRGB decoding function.
+/// MANDATE: ≤60 SLOC, bounded by `CURVE_POINTS`.
+fn curve_tag() -> Vec<u8> {
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(CURVE_POINTS as u32).to_be_bytes());
+    for i in 0..CURVE_POINTS {
+        let c = i as f64 / (CURVE_POINTS - 1) as f64;
+        let linear = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+        tag.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    tag
+}
+
+/// `textType` tag.
+/// MANDATE: ≤60 SLOC, ASCII text.
+fn text_tag(text: &str) -> Vec<u8> {
+    // MANDATE: Assertions
+    assert!(text.is_ascii());
+    let mut tag = b"text\0\0\0\0".to_vec();
+    tag.extend_from_slice(text.as_bytes());
+    tag.push(0);
+    tag
+}
+
+/// `textDescriptionType` tag with ASCII text only.
+/// MANDATE: ≤60 SLOC, ASCII text.
+fn desc_tag(text: &str) -> Vec<u8> {
+    // MANDATE: Assertions
+    assert!(text.is_ascii());
+    let mut tag = b"desc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+    tag.extend_from_slice(text.as_bytes());
+    tag.push(0);
+    // Empty Unicode (language code, count) and ScriptCode (code, count, 67 bytes) parts
+    tag.extend_from_slice(&[0; 8]);
+    tag.extend_from_slice(&[0; 3]);
+    tag.extend_from_slice(&[0; 67]);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn be_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn test_srgb_profile_layout() {
+        let profile = srgb_profile();
+        assert_eq!(be_u32(&profile, 0) as usize, profile.len());
+        assert_eq!(&profile[12..24], b"mntrRGB XYZ ");
+        assert_eq!(be_u32(&profile, 128), 9);
+        assert_eq!(profile, srgb_profile());
+
+        // Every tag lies inside the profile and starts with its type signature
+        for i in 0..9 {
+            let entry = 132 + 12 * i;
+            let (offset, len) = (be_u32(&profile, entry + 4), be_u32(&profile, entry + 8));
+            assert!((offset + len) as usize <= profile.len());
+            let kind = &profile[offset as usize..offset as usize + 4];
+            assert!([b"desc", b"text", b"XYZ ", b"curv"]
+                .iter()
+                .any(|k| k == &kind));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// MANDATE: JPEG export with background flattening and DPI metadata
+#![deny(warnings)]
+
+use super::raster::{render_image, Background, RasterImage, RasterOptions};
+use super::{write_file, ExportError};
+use crate::document::Document;
+use crate::selection::Selection;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Default JPEG quality.
+/// MANDATE: Fixed constant.
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Background used when a JPEG export asks for transparency.
+/// MANDATE: Fixed constant.
+pub const DEFAULT_MATTE: Vec3 = Vec3::ONE;
+
+/// JPEG export settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JpegOptions {
+    #[serde(default, flatten)]
+    pub raster: RasterOptions,
+    /// 0 (smallest) to 100 (best).
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self {
+            raster: RasterOptions::default(),
+            quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+}
+
+/// Serde default for `JpegOptions::quality`.
+/// MANDATE: ≤60 SLOC.
+fn default_quality() -> u8 {
+    DEFAULT_JPEG_QUALITY
+}
+
+/// Raster options with a transparent background replaced by the default matte,
+/// since JPEG has no alpha channel.
+/// MANDATE: ≤60 SLOC.
+pub fn opaque_options(options: &RasterOptions) -> RasterOptions {
+    let background = match options.background {
+        Background::Transparent => Background::Solid(DEFAULT_MATTE),
+        solid => solid,
+    };
+    RasterOptions {
+        background,
+        ..*options
+    }
+}
+
+/// Encode image as a baseline JFIF JPEG, compositing any translucent pixels
+/// over white.
+/// MANDATE: ≤60 SLOC, error on a bad buffer or quality above 100.
+pub fn encode_jpeg(image: &RasterImage, quality: u8) -> Result<Vec<u8>, ExportError> {
+    // MANDATE: Input validation
+    if quality > 100 {
+        return Err(ExportError::InvalidOption(format!(
+            "JPEG quality {} outside 0..=100",
+            quality
+        )));
+    }
+    image.check()?;
+    let (Ok(width), Ok(height)) = (u16::try_from(image.width), u16::try_from(image.height)) else {
+        return Err(ExportError::InvalidOption(format!(
+            "{}x{} too large for JPEG",
+            image.width, image.height
+        )));
+    };
+
+    let rgb = flatten(&image.pixels);
+    let mut bytes = Vec::new();
+    // The encoder's scale starts at 1
+    let mut encoder = jpeg_encoder::Encoder::new(&mut bytes, quality.max(1));
+    let dpi = image.dpi().round().clamp(1.0, f32::from(u16::MAX)) as u16;
+    encoder.set_density(jpeg_encoder::PixelDensity::dpi(dpi));
+    encoder
+        .encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|err| ExportError::Encode(err.to_string()))?;
+
+    // MANDATE: Assertions
+    assert!(bytes.starts_with(&[0xFF, 0xD8]));
+    Ok(bytes)
+}
+
+/// Straight-alpha sRGB RGBA8 composited over white, as RGB8.
+/// MANDATE: ≤60 SLOC.
+fn flatten(pixels: &[u8]) -> Vec<u8> {
+    // MANDATE: Assertions
+    assert_eq!(pixels.len() % 4, 0);
+
+    let mut rgb = Vec::with_capacity(pixels.len() / 4 * 3);
+    for pixel in pixels.chunks_exact(4) {
+        let alpha = u32::from(pixel[3]);
+        for &channel in &pixel[..3] {
+            let mixed = (u32::from(channel) * alpha + 255 * (255 - alpha) + 127) / 255;
+            rgb.push(mixed as u8);
+        }
+    }
+    rgb
+}
+
+/// Render the export area over an opaque background and write it as a JPEG file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_jpeg(
+    doc: &Document,
+    selection: &Selection,
+    options: &JpegOptions,
+    path: &Path,
+) -> Result<RasterImage, ExportError> {
+    let image = render_image(doc, selection, &opaque_options(&options.raster))?;
+    let bytes = encode_jpeg(&image, options.quality)?;
+    write_file(path, &bytes)?;
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(pixels: Vec<u8>, width: u32) -> RasterImage {
+        RasterImage {
+            width,
+            height: pixels.len() as u32 / 4 / width,
+            scale: 300.0 / 72.0,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_encode_jpeg_header() {
+        let bytes = encode_jpeg(&image(vec![200; 8 * 8 * 4], 8), 90).unwrap();
+        assert!(bytes.ends_with(&[0xFF, 0xD9]));
+        // APP0 JFIF segment with density in dots per inch
+        assert_eq!(&bytes[2..4], &[0xFF, 0xE0]);
+        assert_eq!(&bytes[6..11], b"JFIF\0");
+        assert_eq!(bytes[13], 1);
+        assert_eq!(u16::from_be_bytes([bytes[14], bytes[15]]), 300);
+
+        // Lower quality gives a smaller file for the same noisy content
+        let noise: Vec<u8> = (0..32 * 32 * 4).map(|i| (i * 37 % 251) as u8).collect();
+        let small = encode_jpeg(&image(noise.clone(), 32), 0).unwrap();
+        let large = encode_jpeg(&image(noise, 32), 100).unwrap();
+        assert!(small.len() < large.len());
+        assert!(encode_jpeg(&image(vec![0; 4], 1), 101).is_err());
+    }
+
+    #[test]
+    fn test_flatten_and_opaque_options() {
+        let rgb = flatten(&[255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 128]);
+        assert_eq!(rgb, vec![255, 0, 0, 255, 255, 255, 127, 127, 127]);
+
+        let options = opaque_options(&RasterOptions::default());
+        assert_eq!(options.background, Background::Solid(Vec3::ONE));
+        let black = RasterOptions {
+            background: Background::Solid(Vec3::ZERO),
+            ..RasterOptions::default()
+        };
+        assert_eq!(opaque_options(&black), black);
+    }
+}
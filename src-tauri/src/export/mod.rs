@@ -0,0 +1,211 @@
+// MANDATE: Document export to image and vector formats
+#![deny(warnings)]
+
+pub mod icc;
+pub mod jpeg;
+pub mod pdf;
+pub mod png;
+pub mod preset;
+pub mod raster;
+pub mod svg;
+pub mod webp;
+
+use crate::artboard::ArtboardId;
+use crate::document::Document;
+use crate::selection::{Bounds, Selection};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Export errors.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ExportError {
+    #[error("Nothing to export: {0}")]
+    Empty(String),
+    #[error("Invalid export option: {0}")]
+    InvalidOption(String),
+    #[error("Render error: {0}")]
+    Render(String),
+    #[error("Encoding error: {0}")]
+    Encode(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+/// Part of the document to export.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportArea {
+    /// Bounds of everything visible.
+    #[default]
+    Document,
+    /// World-space rectangle.
+    Rect { min: Vec2, max: Vec2 },
+    /// Bounds of the current selection.
+    Selection,
+    /// Rectangle of one artboard.
+    Artboard { id: ArtboardId },
+}
+
+/// World-space bounds of the export area.
+/// MANDATE: ≤60 SLOC, error on an empty or degenerate area.
+pub fn export_bounds(
+    doc: &Document,
+    selection: &Selection,
+    area: ExportArea,
+) -> Result<Bounds, ExportError> {
+    let bounds = match area {
+        ExportArea::Document => visible_bounds(doc),
+        ExportArea::Selection => selection.bounds(doc),
+        ExportArea::Artboard { id } => {
+            let artboard = doc
+                .artboard(id)
+                .ok_or_else(|| ExportError::InvalidOption(format!("no artboard {}", id)))?;
+            Some(Bounds {
+                min: artboard.position,
+                max: artboard.max(),
+            })
+        }
+        ExportArea::Rect { min, max } => {
+            // MANDATE: Input validation
+            if !min.is_finite() || !max.is_finite() {
+                return Err(ExportError::InvalidOption("rect not finite".into()));
+            }
+            Some(Bounds { min, max })
+        }
+    };
+
+    let bounds = bounds.ok_or_else(|| ExportError::Empty(format!("{:?} is empty", area)))?;
+    if bounds.min.x >= bounds.max.x || bounds.min.y >= bounds.max.y {
+        return Err(ExportError::Empty(format!("{:?} has no area", area)));
+    }
+
+    // MANDATE: Assertions
+    assert!(bounds.min.x < bounds.max.x && bounds.min.y < bounds.max.y);
+    Ok(bounds)
+}
+
+/// Write bytes to path via a sibling temp file, so a failed export keeps the old file.
+/// MANDATE: ≤60 SLOC, temp file removed on failure.
+pub fn write_file(path: &Path, bytes: &[u8]) -> Result<(), ExportError> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let result = std::fs::write(&tmp_path, bytes).and_then(|()| std::fs::rename(&tmp_path, path));
+    if result.is_err() {
+        // Best effort: the temp file does not exist if creating it failed
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result.map_err(|e| ExportError::Io(e.to_string()))
+}
+
+/// Number rounded to 4 decimals in its shortest form, never `-0`.
+/// MANDATE: ≤60 SLOC, deterministic formatting.
+pub fn number(value: f32) -> String {
+    let rounded = (f64::from(value) * 1e4).round() / 1e4;
+    if rounded == 0.0 {
+        "0".into()
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// Union of artboard rectangles and world bounds of shapes on visible layers.
+/// MANDATE: ≤60 SLOC, bounded by the shape and artboard counts.
+pub fn visible_bounds(doc: &Document) -> Option<Bounds> {
+    let layers = doc.layers();
+    let artboards = doc
+        .artboards()
+        .into_iter()
+        .map(|artboard| (artboard.position, artboard.max()));
+    let shapes = doc
+        .all_shapes()
+        .into_iter()
+        .filter(|shape| {
+            layers.is_visible(shape.layer_id) && layers.effective_opacity(shape.layer_id) > 0.0
+        })
+        .filter_map(|shape| doc.world_bounds(shape.id));
+
+    let mut bounds: Option<Bounds> = None;
+    for (min, max) in artboards.chain(shapes) {
+        bounds = Some(match bounds {
+            Some(b) => Bounds {
+                min: b.min.min(min),
+                max: b.max.max(max),
+            },
+            None => Bounds { min, max },
+        });
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_bounds_areas() {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let hidden = doc.add_layer("Hidden".into()).unwrap();
+        let a = doc.add_shape(layer, Vec2::ZERO, Vec2::splat(10.0)).unwrap();
+        doc.add_shape(layer, Vec2::new(20.0, 5.0), Vec2::splat(10.0))
+            .unwrap();
+        doc.add_shape(hidden, Vec2::splat(-100.0), Vec2::ONE)
+            .unwrap();
+        doc.set_layer_visible(hidden, false).unwrap();
+
+        let mut selection = Selection::new();
+        let whole = export_bounds(&doc, &selection, ExportArea::Document).unwrap();
+        assert_eq!((whole.min, whole.max), (Vec2::ZERO, Vec2::new(30.0, 15.0)));
+
+        assert!(export_bounds(&doc, &selection, ExportArea::Selection).is_err());
+        selection.add(&[crate::selection::SelectionItem::Shape(a)]);
+        let selected = export_bounds(&doc, &selection, ExportArea::Selection).unwrap();
+        assert_eq!(selected.max, Vec2::splat(10.0));
+
+        // Artboards frame their own area and widen the document's
+        let board = doc
+            .add_artboard("Board".into(), Vec2::new(-5.0, 0.0), Vec2::splat(8.0))
+            .unwrap();
+        let framed = export_bounds(&doc, &selection, ExportArea::Artboard { id: board }).unwrap();
+        assert_eq!(
+            (framed.min, framed.max),
+            (Vec2::new(-5.0, 0.0), Vec2::new(3.0, 8.0))
+        );
+        let whole = export_bounds(&doc, &selection, ExportArea::Document).unwrap();
+        assert_eq!(whole.min, Vec2::new(-5.0, 0.0));
+        let missing = ExportArea::Artboard { id: board + 1 };
+        assert!(matches!(
+            export_bounds(&doc, &selection, missing),
+            Err(ExportError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_bounds_rejects_degenerate() {
+        let doc = Document::new();
+        let selection = Selection::new();
+        assert!(export_bounds(&doc, &selection, ExportArea::Document).is_err());
+
+        let flat = ExportArea::Rect {
+            min: Vec2::ZERO,
+            max: Vec2::new(10.0, 0.0),
+        };
+        assert!(export_bounds(&doc, &selection, flat).is_err());
+    }
+
+    #[test]
+    fn test_failed_write_removes_temp_file() {
+        // Renaming a file over a directory fails after the temp file is written
+        let path = std::env::temp_dir().join(format!("export-dir-{}.png", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+
+        let result = write_file(&path, b"data");
+        let leftover = Path::new(&format!("{}.tmp", path.display())).exists();
+        std::fs::remove_dir(&path).unwrap();
+
+        assert!(matches!(result, Err(ExportError::Io(_))));
+        assert!(!leftover);
+    }
+}
@@ -0,0 +1,613 @@
+// MANDATE: Vector PDF export with an embedded sRGB profile
+#![deny(warnings)]
+
+use super::icc::{srgb_profile, SRGB_DESCRIPTION};
+use super::raster::linear_to_srgb;
+use super::{export_bounds, number, visible_bounds, write_file, ExportArea, ExportError};
+use crate::document::Document;
+use crate::layer::{Layer, LayerId};
+use crate::selection::{Bounds, Selection};
+use crate::shape::Shape;
+use glam::{Mat3, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Largest page side in points, the PDF 1.4 implementation limit.
+/// MANDATE: Bounded page size.
+pub const MAX_PAGE_SIZE: f32 = 14_400.0;
+
+/// Fixed object numbers; forms and pages follow.
+/// MANDATE: Written once per file.
+const CATALOG: u32 = 1;
+const PAGES: u32 = 2;
+const PROFILE: u32 = 3;
+const RESOURCES: u32 = 4;
+const SCENE: u32 = 5;
+
+/// PDF export settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PdfOptions {
+    /// World rectangle of the single page.
+    #[serde(default)]
+    pub area: ExportArea,
+}
+
+/// Export the chosen area as a one-page PDF.
+/// MANDATE: ≤60 SLOC.
+pub fn to_pdf(
+    doc: &Document,
+    selection: &Selection,
+    options: &PdfOptions,
+) -> Result<Vec<u8>, ExportError> {
+    let page = export_bounds(doc, selection, options.area)?;
+    write_pdf(doc, &[page])
+}
+
+/// Export the chosen area as a PDF file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_pdf(
+    doc: &Document,
+    selection: &Selection,
+    options: &PdfOptions,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let pdf = to_pdf(doc, selection, options)?;
+    write_file(path, &pdf)
+}
+
+/// Export every artboard as a page, in artboard order.
+/// MANDATE: ≤60 SLOC.
+pub fn to_artboards_pdf(doc: &Document) -> Result<Vec<u8>, ExportError> {
+    let pages: Vec<Bounds> = doc
+        .artboards()
+        .into_iter()
+        .map(|artboard| Bounds {
+            min: artboard.position,
+            max: artboard.max(),
+        })
+        .collect();
+    // MANDATE: Input validation
+    if pages.is_empty() {
+        return Err(ExportError::Empty("document has no artboards".into()));
+    }
+    write_pdf(doc, &pages)
+}
+
+/// Export every artboard as a page of one PDF file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_artboards_pdf(doc: &Document, path: &Path) -> Result<(), ExportError> {
+    let pdf = to_artboards_pdf(doc)?;
+    write_file(path, &pdf)
+}
+
+/// Write the visible scene once, as a form every page draws through its
+/// own world rectangle (1 world unit = 1 pt). Layers with opacity below 1
+/// become transparency groups; colours are in the embedded sRGB profile,
+/// which is also the output intent.
+/// MANDATE: ≤60 SLOC, bounded by the page and layer counts, deterministic bytes.
+pub fn write_pdf(doc: &Document, pages: &[Bounds]) -> Result<Vec<u8>, ExportError> {
+    // MANDATE: Input validation
+    if pages.is_empty() {
+        return Err(ExportError::Empty("no pages".into()));
+    }
+    for page in pages {
+        let size = page.max - page.min;
+        if !(size.min_element() > 0.0 && size.max_element() <= MAX_PAGE_SIZE) {
+            return Err(ExportError::InvalidOption(format!(
+                "page {}x{} pt outside (0, {}]",
+                size.x, size.y, MAX_PAGE_SIZE
+            )));
+        }
+    }
+
+    let mut writer = PdfWriter::new();
+    let mut scene = Scene::new(doc, &mut writer);
+    let content = scene.content();
+    let bounds = visible_bounds(doc).map_or([0.0; 4], |b| [b.min.x, b.min.y, b.max.x, b.max.y]);
+    let resources = scene.resources();
+    writer.form(SCENE, bounds, false, content.as_bytes());
+    writer.object(RESOURCES, &resources);
+
+    let profile = srgb_profile();
+    writer.stream(PROFILE, "/N 3 /Alternate /DeviceRGB", &profile);
+    let kids: Vec<u32> = pages
+        .iter()
+        .map(|page| write_page(&mut writer, page))
+        .collect();
+    let kid_refs: Vec<String> = kids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.object(
+        PAGES,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kid_refs.join(" "),
+            kids.len()
+        ),
+    );
+    writer.object(
+        CATALOG,
+        &format!(
+            "<< /Type /Catalog /Pages {} 0 R /OutputIntents [<< /Type /OutputIntent /S /GTS_PDFA1 \
+             /OutputConditionIdentifier ({}) /DestOutputProfile {} 0 R >>] >>",
+            PAGES, SRGB_DESCRIPTION, PROFILE
+        ),
+    );
+
+    // MANDATE: Assertions
+    assert_eq!(kids.len(), pages.len());
+    writer.finish()
+}
+
+/// Write a page drawing the scene form through page, returning its object number.
+/// The form is drawn in a clip group of the page rectangle, so content outside
+/// the page is cut rather than only cropped by the MediaBox.
+/// MANDATE: ≤60 SLOC.
+fn write_page(writer: &mut PdfWriter, page: &Bounds) -> u32 {
+    let size = page.max - page.min;
+    let content = writer.reserve();
+    let id = writer.reserve();
+    let ops = format!(
+        "q 0 0 {} {} re W n 1 0 0 1 {} {} cm /Fm{} Do Q\n",
+        number(size.x),
+        number(size.y),
+        number(-page.min.x),
+        number(-page.min.y),
+        SCENE
+    );
+    writer.stream(content, "", ops.as_bytes());
+    writer.object(
+        id,
+        &format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} 0 R /Contents {} 0 R \
+             /Group << /S /Transparency /CS [/ICCBased {} 0 R] >> >>",
+            PAGES,
+            number(size.x),
+            number(size.y),
+            RESOURCES,
+            content,
+            PROFILE
+        ),
+    );
+    id
+}
+
+/// Scene content builder: collects the forms and graphics states it uses.
+struct Scene<'a> {
+    doc: &'a Document,
+    writer: &'a mut PdfWriter,
+    /// Graphics state name index by alpha bits, sorted for stable output.
+    states: BTreeMap<u32, usize>,
+    /// Object numbers of transparency group forms, in creation order.
+    forms: Vec<u32>,
+}
+
+impl<'a> Scene<'a> {
+    /// Create builder writing forms into writer.
+    /// MANDATE: ≤60 SLOC.
+    fn new(doc: &'a Document, writer: &'a mut PdfWriter) -> Self {
+        Self {
+            doc,
+            writer,
+            states: BTreeMap::new(),
+            forms: Vec::new(),
+        }
+    }
+
+    /// Operators drawing artboard backgrounds, then visible layers bottom to
+    /// top. Layers are visited in reverse render order, so every child is
+    /// finished before its parent.
+    /// MANDATE: ≤60 SLOC, bounded by the layer count, no recursion.
+    fn content(&mut self) -> String {
+        let tree = self.doc.layers();
+        let mut finished: HashMap<LayerId, String> = HashMap::new();
+        for id in tree.render_order().into_iter().rev() {
+            // Hidden ancestors hide the subtree, so no entry is left unclaimed
+            let Some(layer) = tree.get_layer(id).filter(|_| tree.is_visible(id)) else {
+                continue;
+            };
+            let mut body = String::new();
+            for shape in self.doc.shapes_in_layer(id) {
+                body.push_str(&self.shape_ops(shape));
+            }
+            for child in tree.children(Some(id)) {
+                body.push_str(&finished.remove(&child.id).unwrap_or_default());
+            }
+            if !body.is_empty() {
+                let ops = self.layer_ops(layer, body);
+                finished.insert(id, ops);
+            }
+        }
+
+        let mut content = String::new();
+        for artboard in self.doc.artboards() {
+            let rect = Mat3::from_scale_angle_translation(artboard.size, 0.0, artboard.position);
+            content.push_str(&self.fill_ops(rect, artboard.background));
+        }
+        for layer in tree.children(None) {
+            content.push_str(&finished.remove(&layer.id).unwrap_or_default());
+        }
+
+        // MANDATE: Assertions
+        assert!(finished.is_empty());
+        content
+    }
+
+    /// Wrap layer body in its transform, as a transparency group when translucent.
+    /// MANDATE: ≤60 SLOC.
+    fn layer_ops(&mut self, layer: &Layer, body: String) -> String {
+        let transform = if layer.transform == Mat3::IDENTITY {
+            String::new()
+        } else {
+            format!("{} cm ", matrix(layer.transform))
+        };
+        if layer.opacity >= 1.0 {
+            return format!("q {}\n{}Q\n", transform, body);
+        }
+
+        // Group bounds in layer space: everything visible, mapped back
+        let world = self.doc.layers().world_transform(layer.id);
+        let bbox = visible_bounds(self.doc).map_or([0.0; 4], |b| local_box(world.inverse(), &b));
+        let form = self.writer.reserve();
+        self.writer.form(form, bbox, true, body.as_bytes());
+        self.forms.push(form);
+        let state = self.state(layer.opacity);
+        format!("q {}/GS{} gs /Fm{} Do Q\n", transform, state, form)
+    }
+
+    /// Operators filling a shape's quad in its colour, inside a clip group
+    /// of its artboard when that clips content.
+    /// MANDATE: ≤60 SLOC.
+    fn shape_ops(&mut self, shape: &Shape) -> String {
+        let ops = self.fill_ops(shape.local_transform(), shape.color);
+        let Some((min, max)) = self.doc.shape_clip(shape.id) else {
+            return ops;
+        };
+        // The clip rect is in world space: leave the layer's space, clip, return
+        let world = self.doc.layers().world_transform(shape.layer_id);
+        let size = max - min;
+        format!(
+            "q {} cm {} {} {} {} re W n {} cm\n{}Q\n",
+            matrix(world.inverse()),
+            number(min.x),
+            number(min.y),
+            number(size.x),
+            number(size.y),
+            matrix(world),
+            ops
+        )
+    }
+
+    /// Operators filling the unit square mapped by transform in a linear colour.
+    /// MANDATE: ≤60 SLOC.
+    fn fill_ops(&mut self, transform: Mat3, color: Vec4) -> String {
+        let [r, g, b] =
+            [color.x, color.y, color.z].map(|c| number(f32::from(linear_to_srgb(c)) / 255.0));
+        let alpha = if color.w < 1.0 {
+            format!("/GS{} gs ", self.state(color.w))
+        } else {
+            String::new()
+        };
+        format!(
+            "q {} cm {}/CS0 cs {} {} {} sc 0 0 1 1 re f Q\n",
+            matrix(transform),
+            alpha,
+            r,
+            g,
+            b
+        )
+    }
+
+    /// Graphics state index for a constant alpha.
+    /// MANDATE: ≤60 SLOC.
+    fn state(&mut self, alpha: f32) -> usize {
+        let next = self.states.len();
+        *self.states.entry(alpha.to_bits()).or_insert(next)
+    }
+
+    /// Shared resource dictionary for pages and forms.
+    /// MANDATE: ≤60 SLOC.
+    fn resources(&self) -> String {
+        let mut states = String::new();
+        for (bits, index) in &self.states {
+            let alpha = number(f32::from_bits(*bits));
+            states.push_str(&format!(
+                " /GS{} << /Type /ExtGState /ca {} /CA {} >>",
+                index, alpha, alpha
+            ));
+        }
+        let mut forms = format!(" /Fm{} {} 0 R", SCENE, SCENE);
+        for form in &self.forms {
+            forms.push_str(&format!(" /Fm{} {} 0 R", form, form));
+        }
+        format!(
+            "<< /ColorSpace << /CS0 [/ICCBased {} 0 R] >> /ExtGState <<{} >> /XObject <<{} >> >>",
+            PROFILE, states, forms
+        )
+    }
+}
+
+/// Bounding box of world bounds in a layer's space.
+/// MANDATE: ≤60 SLOC.
+fn local_box(to_local: Mat3, bounds: &Bounds) -> [f32; 4] {
+    let corners = [
+        bounds.min,
+        Vec2::new(bounds.max.x, bounds.min.y),
+        bounds.max,
+        Vec2::new(bounds.min.x, bounds.max.y),
+    ]
+    .map(|c| to_local.transform_point2(c));
+    let min = corners.iter().fold(Vec2::INFINITY, |acc, c| acc.min(*c));
+    let max = corners
+        .iter()
+        .fold(Vec2::NEG_INFINITY, |acc, c| acc.max(*c));
+    [min.x, min.y, max.x, max.y]
+}
+
+/// PDF `a b c d e f` operands of an affine transform.
+/// MANDATE: ≤60 SLOC.
+fn matrix(m: Mat3) -> String {
+    [
+        m.x_axis.x, m.x_axis.y, m.y_axis.x, m.y_axis.y, m.z_axis.x, m.z_axis.y,
+    ]
+    .map(number)
+    .join(" ")
+}
+
+/// Minimal PDF file writer: numbered objects, then the cross-reference table.
+#[derive(Debug)]
+struct PdfWriter {
+    bytes: Vec<u8>,
+    /// Byte offset of each object, indexed by object number - 1.
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    /// Start a file with the fixed objects reserved.
+    /// MANDATE: ≤60 SLOC.
+    fn new() -> Self {
+        Self {
+            // Binary comment marks the file as 8-bit for transfer tools
+            bytes: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: vec![None; SCENE as usize],
+        }
+    }
+
+    /// Reserve the next object number.
+    /// MANDATE: ≤60 SLOC.
+    fn reserve(&mut self) -> u32 {
+        self.offsets.push(None);
+        self.offsets.len() as u32
+    }
+
+    /// Write a dictionary object.
+    /// MANDATE: ≤60 SLOC, each object written once.
+    fn object(&mut self, id: u32, body: &str) {
+        self.begin(id);
+        self.bytes.extend_from_slice(body.as_bytes());
+        self.bytes.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Write a stream object with extra dictionary entries.
+    /// MANDATE: ≤60 SLOC, each object written once.
+    fn stream(&mut self, id: u32, entries: &str, data: &[u8]) {
+        self.begin(id);
+        let separator = if entries.is_empty() { "" } else { " " };
+        let dict = format!(
+            "<< {}{}/Length {} >>\nstream\n",
+            entries,
+            separator,
+            data.len()
+        );
+        self.bytes.extend_from_slice(dict.as_bytes());
+        self.bytes.extend_from_slice(data);
+        self.bytes.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    /// Write a form XObject, optionally an isolated transparency group.
+    /// MANDATE: ≤60 SLOC.
+    fn form(&mut self, id: u32, bbox: [f32; 4], group: bool, content: &[u8]) {
+        let mut entries = format!(
+            "/Type /XObject /Subtype /Form /BBox [{}] /Resources {} 0 R",
+            bbox.map(number).join(" "),
+            RESOURCES
+        );
+        if group {
+            entries.push_str(&format!(
+                " /Group << /S /Transparency /CS [/ICCBased {} 0 R] >>",
+                PROFILE
+            ));
+        }
+        self.stream(id, &entries, content);
+    }
+
+    /// Record the offset of an object and write its header.
+    /// MANDATE: ≤60 SLOC.
+    fn begin(&mut self, id: u32) {
+        let slot = &mut self.offsets[id as usize - 1];
+        // MANDATE: Assertions
+        assert!(slot.is_none(), "object {} written twice", id);
+        *slot = Some(self.bytes.len());
+        self.bytes
+            .extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+    }
+
+    /// Append the cross-reference table and trailer.
+    /// MANDATE: ≤60 SLOC, every reserved object written.
+    fn finish(mut self) -> Result<Vec<u8>, ExportError> {
+        let xref = self.bytes.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for (index, offset) in self.offsets.iter().enumerate() {
+            let offset = offset.ok_or_else(|| {
+                ExportError::Encode(format!(
+                    "PDF object {} reserved but never written",
+                    index + 1
+                ))
+            })?;
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            CATALOG,
+            xref
+        ));
+        self.bytes.extend_from_slice(table.as_bytes());
+        Ok(self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    /// Check the cross-reference table against the file and return it as text.
+    fn parse(pdf: &[u8]) -> String {
+        let tail = String::from_utf8(pdf[pdf.len() - 64..].to_vec()).unwrap();
+        let start: usize = tail
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let table = String::from_utf8(pdf[start..].to_vec()).unwrap();
+        assert!(table.starts_with("xref\n"));
+
+        let mut lines = table.lines().skip(1);
+        let count: usize = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        for (id, line) in lines.skip(1).take(count - 1).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", id + 1).as_bytes()));
+        }
+        let text = String::from_utf8_lossy(pdf).into_owned();
+        assert_eq!(text.matches(" 0 obj\n").count(), count - 1);
+        text
+    }
+
+    fn scene() -> (Document, LayerId) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let a = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(30.0, 15.0))
+            .unwrap();
+        doc.set_shape_color(a, Vec4::new(1.0, 0.0, 0.0, 0.5))
+            .unwrap();
+        (doc, layer)
+    }
+
+    #[test]
+    fn test_to_pdf_page_and_objects() {
+        let (doc, _) = scene();
+        let pdf = to_pdf(&doc, &Selection::new(), &PdfOptions::default()).unwrap();
+        let text = parse(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert_eq!(text.matches("/Type /Page ").count(), 1);
+        assert!(text.contains("/MediaBox [0 0 30 15]"));
+        assert!(text.contains("q 0 0 30 15 re W n 1 0 0 1 0 0 cm /Fm5 Do Q"));
+        assert!(text.contains("/ICCBased 3 0 R"));
+        assert!(text.contains("/GS0 << /Type /ExtGState /ca 0.5 /CA 0.5 >>"));
+        assert!(text.contains("q 30 0 0 15 0 0 cm /GS0 gs /CS0 cs 1 0 0 sc 0 0 1 1 re f Q"));
+        assert_eq!(
+            pdf,
+            to_pdf(&doc, &Selection::new(), &PdfOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layer_opacity_transparency_group() {
+        let (mut doc, layer) = scene();
+        let group = doc.add_group("Group".into()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+        doc.set_layer_opacity(group, 0.25).unwrap();
+        let hidden = doc.add_layer("Hidden".into()).unwrap();
+        doc.add_shape(hidden, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_layer_visible(hidden, false).unwrap();
+
+        let pages = [
+            Bounds {
+                min: Vec2::ZERO,
+                max: Vec2::splat(10.0),
+            },
+            Bounds {
+                min: Vec2::splat(10.0),
+                max: Vec2::new(30.0, 15.0),
+            },
+        ];
+        let text = parse(&write_pdf(&doc, &pages).unwrap());
+        assert_eq!(text.matches("/Type /Page ").count(), 2);
+        assert!(text.contains("/MediaBox [0 0 20 5]"));
+        assert_eq!(text.matches("/S /Transparency").count(), 3);
+        assert!(text.contains("/ca 0.25"));
+        assert_eq!(text.matches(" re f Q").count(), 1);
+    }
+
+    #[test]
+    fn test_artboard_pages_and_backgrounds() {
+        let (mut doc, _) = scene();
+        assert!(matches!(to_artboards_pdf(&doc), Err(ExportError::Empty(_))));
+        let id = doc
+            .add_artboard("Card".into(), Vec2::new(-5.0, 0.0), Vec2::new(20.0, 10.0))
+            .unwrap();
+        let mut board = doc.artboard(id).unwrap().clone();
+        board.background = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        doc.replace_artboard(board).unwrap();
+        doc.add_artboard("Wide".into(), Vec2::ZERO, Vec2::new(40.0, 10.0))
+            .unwrap();
+
+        let text = parse(&to_artboards_pdf(&doc).unwrap());
+        assert_eq!(text.matches("/Type /Page ").count(), 2);
+        assert!(text.contains("/MediaBox [0 0 20 10]"));
+        assert!(text.contains("/MediaBox [0 0 40 10]"));
+        // Backgrounds are painted first, beneath the shapes
+        let blue = text
+            .find("q 20 0 0 10 -5 0 cm /CS0 cs 0 0 1 sc 0 0 1 1 re f Q")
+            .unwrap();
+        let red = text.find("/CS0 cs 1 0 0 sc").unwrap();
+        assert!(blue < red);
+        // The red shape is centred on both; the topmost clips it
+        assert!(text.contains("q 1 0 0 1 0 0 cm 0 0 40 10 re W n 1 0 0 1 0 0 cm\nq 30 0 0 15"));
+    }
+
+    #[test]
+    fn test_hidden_group_hides_visible_child() {
+        let (mut doc, layer) = scene();
+        let group = doc.add_group("Group".into()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+        doc.set_layer_visible(group, false).unwrap();
+
+        let page = [Bounds {
+            min: Vec2::ZERO,
+            max: Vec2::splat(10.0),
+        }];
+        let text = parse(&write_pdf(&doc, &page).unwrap());
+        assert_eq!(text.matches(" re f Q").count(), 0);
+    }
+
+    #[test]
+    fn test_write_pdf_rejects_pages() {
+        let (doc, _) = scene();
+        assert!(write_pdf(&doc, &[]).is_err());
+        let huge = Bounds {
+            min: Vec2::ZERO,
+            max: Vec2::new(20_000.0, 1.0),
+        };
+        assert!(write_pdf(&doc, &[huge]).is_err());
+    }
+
+    #[test]
+    fn test_finish_reports_unwritten_object() {
+        let mut writer = PdfWriter::new();
+        writer.reserve();
+        assert!(matches!(writer.finish(), Err(ExportError::Encode(_))));
+    }
+}
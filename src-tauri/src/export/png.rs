@@ -0,0 +1,143 @@
+// MANDATE: PNG export with sRGB and DPI metadata
+#![deny(warnings)]
+
+use super::raster::{render_image, RasterImage, RasterOptions};
+use super::{write_file, ExportError};
+use crate::document::Document;
+use crate::selection::Selection;
+use std::path::Path;
+
+/// Metres per inch, for the pHYs chunk.
+/// MANDATE: Fixed constant.
+const METRES_PER_INCH: f32 = 0.0254;
+
+/// Encode image as an 8-bit RGBA PNG with sRGB and pHYs chunks.
+/// MANDATE: ≤60 SLOC, error on a pixel buffer that does not match the size.
+pub fn encode_png(image: &RasterImage) -> Result<Vec<u8>, ExportError> {
+    // MANDATE: Input validation
+    image.check()?;
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let per_metre = (image.dpi() / METRES_PER_INCH).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: per_metre,
+        yppu: per_metre,
+        unit: png::Unit::Meter,
+    }));
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    writer
+        .write_image_data(&image.pixels)
+        .map_err(encode_error)?;
+    writer.finish().map_err(encode_error)?;
+
+    // MANDATE: Assertions
+    assert!(bytes.starts_with(b"\x89PNG"));
+    Ok(bytes)
+}
+
+/// Render the export area and write it as a PNG file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_png(
+    doc: &Document,
+    selection: &Selection,
+    options: &RasterOptions,
+    path: &Path,
+) -> Result<RasterImage, ExportError> {
+    let image = render_image(doc, selection, options)?;
+    let bytes = encode_png(&image)?;
+    write_file(path, &bytes)?;
+    Ok(image)
+}
+
+/// Convert encoder error.
+/// MANDATE: ≤60 SLOC.
+fn encode_error(err: png::EncodingError) -> ExportError {
+    ExportError::Encode(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::raster::{Background, ExportScale};
+    use crate::export::ExportArea;
+    use glam::{Vec2, Vec3, Vec4};
+
+    fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>, png::Info<'static>) {
+        let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        let meta = reader.info().clone();
+        (info, pixels, meta)
+    }
+
+    #[test]
+    fn test_encode_png_metadata() {
+        let image = RasterImage {
+            width: 2,
+            height: 1,
+            scale: 2.0,
+            pixels: vec![255, 0, 0, 255, 0, 0, 255, 128],
+        };
+        let (info, pixels, meta) = decode(&encode_png(&image).unwrap());
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(pixels, image.pixels);
+        assert!(meta.srgb.is_some());
+
+        // 144 DPI
+        let dims = meta.pixel_dims.unwrap();
+        assert_eq!((dims.xppu, dims.unit), (5669, png::Unit::Meter));
+    }
+
+    #[test]
+    fn test_encode_png_rejects_bad_buffer() {
+        let image = RasterImage {
+            width: 2,
+            height: 2,
+            scale: 1.0,
+            pixels: vec![0; 4],
+        };
+        assert!(encode_png(&image).is_err());
+    }
+
+    #[test]
+    fn test_export_png_region() {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let red = doc.add_shape(layer, Vec2::ZERO, Vec2::splat(4.0)).unwrap();
+        doc.set_shape_color(red, Vec4::new(1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        let selection = Selection::new();
+        let dir = std::env::temp_dir().join(format!("png-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("region.png");
+
+        // Left half of the image is the shape, right half the background
+        let options = RasterOptions {
+            area: ExportArea::Rect {
+                min: Vec2::ZERO,
+                max: Vec2::new(8.0, 4.0),
+            },
+            scale: ExportScale::Factor(2.0),
+            background: Background::Solid(Vec3::new(0.0, 0.0, 1.0)),
+        };
+        let image = export_png(&doc, &selection, &options, &path).unwrap();
+        let (info, pixels, _) = decode(&std::fs::read(&path).unwrap());
+        assert_eq!((info.width, info.height), (16, 8));
+        assert_eq!(&pixels[..4], &[255, 0, 0, 255]);
+        assert_eq!(&pixels[60..64], &[0, 0, 255, 255]);
+        assert_eq!(image.pixels, pixels);
+
+        let transparent = RasterOptions {
+            background: Background::Transparent,
+            ..options
+        };
+        let image = export_png(&doc, &selection, &transparent, &path).unwrap();
+        assert_eq!(&image.pixels[60..64], &[0, 0, 0, 0]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,406 @@
+// MANDATE: Export presets bundling format, scale and background
+#![deny(warnings)]
+
+use super::jpeg::{encode_jpeg, opaque_options};
+use super::png::encode_png;
+use super::raster::{
+    render_image_with, Background, ExportScale, RasterImage, RasterOptions, BASE_DPI,
+};
+use super::webp::encode_webp;
+use super::{write_file, ExportArea, ExportError};
+use crate::document::Document;
+use crate::render::offscreen::OffscreenRenderer;
+use crate::selection::Selection;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Most presets exported in one call.
+/// MANDATE: Bounded batch.
+pub const MAX_PRESETS: usize = 32;
+
+/// Raster file format and its encoder settings.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImageFormat {
+    Png,
+    /// Flattened onto the background, white if transparent.
+    Jpeg {
+        quality: u8,
+    },
+    Webp {
+        lossless: bool,
+        quality: u8,
+    },
+}
+
+impl ImageFormat {
+    /// File extension, without the dot.
+    /// MANDATE: ≤60 SLOC.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg { .. } => "jpg",
+            Self::Webp { .. } => "webp",
+        }
+    }
+}
+
+/// Named way of exporting: one format at one scale over one background.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    /// Appended to the file stem, e.g. `@2x`.
+    #[serde(default)]
+    pub suffix: String,
+    pub format: ImageFormat,
+    #[serde(default)]
+    pub scale: ExportScale,
+    #[serde(default)]
+    pub background: Background,
+}
+
+/// File written for one preset.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresetOutput {
+    pub preset: String,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+}
+
+/// Built-in presets: "web" (lossy WebP at 1x), "@2x retina" (PNG at 2x)
+/// and "print 300dpi" (JPEG at 300/72 over white).
+/// MANDATE: ≤60 SLOC, same list on every call.
+pub fn builtin_presets() -> Vec<ExportPreset> {
+    vec![
+        ExportPreset {
+            name: "web".into(),
+            suffix: String::new(),
+            format: ImageFormat::Webp {
+                lossless: false,
+                quality: 80,
+            },
+            scale: ExportScale::Factor(1.0),
+            background: Background::Transparent,
+        },
+        ExportPreset {
+            name: "@2x retina".into(),
+            suffix: "@2x".into(),
+            format: ImageFormat::Png,
+            scale: ExportScale::Factor(2.0),
+            background: Background::Transparent,
+        },
+        ExportPreset {
+            name: "print 300dpi".into(),
+            suffix: "-print".into(),
+            format: ImageFormat::Jpeg { quality: 95 },
+            scale: ExportScale::Factor(300.0 / BASE_DPI),
+            background: Background::Solid(Vec3::ONE),
+        },
+    ]
+}
+
+/// Export `area` once per preset as `<dir>/<stem><suffix>.<ext>`, stopping
+/// at the first failure.
+/// MANDATE: ≤60 SLOC, at most `MAX_PRESETS`, error on clashing file names.
+pub fn export_presets(
+    doc: &Document,
+    selection: &Selection,
+    area: ExportArea,
+    presets: &[ExportPreset],
+    dir: &Path,
+    stem: &str,
+) -> Result<Vec<PresetOutput>, ExportError> {
+    write_presets(&mut None, doc, selection, area, presets, dir, stem)
+}
+
+/// Export presets with `renderer`, rendering once per distinct scale and
+/// background and encoding that image for every preset sharing them.
+/// MANDATE: ≤60 SLOC, at most `MAX_PRESETS`, error on clashing file names.
+fn write_presets(
+    renderer: &mut Option<OffscreenRenderer>,
+    doc: &Document,
+    selection: &Selection,
+    area: ExportArea,
+    presets: &[ExportPreset],
+    dir: &Path,
+    stem: &str,
+) -> Result<Vec<PresetOutput>, ExportError> {
+    // MANDATE: Input validation
+    if presets.is_empty() || presets.len() > MAX_PRESETS {
+        return Err(ExportError::InvalidOption(format!(
+            "{} presets, expected 1..={}",
+            presets.len(),
+            MAX_PRESETS
+        )));
+    }
+    if stem.is_empty() || stem.contains(['/', '\\']) {
+        return Err(ExportError::InvalidOption(format!(
+            "invalid file stem '{}'",
+            stem
+        )));
+    }
+    let paths: Vec<PathBuf> = presets
+        .iter()
+        .map(|preset| file_path(dir, stem, preset))
+        .collect::<Result<_, _>>()?;
+    if let Some(i) = (1..paths.len()).find(|&i| paths[..i].contains(&paths[i])) {
+        return Err(ExportError::InvalidOption(format!(
+            "presets write the same file {}",
+            paths[i].display()
+        )));
+    }
+
+    let options: Vec<RasterOptions> = presets.iter().map(|p| preset_options(area, p)).collect();
+    let mut outputs: Vec<Option<PresetOutput>> = vec![None; presets.len()];
+    for i in 0..presets.len() {
+        // Presets sharing options with an earlier one were written with it
+        if outputs[i].is_some() {
+            continue;
+        }
+        let image = render_image_with(renderer, doc, selection, &options[i])?;
+        for j in (i..presets.len()).filter(|&j| options[j] == options[i]) {
+            let bytes = encode_preset(&image, presets[j].format)?;
+            write_file(&paths[j], &bytes)?;
+            outputs[j] = Some(PresetOutput {
+                preset: presets[j].name.clone(),
+                path: paths[j].clone(),
+                width: image.width,
+                height: image.height,
+                bytes: bytes.len(),
+            });
+        }
+    }
+    let outputs: Vec<PresetOutput> = outputs.into_iter().flatten().collect();
+
+    // MANDATE: Assertions
+    assert_eq!(outputs.len(), presets.len());
+    Ok(outputs)
+}
+
+/// Export every artboard with every preset, naming files after the artboards.
+/// Names are made file-safe and numbered when two artboards share one.
+/// MANDATE: ≤60 SLOC, at most `MAX_PRESETS` per artboard.
+pub fn export_artboards(
+    doc: &Document,
+    selection: &Selection,
+    presets: &[ExportPreset],
+    dir: &Path,
+) -> Result<Vec<PresetOutput>, ExportError> {
+    let artboards = doc.artboards();
+    // MANDATE: Input validation
+    if artboards.is_empty() {
+        return Err(ExportError::Empty("document has no artboards".into()));
+    }
+
+    let mut renderer = None;
+    let mut stems: Vec<String> = Vec::with_capacity(artboards.len());
+    let mut outputs = Vec::with_capacity(artboards.len() * presets.len());
+    for artboard in artboards {
+        let base = file_stem(&artboard.name);
+        let mut stem = base.clone();
+        // Bounded: at most one clash per earlier artboard
+        let mut n = 1;
+        while stems.contains(&stem) {
+            n += 1;
+            stem = format!("{}-{}", base, n);
+        }
+        let area = ExportArea::Artboard { id: artboard.id };
+        outputs.extend(write_presets(
+            &mut renderer,
+            doc,
+            selection,
+            area,
+            presets,
+            dir,
+            &stem,
+        )?);
+        stems.push(stem);
+    }
+
+    // MANDATE: Assertions
+    assert_eq!(outputs.len(), stems.len() * presets.len());
+    Ok(outputs)
+}
+
+/// Artboard name with path separators and control characters replaced.
+/// MANDATE: ≤60 SLOC, never empty.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if stem.is_empty() || stem.chars().all(|c| c == '.') {
+        return "artboard".into();
+    }
+    stem
+}
+
+/// Raster options one preset renders with; JPEG is flattened over its matte.
+/// MANDATE: ≤60 SLOC, equal options give identical images.
+fn preset_options(area: ExportArea, preset: &ExportPreset) -> RasterOptions {
+    let options = RasterOptions {
+        area,
+        scale: preset.scale,
+        background: preset.background,
+    };
+    match preset.format {
+        ImageFormat::Jpeg { .. } => opaque_options(&options),
+        ImageFormat::Png | ImageFormat::Webp { .. } => options,
+    }
+}
+
+/// Encode a rendered image in one preset format.
+/// MANDATE: ≤60 SLOC.
+fn encode_preset(image: &RasterImage, format: ImageFormat) -> Result<Vec<u8>, ExportError> {
+    match format {
+        ImageFormat::Png => encode_png(image),
+        ImageFormat::Jpeg { quality } => encode_jpeg(image, quality),
+        ImageFormat::Webp { lossless, quality } => encode_webp(image, lossless, quality),
+    }
+}
+
+/// Output path of one preset.
+/// MANDATE: ≤60 SLOC, suffix must not leave `dir`.
+fn file_path(dir: &Path, stem: &str, preset: &ExportPreset) -> Result<PathBuf, ExportError> {
+    // MANDATE: Input validation
+    if preset.suffix.contains(['/', '\\']) {
+        return Err(ExportError::InvalidOption(format!(
+            "preset '{}' suffix contains a path separator",
+            preset.name
+        )));
+    }
+    let name = format!("{}{}.{}", stem, preset.suffix, preset.format.extension());
+    Ok(dir.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Vec2, Vec4};
+
+    #[test]
+    fn test_builtin_presets() {
+        let presets = builtin_presets();
+        let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["web", "@2x retina", "print 300dpi"]);
+        let dir = Path::new("out");
+        let files: Vec<PathBuf> = presets
+            .iter()
+            .map(|p| file_path(dir, "logo", p).unwrap())
+            .collect();
+        assert_eq!(
+            files,
+            [
+                dir.join("logo.webp"),
+                dir.join("logo@2x.png"),
+                dir.join("logo-print.jpg")
+            ]
+        );
+
+        // Presets are plain data for the frontend
+        let json = serde_json::to_string(&presets[2]).unwrap();
+        let back: ExportPreset = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, presets[2]);
+    }
+
+    #[test]
+    fn test_export_presets_rejects_bad_batches() {
+        let doc = Document::new();
+        let selection = Selection::new();
+        let dir = Path::new("out");
+        let area = ExportArea::Document;
+        let mut presets = builtin_presets();
+        assert!(export_presets(&doc, &selection, area, &[], dir, "a").is_err());
+        assert!(export_presets(&doc, &selection, area, &presets, dir, "a/b").is_err());
+        presets[1].suffix = String::new();
+        presets[1].format = presets[0].format;
+        assert!(matches!(
+            export_presets(&doc, &selection, area, &presets, dir, "a"),
+            Err(ExportError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_presets_share_renders() {
+        let presets = builtin_presets();
+        let area = ExportArea::Document;
+        let web = preset_options(area, &presets[0]);
+        assert_eq!(web.background, Background::Transparent);
+        assert_ne!(web, preset_options(area, &presets[1]));
+
+        // A transparent JPEG renders like a PNG over white
+        let mut jpeg = presets[0].clone();
+        jpeg.format = ImageFormat::Jpeg { quality: 90 };
+        let mut png = presets[0].clone();
+        png.format = ImageFormat::Png;
+        png.background = Background::Solid(Vec3::ONE);
+        assert_eq!(preset_options(area, &jpeg), preset_options(area, &png));
+    }
+
+    #[test]
+    fn test_artboard_file_stems() {
+        assert_eq!(file_stem(" Home/Dark: v2 "), "Home_Dark_ v2");
+        assert_eq!(file_stem(".."), "artboard");
+        let doc = Document::new();
+        let result = export_artboards(
+            &doc,
+            &Selection::new(),
+            &builtin_presets(),
+            Path::new("out"),
+        );
+        assert!(matches!(result, Err(ExportError::Empty(_))));
+    }
+
+    #[test]
+    fn test_export_presets_writes_each_format() {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let shape = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(9.0, 6.0))
+            .unwrap();
+        doc.set_shape_color(shape, Vec4::new(1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("preset-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let outputs = export_presets(
+            &doc,
+            &Selection::new(),
+            ExportArea::Document,
+            &builtin_presets(),
+            &dir,
+            "card",
+        )
+        .unwrap();
+        let sizes: Vec<(u32, u32)> = outputs.iter().map(|o| (o.width, o.height)).collect();
+        assert_eq!(sizes, [(9, 6), (18, 12), (38, 25)]);
+        for output in &outputs {
+            let bytes = std::fs::read(&output.path).unwrap();
+            assert_eq!(bytes.len(), output.bytes);
+        }
+        assert!(std::fs::read(dir.join("card-print.jpg"))
+            .unwrap()
+            .starts_with(&[0xFF, 0xD8]));
+
+        // One file set per artboard, clashing names numbered
+        for x in [0.0, 10.0] {
+            doc.add_artboard("Card".into(), Vec2::new(x, 0.0), Vec2::new(4.0, 3.0))
+                .unwrap();
+        }
+        let outputs =
+            export_artboards(&doc, &Selection::new(), &builtin_presets()[1..2], &dir).unwrap();
+        let paths: Vec<PathBuf> = outputs.iter().map(|o| o.path.clone()).collect();
+        assert_eq!(paths, [dir.join("Card@2x.png"), dir.join("Card-2@2x.png")]);
+        assert!(outputs.iter().all(|o| (o.width, o.height) == (8, 6)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
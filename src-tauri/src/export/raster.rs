@@ -0,0 +1,369 @@
+// MANDATE: Headless rasterization of an export area
+#![deny(warnings)]
+
+use super::{export_bounds, ExportArea, ExportError};
+use crate::document::Document;
+use crate::render::frame::FrameBuilder;
+use crate::render::offscreen::OffscreenRenderer;
+use crate::selection::{Bounds, Selection};
+use glam::{Mat3, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Resolution of 1x exports: one world unit per pixel at 72 DPI.
+/// MANDATE: Fixed constant.
+pub const BASE_DPI: f32 = 72.0;
+
+/// Largest exported image side in pixels: the texture side guaranteed by the
+/// default device limits the offscreen renderer requests.
+/// MANDATE: Bounded allocation (256 MiB of RGBA at the limit).
+pub const MAX_EXPORT_SIZE: u32 = 8_192;
+
+/// Largest explicit scale factor; `Fit` is bounded by `MAX_EXPORT_SIZE` instead.
+/// MANDATE: Bounded value.
+pub const MAX_EXPORT_SCALE: f32 = 16.0;
+
+/// Output resolution.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportScale {
+    /// Pixels per world unit, e.g. 1.0, 2.0, 3.0.
+    Factor(f32),
+    /// Largest image fitting `width` × `height` pixels, keeping aspect ratio.
+    Fit { width: u32, height: u32 },
+}
+
+impl Default for ExportScale {
+    fn default() -> Self {
+        Self::Factor(1.0)
+    }
+}
+
+/// What fills pixels no shape covers.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Background {
+    #[default]
+    Transparent,
+    /// Opaque colour, in the same space as shape colours.
+    Solid(Vec3),
+}
+
+/// Raster export settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RasterOptions {
+    #[serde(default)]
+    pub area: ExportArea,
+    #[serde(default)]
+    pub scale: ExportScale,
+    #[serde(default)]
+    pub background: Background,
+}
+
+/// Rendered image: straight-alpha sRGB RGBA8 rows, top row first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterImage {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels per world unit.
+    pub scale: f32,
+    pub pixels: Vec<u8>,
+}
+
+impl RasterImage {
+    /// Resolution in dots per inch.
+    /// MANDATE: ≤60 SLOC.
+    pub fn dpi(&self) -> f32 {
+        BASE_DPI * self.scale
+    }
+
+    /// Error unless the buffer holds exactly `width` × `height` RGBA pixels.
+    /// MANDATE: ≤60 SLOC.
+    pub fn check(&self) -> Result<(), ExportError> {
+        let expected = self.width as usize * self.height as usize * 4;
+        if self.pixels.len() != expected {
+            return Err(ExportError::Encode(format!(
+                "{} pixel bytes for a {}x{} image",
+                self.pixels.len(),
+                self.width,
+                self.height
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Render the export area headlessly.
+/// MANDATE: ≤60 SLOC, validated options, bounded image size.
+pub fn render_image(
+    doc: &Document,
+    selection: &Selection,
+    options: &RasterOptions,
+) -> Result<RasterImage, ExportError> {
+    render_image_with(&mut None, doc, selection, options)
+}
+
+/// Render the export area with `renderer`, creating it on first use and
+/// resizing its target after, so one device serves a batch of exports.
+/// MANDATE: ≤60 SLOC, validated options, bounded image size.
+pub fn render_image_with(
+    renderer: &mut Option<OffscreenRenderer>,
+    doc: &Document,
+    selection: &Selection,
+    options: &RasterOptions,
+) -> Result<RasterImage, ExportError> {
+    let bounds = export_bounds(doc, selection, options.area)?;
+    let (width, height, scale) = pixel_size(&bounds, options.scale)?;
+    let clear = match options.background {
+        Background::Transparent => wgpu::Color::TRANSPARENT,
+        Background::Solid(color) => {
+            // MANDATE: Input validation
+            if !color.is_finite() || color.min_element() < 0.0 || color.max_element() > 1.0 {
+                return Err(ExportError::InvalidOption("background outside 0..1".into()));
+            }
+            let [r, g, b] = color.as_dvec3().to_array();
+            wgpu::Color { r, g, b, a: 1.0 }
+        }
+    };
+
+    let view = region_view(&bounds, width, height, scale);
+    let covered = Vec2::new(width as f32, height as f32) / scale;
+    let region = (
+        Vec2::new(bounds.min.x, bounds.max.y - covered.y),
+        Vec2::new(bounds.min.x + covered.x, bounds.max.y),
+    );
+    let mut builder = FrameBuilder::new();
+    let frame = builder.build_view(doc, view, region);
+
+    let renderer = match renderer {
+        Some(renderer) => {
+            renderer
+                .resize(width, height)
+                .map_err(ExportError::Render)?;
+            renderer
+        }
+        None => renderer.insert(
+            pollster::block_on(OffscreenRenderer::new(width, height))
+                .map_err(ExportError::Render)?,
+        ),
+    };
+    let mut pixels = renderer
+        .render(&frame.instances, clear)
+        .map_err(ExportError::Render)?;
+    unpremultiply(&mut pixels);
+
+    // MANDATE: Assertions
+    assert_eq!(pixels.len(), width as usize * height as usize * 4);
+    Ok(RasterImage {
+        width,
+        height,
+        scale,
+        pixels,
+    })
+}
+
+/// Image size in pixels and pixels per world unit for bounds at scale.
+/// MANDATE: ≤60 SLOC, validated scale, sides within `MAX_EXPORT_SIZE`.
+pub fn pixel_size(bounds: &Bounds, scale: ExportScale) -> Result<(u32, u32, f32), ExportError> {
+    // MANDATE: Input validation, reporting the limit the caller exceeded
+    let extent = bounds.max - bounds.min;
+    let factor = match scale {
+        ExportScale::Factor(factor) if !(factor > 0.0 && factor <= MAX_EXPORT_SCALE) => {
+            return Err(ExportError::InvalidOption(format!(
+                "scale {} outside (0, {}]",
+                factor, MAX_EXPORT_SCALE
+            )));
+        }
+        ExportScale::Factor(factor) => factor,
+        ExportScale::Fit { width, height }
+            if !(1..=MAX_EXPORT_SIZE).contains(&width)
+                || !(1..=MAX_EXPORT_SIZE).contains(&height) =>
+        {
+            return Err(ExportError::InvalidOption(format!(
+                "fit size {}x{} outside 1..={}",
+                width, height, MAX_EXPORT_SIZE
+            )));
+        }
+        ExportScale::Fit { width, height } => {
+            (width as f32 / extent.x).min(height as f32 / extent.y)
+        }
+    };
+    if !factor.is_finite() {
+        return Err(ExportError::InvalidOption(format!(
+            "area {}x{} too small to fit",
+            extent.x, extent.y
+        )));
+    }
+
+    let mut size = (extent * factor).ceil();
+    if let ExportScale::Fit { width, height } = scale {
+        // Rounding must never overshoot the requested box
+        size = size.min(Vec2::new(width as f32, height as f32));
+    }
+    if size.min_element() < 1.0 || size.max_element() > MAX_EXPORT_SIZE as f32 {
+        return Err(ExportError::InvalidOption(format!(
+            "image size {}x{} outside 1..={}",
+            size.x, size.y, MAX_EXPORT_SIZE
+        )));
+    }
+
+    // MANDATE: Assertions
+    assert!(size.x >= 1.0 && size.y >= 1.0);
+    Ok((size.x as u32, size.y as u32, factor))
+}
+
+/// World-to-clip matrix placing the top-left of bounds at the image's
+/// top-left pixel, with `scale` pixels per world unit.
+/// MANDATE: ≤60 SLOC.
+pub fn region_view(bounds: &Bounds, width: u32, height: u32, scale: f32) -> Mat3 {
+    // MANDATE: Assertions
+    assert!(width > 0 && height > 0);
+    assert!(scale > 0.0);
+
+    let clip_per_unit = Vec2::new(2.0 * scale / width as f32, 2.0 * scale / height as f32);
+    let top_left = Vec2::new(bounds.min.x, bounds.max.y);
+    Mat3::from_translation(Vec2::new(-1.0, 1.0))
+        * Mat3::from_scale(clip_per_unit)
+        * Mat3::from_translation(-top_left)
+}
+
+/// Convert premultiplied sRGB RGBA8 (as blended over a transparent target)
+/// to straight alpha, un-premultiplying in linear light.
+/// MANDATE: ≤60 SLOC, bounded by the pixel count.
+pub fn unpremultiply(pixels: &mut [u8]) {
+    // MANDATE: Assertions
+    assert_eq!(pixels.len() % 4, 0);
+
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3];
+        if alpha == 255 {
+            continue;
+        }
+        if alpha == 0 {
+            pixel[..3].fill(0);
+            continue;
+        }
+        let coverage = f32::from(alpha) / 255.0;
+        for channel in &mut pixel[..3] {
+            let linear = (srgb_to_linear(*channel) / coverage).min(1.0);
+            *channel = linear_to_srgb(linear);
+        }
+    }
+}
+
+/// Decode an 8-bit sRGB channel to linear light.
+/// MANDATE: ≤60 SLOC.
+pub fn srgb_to_linear(value: u8) -> f32 {
+    let c = f32::from(value) / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode linear light as an 8-bit sRGB channel.
+/// MANDATE: ≤60 SLOC.
+pub fn linear_to_srgb(linear: f32) -> u8 {
+    let c = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(min: (f32, f32), max: (f32, f32)) -> Bounds {
+        Bounds {
+            min: Vec2::new(min.0, min.1),
+            max: Vec2::new(max.0, max.1),
+        }
+    }
+
+    #[test]
+    fn test_pixel_size_factor_and_fit() {
+        let area = bounds((0.0, 0.0), (100.0, 50.0));
+        assert_eq!(
+            pixel_size(&area, ExportScale::Factor(2.0)).unwrap(),
+            (200, 100, 2.0)
+        );
+        let fit = ExportScale::Fit {
+            width: 400,
+            height: 400,
+        };
+        assert_eq!(pixel_size(&area, fit).unwrap(), (400, 200, 4.0));
+        // Fitting a small icon may scale past the factor cap
+        let icon = bounds((0.0, 0.0), (24.0, 24.0));
+        let fit = ExportScale::Fit {
+            width: 512,
+            height: 512,
+        };
+        let (width, height, factor) = pixel_size(&icon, fit).unwrap();
+        assert_eq!((width, height), (512, 512));
+        assert!(factor > MAX_EXPORT_SCALE);
+        assert_eq!(
+            pixel_size(&bounds((0.0, 0.0), (10.5, 1.0)), ExportScale::Factor(1.0)).unwrap(),
+            (11, 1, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_pixel_size_rejects() {
+        let area = bounds((0.0, 0.0), (100.0, 50.0));
+        assert!(pixel_size(&area, ExportScale::Factor(0.0)).is_err());
+        assert!(pixel_size(&area, ExportScale::Factor(f32::NAN)).is_err());
+        assert!(pixel_size(&area, ExportScale::Factor(MAX_EXPORT_SCALE + 1.0)).is_err());
+        let empty = ExportScale::Fit {
+            width: 0,
+            height: 10,
+        };
+        assert_eq!(
+            pixel_size(&area, empty),
+            Err(ExportError::InvalidOption(format!(
+                "fit size 0x10 outside 1..={}",
+                MAX_EXPORT_SIZE
+            )))
+        );
+        let fit = ExportScale::Fit {
+            width: 10,
+            height: 10,
+        };
+        assert!(pixel_size(&bounds((0.0, 0.0), (0.0, 0.0)), fit).is_err());
+        let huge = bounds((0.0, 0.0), (10_000.0, 1.0));
+        assert!(pixel_size(&huge, ExportScale::Factor(2.0)).is_err());
+    }
+
+    #[test]
+    fn test_max_export_size_fits_device() {
+        let limits = wgpu::Limits::default();
+        assert!(MAX_EXPORT_SIZE <= limits.max_texture_dimension_2d);
+        // The readback buffer of a full-size image, rows already 256-byte aligned
+        let readback = u64::from(MAX_EXPORT_SIZE) * u64::from(MAX_EXPORT_SIZE) * 4;
+        assert!(readback <= limits.max_buffer_size);
+    }
+
+    #[test]
+    fn test_region_view_maps_corners() {
+        let area = bounds((10.0, 20.0), (30.0, 30.0));
+        let view = region_view(&area, 40, 20, 2.0);
+        let top_left = view.transform_point2(Vec2::new(10.0, 30.0));
+        let bottom_right = view.transform_point2(Vec2::new(30.0, 20.0));
+        assert!(top_left.abs_diff_eq(Vec2::new(-1.0, 1.0), 1e-6));
+        assert!(bottom_right.abs_diff_eq(Vec2::new(1.0, -1.0), 1e-6));
+    }
+
+    #[test]
+    fn test_unpremultiply() {
+        let mut pixels = vec![128, 0, 0, 255, 188, 0, 0, 128, 9, 9, 9, 0];
+        unpremultiply(&mut pixels);
+        assert_eq!(&pixels[..4], &[128, 0, 0, 255]);
+        // Red at half coverage, blended in linear light, recovers full red
+        assert_eq!(&pixels[4..8], &[255, 0, 0, 128]);
+        assert_eq!(&pixels[8..], &[0, 0, 0, 0]);
+    }
+}
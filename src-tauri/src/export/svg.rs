@@ -0,0 +1,392 @@
+// MANDATE: Deterministic SVG export of the vector scene
+#![deny(warnings)]
+
+use super::raster::linear_to_srgb;
+use super::{export_bounds, number, write_file, ExportArea, ExportError};
+use crate::artboard::Artboard;
+use crate::document::Document;
+use crate::layer::Layer;
+use crate::layer_tree::MAX_DEPTH;
+use crate::selection::Selection;
+use crate::shape::Shape;
+use glam::{Mat3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// How hidden layers appear in the output.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HiddenLayers {
+    /// Left out, with everything inside them.
+    #[default]
+    Omit,
+    /// Kept as `<g display="none">`.
+    DisplayNone,
+}
+
+/// SVG export settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SvgOptions {
+    /// Area framed by the `viewBox`; the whole scene is still emitted.
+    #[serde(default)]
+    pub area: ExportArea,
+    #[serde(default)]
+    pub hidden: HiddenLayers,
+}
+
+/// Serialize the document as SVG: artboard backgrounds as `<rect>`s, then one
+/// `<g>` per layer, nested like the layer tree, with shapes as `<rect>`s,
+/// everything in render order. Shapes of clipping artboards reference a
+/// `<clipPath>` of the artboard written just before them. World space is
+/// y-up, so a root group flips it into SVG's y-down space. Fills are sRGB;
+/// materials and textures are not exported.
+/// MANDATE: ≤60 SLOC, bounded by the layer count, same document gives same bytes.
+pub fn to_svg(
+    doc: &Document,
+    selection: &Selection,
+    options: &SvgOptions,
+) -> Result<String, ExportError> {
+    let bounds = export_bounds(doc, selection, options.area)?;
+    let size = bounds.max - bounds.min;
+    let tree = doc.layers();
+    let mut ids = HashSet::new();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"{} {} {w} {h}\">\n",
+        number(bounds.min.x),
+        number(-bounds.max.y),
+        w = number(size.x),
+        h = number(size.y)
+    ));
+    out.push_str("<g transform=\"matrix(1 0 0 -1 0 0)\">\n");
+    for artboard in doc.artboards() {
+        let id = unique_id(&mut ids, &artboard.name);
+        out.push_str(&indent(1, &artboard_tag(artboard, &id)));
+    }
+
+    // Groups still open, innermost last
+    let mut open: Vec<&Layer> = Vec::new();
+    for layer in tree
+        .render_order()
+        .into_iter()
+        .filter_map(|id| tree.get_layer(id))
+    {
+        if options.hidden == HiddenLayers::Omit && !tree.is_visible(layer.id) {
+            continue;
+        }
+        while open.last().is_some_and(|g| Some(g.id) != layer.parent) {
+            open.pop();
+            out.push_str(&indent(open.len() + 1, "</g>\n"));
+        }
+
+        let depth = open.len() + 1;
+        let id = unique_id(&mut ids, &layer.name);
+        out.push_str(&indent(depth, &group_tag(layer, &id)));
+        if layer.is_group {
+            open.push(layer);
+            continue;
+        }
+        for shape in doc.shapes_in_layer(layer.id) {
+            out.push_str(&shape_tags(doc, shape, &mut ids, depth + 1));
+        }
+        out.push_str(&indent(depth, "</g>\n"));
+    }
+    while open.pop().is_some() {
+        out.push_str(&indent(open.len() + 1, "</g>\n"));
+    }
+    out.push_str("</g>\n</svg>\n");
+
+    // MANDATE: Assertions
+    assert!(open.is_empty());
+    assert!(ids.len() <= tree.len() + doc.artboard_count() + doc.shape_count());
+    Ok(out)
+}
+
+/// Export the document as an SVG file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_svg(
+    doc: &Document,
+    selection: &Selection,
+    options: &SvgOptions,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let svg = to_svg(doc, selection, options)?;
+    write_file(path, svg.as_bytes())
+}
+
+/// Opening `<g>` tag for a layer with its own transform, opacity and visibility.
+/// MANDATE: ≤60 SLOC.
+fn group_tag(layer: &Layer, id: &str) -> String {
+    let mut tag = format!("<g id=\"{}\"", id);
+    if layer.transform != Mat3::IDENTITY {
+        tag.push_str(&format!(" transform=\"{}\"", matrix(layer.transform)));
+    }
+    if layer.opacity < 1.0 {
+        tag.push_str(&format!(" opacity=\"{}\"", number(layer.opacity)));
+    }
+    if !layer.visible {
+        tag.push_str(" display=\"none\"");
+    }
+    tag.push_str(">\n");
+    tag
+}
+
+/// Background `<rect>` for an artboard in world space.
+/// MANDATE: ≤60 SLOC.
+fn artboard_tag(artboard: &Artboard, id: &str) -> String {
+    let mut tag = format!(
+        "<rect id=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"",
+        id,
+        number(artboard.position.x),
+        number(artboard.position.y),
+        number(artboard.size.x),
+        number(artboard.size.y),
+        hex_color(artboard.background)
+    );
+    if artboard.background.w < 1.0 {
+        tag.push_str(&format!(
+            " fill-opacity=\"{}\"",
+            number(artboard.background.w)
+        ));
+    }
+    tag.push_str("/>\n");
+    tag
+}
+
+/// Lines for a shape at depth: its `<rect>`, after a `<clipPath>` of its
+/// artboard when that clips content.
+/// MANDATE: ≤60 SLOC.
+fn shape_tags(doc: &Document, shape: &Shape, ids: &mut HashSet<String>, depth: usize) -> String {
+    let Some((min, max)) = doc.shape_clip(shape.id) else {
+        return indent(depth, &rect_tag(shape, None));
+    };
+    let id = unique_id(ids, &format!("clip-{}", shape.id));
+    // The clip rect is in world space; the rect's user space is layer × placement
+    let world = doc.layers().world_transform(shape.layer_id);
+    let to_local = (world * placement(shape).unwrap_or(Mat3::IDENTITY)).inverse();
+    let clip = format!(
+        "<clipPath id=\"{}\"><rect {}x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/></clipPath>\n",
+        id,
+        transform_attr(to_local),
+        number(min.x),
+        number(min.y),
+        number(max.x - min.x),
+        number(max.y - min.y)
+    );
+    indent(depth, &clip) + &indent(depth, &rect_tag(shape, Some(&id)))
+}
+
+/// Transform of a rotated or skewed shape without its size scale, applied
+/// to the sized rect; `None` when `x`/`y` placement suffices.
+/// MANDATE: ≤60 SLOC.
+fn placement(shape: &Shape) -> Option<Mat3> {
+    if shape.rotation == 0.0 && shape.skew == 0.0 {
+        return None;
+    }
+    Some(shape.local_transform() * Mat3::from_scale(shape.size.recip()))
+}
+
+/// `transform="…" ` attribute, empty for the identity.
+/// MANDATE: ≤60 SLOC.
+fn transform_attr(m: Mat3) -> String {
+    if m.abs_diff_eq(Mat3::IDENTITY, 1e-6) {
+        return String::new();
+    }
+    format!("transform=\"{}\" ", matrix(m))
+}
+
+/// `<rect>` for a shape in layer space; rotated or skewed shapes get a transform.
+/// MANDATE: ≤60 SLOC.
+fn rect_tag(shape: &Shape, clip: Option<&str>) -> String {
+    let placement = match placement(shape) {
+        None => format!(
+            "x=\"{}\" y=\"{}\"",
+            number(shape.position.x),
+            number(shape.position.y)
+        ),
+        Some(transform) => format!("transform=\"{}\"", matrix(transform)),
+    };
+
+    let mut tag = format!(
+        "<rect {} width=\"{}\" height=\"{}\" fill=\"{}\"",
+        placement,
+        number(shape.size.x),
+        number(shape.size.y),
+        hex_color(shape.color)
+    );
+    if shape.color.w < 1.0 {
+        tag.push_str(&format!(" fill-opacity=\"{}\"", number(shape.color.w)));
+    }
+    if let Some(id) = clip {
+        tag.push_str(&format!(" clip-path=\"url(#{})\"", id));
+    }
+    tag.push_str("/>\n");
+    tag
+}
+
+/// XML id derived from a layer name, made unique among ids already used.
+/// MANDATE: ≤60 SLOC, suffix loop bounded by the ids in use.
+fn unique_id(used: &mut HashSet<String>, name: &str) -> String {
+    let mut base: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        base.insert(0, '_');
+    }
+
+    let mut id = base.clone();
+    for suffix in 2..=used.len() + 2 {
+        if !used.contains(&id) {
+            break;
+        }
+        id = format!("{}-{}", base, suffix);
+    }
+
+    // MANDATE: Assertions
+    assert!(!used.contains(&id));
+    used.insert(id.clone());
+    id
+}
+
+/// SVG `matrix(a b c d e f)` of an affine transform.
+/// MANDATE: ≤60 SLOC.
+fn matrix(m: Mat3) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        number(m.x_axis.x),
+        number(m.x_axis.y),
+        number(m.y_axis.x),
+        number(m.y_axis.y),
+        number(m.z_axis.x),
+        number(m.z_axis.y)
+    )
+}
+
+/// `#rrggbb` of a linear colour.
+/// MANDATE: ≤60 SLOC.
+fn hex_color(color: Vec4) -> String {
+    let [r, g, b] = [color.x, color.y, color.z].map(linear_to_srgb);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Line indented two spaces per nesting level.
+/// MANDATE: ≤60 SLOC, depth bounded by the layer tree.
+fn indent(depth: usize, line: &str) -> String {
+    // MANDATE: Assertions
+    assert!(depth <= MAX_DEPTH + 1);
+    format!("{}{}", "  ".repeat(depth), line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    fn scene() -> Document {
+        let mut doc = Document::new();
+        let group = doc.add_group("Group".into()).unwrap();
+        let layer = doc.add_layer("Layer 1".into()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+        doc.set_layer_opacity(group, 0.5).unwrap();
+        let hidden = doc.add_layer("Layer 1".into()).unwrap();
+        doc.set_layer_visible(hidden, false).unwrap();
+
+        let red = doc
+            .add_shape(layer, Vec2::ZERO, Vec2::new(10.0, 5.0))
+            .unwrap();
+        doc.set_shape_color(red, Vec4::new(1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        doc.add_shape(hidden, Vec2::splat(2.0), Vec2::ONE).unwrap();
+        doc
+    }
+
+    #[test]
+    fn test_to_svg_snapshot() {
+        let doc = scene();
+        let svg = to_svg(&doc, &Selection::new(), &SvgOptions::default()).unwrap();
+        let expected = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"5\" viewBox=\"0 -5 10 5\">
+<g transform=\"matrix(1 0 0 -1 0 0)\">
+  <g id=\"Group\" opacity=\"0.5\">
+    <g id=\"Layer_1\">
+      <rect x=\"0\" y=\"0\" width=\"10\" height=\"5\" fill=\"#ff0000\"/>
+    </g>
+  </g>
+</g>
+</svg>
+";
+        assert_eq!(svg, expected);
+        assert_eq!(
+            to_svg(&doc, &Selection::new(), &SvgOptions::default()).unwrap(),
+            svg
+        );
+    }
+
+    #[test]
+    fn test_to_svg_hidden_display_none() {
+        let doc = scene();
+        let options = SvgOptions {
+            hidden: HiddenLayers::DisplayNone,
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&doc, &Selection::new(), &options).unwrap();
+        assert!(svg.contains(
+            "  <g id=\"Layer_1-2\" display=\"none\">\n    <rect x=\"2\" y=\"2\" width=\"1\" height=\"1\""
+        ));
+        assert_eq!(svg.matches("<g").count(), svg.matches("</g>").count());
+    }
+
+    #[test]
+    fn test_to_svg_artboard_background() {
+        let mut doc = scene();
+        let board = doc
+            .add_artboard("Group".into(), Vec2::new(-2.0, -1.0), Vec2::new(20.0, 10.0))
+            .unwrap();
+        let mut artboard = doc.artboard(board).unwrap().clone();
+        artboard.background = Vec4::new(0.0, 0.0, 1.0, 0.5);
+        doc.replace_artboard(artboard).unwrap();
+
+        let options = SvgOptions {
+            area: ExportArea::Artboard { id: board },
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&doc, &Selection::new(), &options).unwrap();
+        assert!(svg.contains("viewBox=\"-2 -9 20 10\""));
+        // Backgrounds come first and share the id namespace with layers
+        assert!(svg.contains(
+            "<g transform=\"matrix(1 0 0 -1 0 0)\">\n  <rect id=\"Group\" x=\"-2\" y=\"-1\" width=\"20\" height=\"10\" fill=\"#0000ff\" fill-opacity=\"0.5\"/>\n  <g id=\"Group-2\""
+        ));
+        // The red shape is centred on the clipping artboard, so it is cut there
+        assert!(svg.contains(
+            "      <clipPath id=\"clip-1\"><rect x=\"-2\" y=\"-1\" width=\"20\" height=\"10\"/></clipPath>\n      <rect x=\"0\" y=\"0\" width=\"10\" height=\"5\" fill=\"#ff0000\" clip-path=\"url(#clip-1)\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_rect_tag_rotated() {
+        let mut shape = Shape::new(1, 1, Vec2::new(1.0, 2.0), Vec2::new(4.0, 2.0));
+        shape.rotation = std::f32::consts::FRAC_PI_2;
+        shape.pivot = Vec2::ZERO;
+        shape.color = Vec4::new(0.5, 0.5, 0.5, 0.25);
+        assert_eq!(
+            rect_tag(&shape, None),
+            "<rect transform=\"matrix(0 1 -1 0 1 2)\" width=\"4\" height=\"2\" fill=\"#bcbcbc\" fill-opacity=\"0.25\"/>\n"
+        );
+    }
+
+    #[test]
+    fn test_unique_id() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_id(&mut used, "Layer 1"), "Layer_1");
+        assert_eq!(unique_id(&mut used, "Layer 1"), "Layer_1-2");
+        assert_eq!(unique_id(&mut used, "1st <\"layer\">"), "_1st___layer__");
+        assert_eq!(number(-0.00001), "0");
+    }
+}
@@ -0,0 +1,118 @@
+// MANDATE: Lossless and lossy WebP export
+#![deny(warnings)]
+
+use super::raster::{render_image, RasterImage, RasterOptions};
+use super::{write_file, ExportError};
+use crate::document::Document;
+use crate::selection::Selection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Default lossy WebP quality.
+/// MANDATE: Fixed constant.
+pub const DEFAULT_WEBP_QUALITY: u8 = 80;
+
+/// WebP export settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WebpOptions {
+    #[serde(default, flatten)]
+    pub raster: RasterOptions,
+    /// Exact pixels (VP8L) instead of lossy compression (VP8).
+    #[serde(default)]
+    pub lossless: bool,
+    /// 0 (smallest) to 100 (best); ignored when lossless.
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+}
+
+impl Default for WebpOptions {
+    fn default() -> Self {
+        Self {
+            raster: RasterOptions::default(),
+            lossless: false,
+            quality: DEFAULT_WEBP_QUALITY,
+        }
+    }
+}
+
+/// Serde default for `WebpOptions::quality`.
+/// MANDATE: ≤60 SLOC.
+fn default_quality() -> u8 {
+    DEFAULT_WEBP_QUALITY
+}
+
+/// Encode image as WebP, keeping alpha in both modes.
+/// MANDATE: ≤60 SLOC, error on a bad buffer or quality above 100.
+pub fn encode_webp(
+    image: &RasterImage,
+    lossless: bool,
+    quality: u8,
+) -> Result<Vec<u8>, ExportError> {
+    // MANDATE: Input validation
+    if quality > 100 {
+        return Err(ExportError::InvalidOption(format!(
+            "WebP quality {} outside 0..=100",
+            quality
+        )));
+    }
+    image.check()?;
+
+    let encoder = webp::Encoder::from_rgba(&image.pixels, image.width, image.height);
+    let bytes = encoder
+        .encode_simple(lossless, f32::from(quality))
+        .map_err(|err| ExportError::Encode(format!("{:?}", err)))?
+        .to_vec();
+
+    // MANDATE: Assertions
+    assert!(bytes.starts_with(b"RIFF") && bytes[8..12] == *b"WEBP");
+    Ok(bytes)
+}
+
+/// Render the export area and write it as a WebP file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_webp(
+    doc: &Document,
+    selection: &Selection,
+    options: &WebpOptions,
+    path: &Path,
+) -> Result<RasterImage, ExportError> {
+    let image = render_image(doc, selection, &options.raster)?;
+    let bytes = encode_webp(&image, options.lossless, options.quality)?;
+    write_file(path, &bytes)?;
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_webp_modes() {
+        // Translucent but never fully transparent, where the encoder may drop colour
+        let pixels: Vec<u8> = (0..16 * 16 * 4)
+            .map(|i| match i % 4 {
+                3 => 128 + (i % 128) as u8,
+                _ => (i * 31 % 256) as u8,
+            })
+            .collect();
+        let image = RasterImage {
+            width: 16,
+            height: 16,
+            scale: 1.0,
+            pixels,
+        };
+
+        // Lossless keeps every pixel, alpha included
+        let lossless = encode_webp(&image, true, 0).unwrap();
+        let decoded = webp::Decoder::new(&lossless).decode().unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 16));
+        assert!(decoded.is_alpha());
+        assert_eq!(&*decoded, &image.pixels[..]);
+
+        let lossy = encode_webp(&image, false, 10).unwrap();
+        let decoded = webp::Decoder::new(&lossy).decode().unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 16));
+        assert!(lossy.windows(4).any(|chunk| chunk == b"VP8 "));
+        assert!(encode_webp(&image, false, 101).is_err());
+    }
+}
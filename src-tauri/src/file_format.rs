@@ -0,0 +1,540 @@
+// MANDATE: Native .simple JSON document format
+#![deny(warnings)]
+
+use crate::artboard::Artboard;
+use crate::blob_store::BlobStore;
+use crate::container;
+use crate::document::{
+    validate_affine, validate_color, validate_name, validate_opacity, validate_pivot,
+    validate_position, validate_rotation, validate_size, validate_skew, validate_z_order, Document,
+    DocumentError,
+};
+use crate::layer::{Layer, LayerId};
+use crate::layer_tree::MAX_DEPTH;
+use crate::migration::{self, MigrationReport};
+use crate::shape::Shape;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Current file format version.
+/// MANDATE: Mandatory top-level field, see ARCHITECTURE_FILE_FORMAT.md.
+pub const FORMAT_VERSION: &str = "1.5.0";
+
+/// Maximum document file size.
+/// MANDATE: Bounded read.
+pub const MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// File format errors.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum FormatError {
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("File too large: {0} bytes")]
+    TooLarge(u64),
+    #[error("Malformed document at {path}: {message}")]
+    Malformed { path: String, message: String },
+    #[error("Invalid value at {path}: {message}")]
+    Invalid { path: String, message: String },
+    #[error("Missing top-level version field")]
+    MissingVersion,
+    #[error("Unsupported version: {0}")]
+    UnsupportedVersion(String),
+    #[error("Document version {0} is newer than supported version {FORMAT_VERSION}")]
+    NewerVersion(String),
+    #[error("Archive error: {0}")]
+    Archive(String),
+    #[error("Missing blob: {0}")]
+    MissingBlob(String),
+    #[error("Corrupt blob: {0}")]
+    CorruptBlob(String),
+}
+
+/// Serialized document layout.
+/// MANDATE: Layers, shapes and artboards sorted by ID for deterministic output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentFile {
+    pub version: String,
+    pub layers: Vec<Layer>,
+    pub shapes: Vec<Shape>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artboards: Vec<Artboard>,
+}
+
+impl DocumentFile {
+    /// Snapshot document.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn capture(doc: &Document) -> Self {
+        let mut layers: Vec<Layer> = doc
+            .layers()
+            .get_sorted_layers()
+            .into_iter()
+            .cloned()
+            .collect();
+        layers.sort_by_key(|layer| layer.id);
+        let shapes: Vec<Shape> = doc.all_shapes().into_iter().cloned().collect();
+        let artboards: Vec<Artboard> = doc.artboards().into_iter().cloned().collect();
+
+        // MANDATE: Assertions
+        assert_eq!(layers.len(), doc.layers().len());
+        assert_eq!(shapes.len(), doc.shape_count());
+        assert_eq!(artboards.len(), doc.artboard_count());
+
+        Self {
+            version: FORMAT_VERSION.to_string(),
+            layers,
+            shapes,
+            artboards,
+        }
+    }
+
+    /// Build document from validated file and its blobs.
+    /// MANDATE: ≤60 SLOC, validation precedes construction, parents before children.
+    pub fn into_document(self, blobs: BlobStore) -> Result<Document, FormatError> {
+        self.validate(&blobs)?;
+        let depths = self.layer_depths()?;
+
+        let mut layers: Vec<(usize, Layer)> = self.layers.into_iter().enumerate().collect();
+        layers.sort_by_key(|(_, layer)| depths.get(&layer.id).copied());
+
+        let mut doc = Document::with_blobs(blobs);
+        for (i, layer) in layers {
+            doc.insert_layer(layer)
+                .map_err(|e| invalid(format!("$.layers[{}]", i), e))?;
+        }
+        for (i, shape) in self.shapes.into_iter().enumerate() {
+            doc.insert_shape(shape)
+                .map_err(|e| invalid(format!("$.shapes[{}]", i), e))?;
+        }
+        for (i, artboard) in self.artboards.into_iter().enumerate() {
+            doc.insert_artboard(artboard)
+                .map_err(|e| invalid(format!("$.artboards[{}]", i), e))?;
+        }
+
+        Ok(doc)
+    }
+
+    /// Validate every field, reporting the first failure by JSON path.
+    /// MANDATE: ≤60 SLOC, no mutation.
+    pub fn validate(&self, blobs: &BlobStore) -> Result<(), FormatError> {
+        if self.version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(self.version.clone()));
+        }
+
+        // Layer ID -> is group, so shape checks are one lookup each
+        let mut layer_groups: HashMap<LayerId, bool> = HashMap::with_capacity(self.layers.len());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let path = format!("$.layers[{}]", i);
+            if layer_groups.insert(layer.id, layer.is_group).is_some() {
+                return Err(invalid_msg(format!("{}.id", path), "duplicate layer ID"));
+            }
+            validate_layer(&path, layer)?;
+        }
+        self.layer_depths()?;
+
+        let mut shape_ids = HashSet::new();
+        for (i, shape) in self.shapes.iter().enumerate() {
+            let path = format!("$.shapes[{}]", i);
+            if !shape_ids.insert(shape.id) {
+                return Err(invalid_msg(format!("{}.id", path), "duplicate shape ID"));
+            }
+            let problem = match layer_groups.get(&shape.layer_id) {
+                None => Some("unknown layer"),
+                Some(true) => Some("layer is a group"),
+                Some(false) => None,
+            };
+            if let Some(message) = problem {
+                return Err(invalid_msg(format!("{}.layer_id", path), message));
+            }
+            if shape.texture.as_ref().is_some_and(|id| !blobs.contains(id)) {
+                return Err(invalid_msg(format!("{}.texture", path), "missing blob"));
+            }
+            validate_shape(&path, shape)?;
+        }
+
+        let mut artboard_ids = HashSet::new();
+        for (i, artboard) in self.artboards.iter().enumerate() {
+            let path = format!("$.artboards[{}]", i);
+            if !artboard_ids.insert(artboard.id) {
+                return Err(invalid_msg(format!("{}.id", path), "duplicate artboard ID"));
+            }
+            validate_artboard(&path, artboard)?;
+        }
+
+        Ok(())
+    }
+
+    /// Nesting depth of every layer, top level being 1.
+    /// MANDATE: ≤60 SLOC, parents must be groups, walks bounded by MAX_DEPTH.
+    fn layer_depths(&self) -> Result<HashMap<LayerId, usize>, FormatError> {
+        let parents: HashMap<LayerId, Option<LayerId>> =
+            self.layers.iter().map(|l| (l.id, l.parent)).collect();
+        let groups: HashSet<LayerId> = self
+            .layers
+            .iter()
+            .filter(|l| l.is_group)
+            .map(|l| l.id)
+            .collect();
+
+        let mut depths = HashMap::with_capacity(self.layers.len());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let path = format!("$.layers[{}].parent", i);
+            let mut current = layer.parent;
+            let mut depth = 1;
+            while let Some(parent) = current {
+                if !groups.contains(&parent) {
+                    return Err(invalid_msg(path, "parent is not a group"));
+                }
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err(invalid_msg(path, "nesting too deep or cyclic"));
+                }
+                current = parents.get(&parent).copied().flatten();
+            }
+            depths.insert(layer.id, depth);
+        }
+
+        // MANDATE: Assertions
+        assert!(depths.values().all(|d| *d <= MAX_DEPTH));
+        Ok(depths)
+    }
+}
+
+/// Serialize document to pretty JSON.
+/// MANDATE: ≤60 SLOC, deterministic output.
+pub fn to_json(doc: &Document) -> Result<String, FormatError> {
+    let file = DocumentFile::capture(doc);
+    serde_json::to_string_pretty(&file).map_err(|e| FormatError::Malformed {
+        path: "$".into(),
+        message: e.to_string(),
+    })
+}
+
+/// Parse, migrate and validate document JSON, reporting what the migration
+/// changed.
+/// MANDATE: ≤60 SLOC.
+pub fn from_json(text: &str) -> Result<(Document, MigrationReport), FormatError> {
+    let (file, report) = parse_file(text)?;
+    Ok((file.into_document(BlobStore::new())?, report))
+}
+
+/// Parse and migrate document JSON without building the document.
+/// MANDATE: ≤60 SLOC, version upgraded before field decoding.
+pub fn parse_file(text: &str) -> Result<(DocumentFile, MigrationReport), FormatError> {
+    let value: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| FormatError::Malformed {
+            path: "$".into(),
+            message: e.to_string(),
+        })?;
+
+    let (value, report) = migration::upgrade(value)?;
+
+    let file: DocumentFile =
+        serde_path_to_error::deserialize(value).map_err(|e| FormatError::Malformed {
+            path: format!("$.{}", e.path()),
+            message: e.inner().to_string(),
+        })?;
+
+    Ok((file, report))
+}
+
+/// Save document to a .simple file.
+/// Plain JSON when no blobs are referenced, zip container otherwise.
+/// MANDATE: ≤60 SLOC, write-then-rename so a failed save keeps the old file,
+/// temp file removed on failure.
+pub fn save_document(doc: &Document, path: &Path) -> Result<(), FormatError> {
+    let tmp_path = path.with_extension("simple.tmp");
+
+    let written = if doc.referenced_blobs().is_empty() {
+        to_json(doc).and_then(|json| std::fs::write(&tmp_path, json.as_bytes()).map_err(io_error))
+    } else {
+        std::fs::File::create(&tmp_path)
+            .map_err(io_error)
+            .and_then(|file| container::write_container(doc, std::io::BufWriter::new(file)))
+    };
+    let result = written.and_then(|()| std::fs::rename(&tmp_path, path).map_err(io_error));
+    if result.is_err() {
+        // Best effort: the temp file does not exist if creating it failed
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Open document from a .simple file, plain JSON or zip container,
+/// reporting what the migration changed.
+/// MANDATE: ≤60 SLOC, bounded read.
+pub fn open_document(path: &Path) -> Result<(Document, MigrationReport), FormatError> {
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+    let mut magic = [0u8; 4];
+    let read = std::io::Read::read(&mut file, &mut magic).map_err(io_error)?;
+    if container::is_container(&magic[..read]) {
+        return container::read_container(std::io::BufReader::new(file));
+    }
+
+    let metadata = file.metadata().map_err(io_error)?;
+    if metadata.len() > MAX_FILE_BYTES {
+        return Err(FormatError::TooLarge(metadata.len()));
+    }
+
+    let text = std::fs::read_to_string(path).map_err(io_error)?;
+    from_json(&text)
+}
+
+/// Convert I/O error.
+/// MANDATE: ≤60 SLOC.
+pub fn io_error(err: std::io::Error) -> FormatError {
+    FormatError::Io(err.to_string())
+}
+
+/// Validate layer fields.
+/// MANDATE: ≤60 SLOC.
+fn validate_layer(path: &str, layer: &Layer) -> Result<(), FormatError> {
+    check(path, "name", validate_name(&layer.name, "layer"))?;
+    check(path, "opacity", validate_opacity(layer.opacity))?;
+    check(path, "z_order", validate_z_order(layer.z_order))?;
+    check(path, "transform", validate_affine(layer.transform))
+}
+
+/// Validate shape fields.
+/// MANDATE: ≤60 SLOC.
+fn validate_shape(path: &str, shape: &Shape) -> Result<(), FormatError> {
+    check(path, "position", validate_position(shape.position))?;
+    check(path, "size", validate_size(shape.size))?;
+    check(path, "rotation", validate_rotation(shape.rotation))?;
+    check(path, "skew", validate_skew(shape.skew))?;
+    check(path, "pivot", validate_pivot(shape.pivot))?;
+    check(path, "color", validate_color(shape.color))?;
+    check(path, "z_order", validate_z_order(shape.z_order))
+}
+
+/// Validate artboard fields.
+/// MANDATE: ≤60 SLOC.
+fn validate_artboard(path: &str, artboard: &Artboard) -> Result<(), FormatError> {
+    check(path, "name", validate_name(&artboard.name, "artboard"))?;
+    check(path, "position", validate_position(artboard.position))?;
+    check(path, "size", validate_size(artboard.size))?;
+    check(path, "background", validate_color(artboard.background))
+}
+
+/// Attach field path to validation result.
+/// MANDATE: ≤60 SLOC.
+fn check(path: &str, field: &str, result: Result<(), DocumentError>) -> Result<(), FormatError> {
+    result.map_err(|e| invalid(format!("{}.{}", path, field), e))
+}
+
+/// Build invalid-value error from document error.
+/// MANDATE: ≤60 SLOC.
+fn invalid(path: String, err: DocumentError) -> FormatError {
+    let message = match err {
+        DocumentError::InvalidValue(msg) | DocumentError::CapacityExceeded(msg) => msg,
+        other => other.to_string(),
+    };
+    FormatError::Invalid { path, message }
+}
+
+/// Build invalid-value error from message.
+/// MANDATE: ≤60 SLOC.
+fn invalid_msg(path: String, message: &str) -> FormatError {
+    FormatError::Invalid {
+        path,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::MaterialType;
+    use glam::{Mat3, Vec2, Vec4};
+
+    fn sample_document() -> Document {
+        let mut doc = Document::new();
+        let background = doc.add_layer("Background".to_string()).unwrap();
+        let content = doc.add_layer("Content".to_string()).unwrap();
+        doc.set_layer_z_order(content, 2).unwrap();
+        doc.set_layer_opacity(content, 0.75).unwrap();
+        doc.set_layer_visible(background, false).unwrap();
+        let offset = Mat3::from_scale_angle_translation(Vec2::splat(2.0), 0.0, Vec2::new(5.0, 6.0));
+        doc.set_layer_transform(content, offset).unwrap();
+
+        let a = doc
+            .add_shape(background, Vec2::ZERO, Vec2::new(100.0, 50.0))
+            .unwrap();
+        let b = doc
+            .add_shape(content, Vec2::new(-3.5, 7.25), Vec2::new(1.0, 2.0))
+            .unwrap();
+        doc.set_shape_color(a, Vec4::new(0.2, 0.4, 0.6, 0.8))
+            .unwrap();
+        doc.set_shape_z_order(b, -4).unwrap();
+        doc.set_shape_material(b, MaterialType::Textured).unwrap();
+        doc
+    }
+
+    fn sample_json() -> serde_json::Value {
+        serde_json::from_str(&to_json(&sample_document()).unwrap()).unwrap()
+    }
+
+    fn error_for(value: serde_json::Value) -> FormatError {
+        from_json(&value.to_string()).err().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_is_identical() {
+        let json = to_json(&sample_document()).unwrap();
+        let (reloaded, _) = from_json(&json).unwrap();
+
+        assert_eq!(to_json(&reloaded).unwrap(), json);
+        assert_eq!(reloaded.shape_count(), 2);
+        assert_eq!(reloaded.layers().get_layer(2).unwrap().opacity, 0.75);
+        assert_eq!(reloaded.shape(2).unwrap().material, MaterialType::Textured);
+        assert_eq!(reloaded.world_bounds(2), sample_document().world_bounds(2));
+    }
+
+    #[test]
+    fn test_version_is_first_class() {
+        let value = sample_json();
+        assert_eq!(value["version"], FORMAT_VERSION);
+
+        let mut missing = value.clone();
+        missing.as_object_mut().unwrap().remove("version");
+        assert_eq!(error_for(missing), FormatError::MissingVersion);
+
+        let mut future = value;
+        future["version"] = "9.0.0".into();
+        assert_eq!(error_for(future), FormatError::NewerVersion("9.0.0".into()));
+    }
+
+    #[test]
+    fn test_invalid_opacity_reports_path() {
+        let mut value = sample_json();
+        value["layers"][1]["opacity"] = 1.5.into();
+
+        assert!(matches!(
+            error_for(value),
+            FormatError::Invalid { path, .. } if path == "$.layers[1].opacity"
+        ));
+    }
+
+    #[test]
+    fn test_non_finite_reports_path() {
+        let mut value = sample_json();
+        value["shapes"][0]["size"] = serde_json::json!([0.0, 5.0]);
+
+        assert!(matches!(
+            error_for(value),
+            FormatError::Invalid { path, .. } if path == "$.shapes[0].size"
+        ));
+    }
+
+    #[test]
+    fn test_wrong_type_reports_path() {
+        let mut value = sample_json();
+        value["shapes"][1]["z_order"] = "high".into();
+
+        assert!(matches!(
+            error_for(value),
+            FormatError::Malformed { path, .. } if path == "$.shapes[1].z_order"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_and_dangling_ids() {
+        let mut duplicate = sample_json();
+        duplicate["shapes"][1]["id"] = duplicate["shapes"][0]["id"].clone();
+        assert!(matches!(
+            error_for(duplicate),
+            FormatError::Invalid { path, .. } if path == "$.shapes[1].id"
+        ));
+
+        let mut dangling = sample_json();
+        dangling["shapes"][0]["layer_id"] = 404.into();
+        assert!(matches!(
+            error_for(dangling),
+            FormatError::Invalid { path, .. } if path == "$.shapes[0].layer_id"
+        ));
+    }
+
+    #[test]
+    fn test_nested_groups_round_trip() {
+        let mut doc = sample_document();
+        let outer = doc.add_group("Outer".to_string()).unwrap();
+        let inner = doc.add_group("Inner".to_string()).unwrap();
+        doc.set_layer_parent(outer, Some(inner)).unwrap();
+        doc.set_layer_parent(1, Some(outer)).unwrap();
+
+        let json = to_json(&doc).unwrap();
+        let (reloaded, _) = from_json(&json).unwrap();
+        assert_eq!(to_json(&reloaded).unwrap(), json);
+        assert_eq!(reloaded.layers().ancestors(1), vec![outer, inner]);
+    }
+
+    #[test]
+    fn test_group_links_validated() {
+        let mut not_group = sample_json();
+        not_group["layers"][1]["parent"] = 1.into();
+        assert!(matches!(
+            error_for(not_group),
+            FormatError::Invalid { path, .. } if path == "$.layers[1].parent"
+        ));
+
+        let mut cyclic = sample_json();
+        for i in 0..2 {
+            cyclic["layers"][i]["is_group"] = true.into();
+            cyclic["layers"][i]["parent"] = (2 - i).into();
+        }
+        assert!(matches!(
+            error_for(cyclic),
+            FormatError::Invalid { path, .. } if path == "$.layers[0].parent"
+        ));
+    }
+
+    #[test]
+    fn test_artboards_round_trip_and_validate() {
+        let mut doc = sample_document();
+        let id = doc
+            .add_artboard("Phone".into(), Vec2::ZERO, Vec2::new(393.0, 852.0))
+            .unwrap();
+        let json = to_json(&doc).unwrap();
+        let (reloaded, _) = from_json(&json).unwrap();
+        assert_eq!(to_json(&reloaded).unwrap(), json);
+        assert_eq!(reloaded.artboard(id), doc.artboard(id));
+        assert_eq!(reloaded.artboard_at(Vec2::new(1.0, 1.0)), Some(id));
+        // Documents without artboards keep their old shape
+        assert!(sample_json().get("artboards").is_none());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["artboards"][0]["size"] = serde_json::json!([0.0, 10.0]);
+        assert!(matches!(
+            error_for(value),
+            FormatError::Invalid { path, .. } if path == "$.artboards[0].size"
+        ));
+    }
+
+    #[test]
+    fn test_save_and_open_file() {
+        let path = std::env::temp_dir().join(format!("sd-format-{}.simple", std::process::id()));
+        let doc = sample_document();
+
+        save_document(&doc, &path).unwrap();
+        let (reopened, _) = open_document(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(to_json(&reopened).unwrap(), to_json(&doc).unwrap());
+        assert!(matches!(open_document(&path), Err(FormatError::Io(_))));
+    }
+
+    #[test]
+    fn test_failed_save_removes_temp_file() {
+        // Renaming a file over a directory fails after the temp file is written
+        let path =
+            std::env::temp_dir().join(format!("sd-format-dir-{}.simple", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+
+        let result = save_document(&sample_document(), &path);
+        let leftover = path.with_extension("simple.tmp").exists();
+        std::fs::remove_dir(&path).unwrap();
+
+        assert!(matches!(result, Err(FormatError::Io(_))));
+        assert!(!leftover);
+    }
+}
@@ -0,0 +1,1331 @@
+// MANDATE: Command handlers over the document, testable without a window
+#![deny(warnings)]
+
+use crate::artboard::{Artboard, ArtboardContents, ArtboardId, ArtboardPreset};
+use crate::batch_ops::{self, BatchReport, OpacityBatch, TransformBatch, TransformTarget};
+use crate::command::{Command, Step};
+use crate::document::{validate_color, Document, DocumentError};
+use crate::export::jpeg::{self, JpegOptions};
+use crate::export::pdf::{self, PdfOptions};
+use crate::export::png;
+use crate::export::preset::{self, ExportPreset, PresetOutput};
+use crate::export::raster::RasterOptions;
+use crate::export::svg::{self, SvgOptions};
+use crate::export::webp::{self, WebpOptions};
+use crate::export::{ExportArea, ExportError};
+use crate::file_format::{self, FormatError};
+use crate::history::{History, HistoryStatus};
+use crate::import::{self, svg as svg_import, ImportError, ImportReport};
+use crate::layer::{Layer, LayerId};
+use crate::migration::MigrationReport;
+use crate::selection::{self, MarqueeMode, Selection, SelectionItem, MAX_SELECTION};
+use crate::shape::{Shape, ShapeId};
+use crate::transform::{Handle, Modifiers, TransformSession};
+use glam::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Structured command error returned to the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", content = "detail")]
+pub enum CommandError {
+    #[error("Layer not found: {0}")]
+    LayerNotFound(LayerId),
+    #[error("Shape not found: {0}")]
+    ShapeNotFound(ShapeId),
+    #[error("Artboard not found: {0}")]
+    ArtboardNotFound(ArtboardId),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Capacity exceeded: {0}")]
+    CapacityExceeded(String),
+    /// The document is partly changed and the history was cleared.
+    #[error("Rollback failed: {0}")]
+    RollbackFailed(String),
+    #[error("Application state unavailable")]
+    StateUnavailable,
+    #[error("File error: {0}")]
+    File(String),
+    #[error("Export error: {0}")]
+    Export(String),
+    #[error("Import error: {0}")]
+    Import(String),
+}
+
+impl From<DocumentError> for CommandError {
+    fn from(err: DocumentError) -> Self {
+        match err {
+            DocumentError::LayerNotFound(id) => Self::LayerNotFound(id),
+            DocumentError::ShapeNotFound(id) => Self::ShapeNotFound(id),
+            DocumentError::ArtboardNotFound(id) => Self::ArtboardNotFound(id),
+            DocumentError::InvalidValue(msg) => Self::InvalidInput(msg),
+            DocumentError::CapacityExceeded(msg) => Self::CapacityExceeded(msg),
+            DocumentError::RollbackFailed(msg) => Self::RollbackFailed(msg),
+        }
+    }
+}
+
+impl From<FormatError> for CommandError {
+    fn from(err: FormatError) -> Self {
+        Self::File(err.to_string())
+    }
+}
+
+impl From<ExportError> for CommandError {
+    fn from(err: ExportError) -> Self {
+        match err {
+            ExportError::InvalidOption(msg) => Self::InvalidInput(msg),
+            other => Self::Export(other.to_string()),
+        }
+    }
+}
+
+impl From<ImportError> for CommandError {
+    fn from(err: ImportError) -> Self {
+        match err {
+            ImportError::Document(err) => err.into(),
+            other => Self::Import(other.to_string()),
+        }
+    }
+}
+
+/// Request to create a shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddShapeRequest {
+    pub layer_id: LayerId,
+    pub position: Vec2,
+    pub size: Vec2,
+    pub color: Option<Vec4>,
+}
+
+/// Request to edit a shape; absent fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateShapeRequest {
+    pub id: ShapeId,
+    pub position: Option<Vec2>,
+    pub size: Option<Vec2>,
+    pub color: Option<Vec4>,
+    pub z_order: Option<i32>,
+    #[serde(default)]
+    pub pivot: Option<Vec2>,
+    #[serde(default)]
+    pub rotation: Option<f32>,
+    #[serde(default)]
+    pub skew: Option<f32>,
+}
+
+/// Request to create an artboard of an explicit size or a preset's size.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddArtboardRequest {
+    /// Defaults to the preset's label, or "Artboard".
+    pub name: Option<String>,
+    pub position: Vec2,
+    pub size: Option<Vec2>,
+    pub preset: Option<ArtboardPreset>,
+    pub background: Option<Vec4>,
+    pub clip_content: Option<bool>,
+}
+
+/// Request to edit an artboard; absent fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateArtboardRequest {
+    pub id: ArtboardId,
+    pub name: Option<String>,
+    pub position: Option<Vec2>,
+    pub size: Option<Vec2>,
+    pub background: Option<Vec4>,
+    pub clip_content: Option<bool>,
+}
+
+/// Artboard preset as listed in the new-artboard menu.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtboardPresetInfo {
+    pub preset: ArtboardPreset,
+    pub label: String,
+    pub size: Vec2,
+}
+
+/// Request to query a world-space rectangle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRectRequest {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Request to select shapes with a marquee rectangle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarqueeRequest {
+    pub min: Vec2,
+    pub max: Vec2,
+    #[serde(default)]
+    pub mode: MarqueeMode,
+}
+
+/// Selection edit requested by the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SelectRequest {
+    Replace {
+        items: Vec<SelectionItem>,
+    },
+    Add {
+        items: Vec<SelectionItem>,
+    },
+    Toggle {
+        items: Vec<SelectionItem>,
+    },
+    Remove {
+        items: Vec<SelectionItem>,
+    },
+    All,
+    Rect {
+        min: Vec2,
+        max: Vec2,
+        #[serde(default)]
+        mode: MarqueeMode,
+        #[serde(default)]
+        additive: bool,
+    },
+    Clear,
+}
+
+/// Response for layer removal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoveLayerResponse {
+    pub layer_id: LayerId,
+    pub removed_shapes: Vec<ShapeId>,
+}
+
+/// Response for hit testing, topmost shape first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HitTestResponse {
+    pub hits: Vec<ShapeId>,
+}
+
+/// Add shape.
+/// MANDATE: ≤60 SLOC, all-or-nothing on invalid color.
+pub fn add_shape(
+    doc: &mut Document,
+    history: &mut History,
+    request: AddShapeRequest,
+) -> Result<Shape, CommandError> {
+    // MANDATE: Input validation
+    if let Some(color) = request.color {
+        validate_color(color)?;
+    }
+
+    let id = doc.add_shape(request.layer_id, request.position, request.size)?;
+    if let Some(color) = request.color {
+        doc.set_shape_color(id, color)?;
+    }
+
+    let shape = get_shape(doc, id)?;
+    history.record(Command::InsertShape(shape.clone()), false);
+    Ok(shape)
+}
+
+/// Remove shape.
+/// MANDATE: ≤60 SLOC.
+pub fn remove_shape(
+    doc: &mut Document,
+    history: &mut History,
+    id: ShapeId,
+) -> Result<Shape, CommandError> {
+    let shape = doc.remove_shape(id)?;
+    history.record(Command::DeleteShape(shape.clone()), false);
+    Ok(shape)
+}
+
+/// Update shape fields.
+/// MANDATE: ≤60 SLOC, original restored if any field is invalid.
+pub fn update_shape(
+    doc: &mut Document,
+    history: &mut History,
+    request: UpdateShapeRequest,
+) -> Result<Shape, CommandError> {
+    let original = get_shape(doc, request.id)?;
+
+    let result = apply_update(doc, &request);
+    if let Err(err) = result {
+        doc.replace_shape(original)?;
+        return Err(err);
+    }
+
+    let shape = get_shape(doc, request.id)?;
+    history.record(Step::updates(doc, Vec::new(), vec![original]), false);
+    Ok(shape)
+}
+
+/// Add layer.
+/// MANDATE: ≤60 SLOC.
+pub fn add_layer(
+    doc: &mut Document,
+    history: &mut History,
+    name: String,
+) -> Result<Layer, CommandError> {
+    let id = doc.add_layer(name)?;
+    let layer = get_layer(doc, id)?;
+
+    history.record(
+        Command::InsertLayer {
+            layer: layer.clone(),
+            shapes: Vec::new(),
+        },
+        false,
+    );
+    Ok(layer)
+}
+
+/// Add empty group.
+/// MANDATE: ≤60 SLOC.
+pub fn add_group(
+    doc: &mut Document,
+    history: &mut History,
+    name: String,
+) -> Result<Layer, CommandError> {
+    let id = doc.add_group(name)?;
+    let group = get_layer(doc, id)?;
+
+    history.record(
+        Command::InsertLayer {
+            layer: group.clone(),
+            shapes: Vec::new(),
+        },
+        false,
+    );
+    Ok(group)
+}
+
+/// Remove layer with its descendants and their shapes.
+/// MANDATE: ≤60 SLOC, deepest layers deleted first so undo restores parents first.
+pub fn remove_layer(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+) -> Result<RemoveLayerResponse, CommandError> {
+    let mut subtree = vec![get_layer(doc, id)?];
+    for descendant in doc.layers().descendants(id) {
+        subtree.push(get_layer(doc, descendant)?);
+    }
+
+    let mut shapes: HashMap<LayerId, Vec<Shape>> = HashMap::new();
+    for shape_id in doc.shapes_in_subtree(id) {
+        let shape = get_shape(doc, shape_id)?;
+        shapes.entry(shape.layer_id).or_default().push(shape);
+    }
+
+    let mut step = Step::new();
+    for layer in subtree.iter().rev() {
+        let shapes = shapes.remove(&layer.id).unwrap_or_default();
+        step.extend(Command::delete_layer(layer, shapes).into())?;
+    }
+    let removed_shapes = doc.remove_layer(id)?;
+
+    history.record(step, false);
+    Ok(RemoveLayerResponse {
+        layer_id: id,
+        removed_shapes,
+    })
+}
+
+/// Move layer into group (None for top level), optionally to a sibling index.
+/// MANDATE: ≤60 SLOC, renumbered siblings undo with the move.
+pub fn move_layer(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    parent: Option<LayerId>,
+    index: Option<usize>,
+) -> Result<Layer, CommandError> {
+    let before = all_layers(doc);
+    doc.set_layer_parent(id, parent)?;
+    if let Some(index) = index {
+        doc.reorder_sibling(id, index)?;
+    }
+
+    history.record(Step::updates(doc, before, Vec::new()), false);
+    get_layer(doc, id)
+}
+
+/// Dissolve group, keeping its children on screen where they were.
+/// MANDATE: ≤60 SLOC, returns the former children.
+pub fn ungroup_layer(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+) -> Result<Vec<Layer>, CommandError> {
+    let group = get_layer(doc, id)?;
+    let before = all_layers(doc);
+    let children = doc.ungroup(id)?;
+
+    let mut step = Step::updates(doc, before, Vec::new());
+    step.extend(Step::from(Command::DeleteLayer {
+        layer: group,
+        shapes: Vec::new(),
+    }))?;
+    history.record(step, false);
+
+    children
+        .into_iter()
+        .map(|child| get_layer(doc, child))
+        .collect()
+}
+
+/// Reorder layer.
+/// MANDATE: ≤60 SLOC.
+pub fn reorder_layer(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    z_order: i32,
+) -> Result<Layer, CommandError> {
+    edit_layer(doc, history, id, |doc| doc.set_layer_z_order(id, z_order))
+}
+
+/// Set layer visibility.
+/// MANDATE: ≤60 SLOC.
+pub fn set_visibility(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    visible: bool,
+) -> Result<Layer, CommandError> {
+    edit_layer(doc, history, id, |doc| doc.set_layer_visible(id, visible))
+}
+
+/// Set layer opacity.
+/// MANDATE: ≤60 SLOC.
+pub fn set_opacity(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    opacity: f32,
+) -> Result<Layer, CommandError> {
+    edit_layer(doc, history, id, |doc| doc.set_layer_opacity(id, opacity))
+}
+
+/// Lock or unlock layer against picking.
+/// MANDATE: ≤60 SLOC.
+pub fn set_locked(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    locked: bool,
+) -> Result<Layer, CommandError> {
+    edit_layer(doc, history, id, |doc| doc.set_layer_locked(id, locked))
+}
+
+/// Hit-test world point against exact shape outlines.
+/// MANDATE: ≤60 SLOC, validated input.
+pub fn hit_test(doc: &Document, point: Vec2) -> Result<HitTestResponse, CommandError> {
+    if !point.is_finite() {
+        return Err(CommandError::InvalidInput("point not finite".into()));
+    }
+
+    Ok(HitTestResponse {
+        hits: selection::hit_test(doc, point),
+    })
+}
+
+/// Select shapes with a marquee rectangle.
+/// MANDATE: ≤60 SLOC, validated bounds.
+pub fn marquee_select(
+    doc: &Document,
+    request: MarqueeRequest,
+) -> Result<Vec<ShapeId>, CommandError> {
+    let MarqueeRequest { min, max, mode } = request;
+
+    // MANDATE: Input validation
+    if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y {
+        return Err(CommandError::InvalidInput(
+            "invalid marquee rectangle".into(),
+        ));
+    }
+
+    Ok(selection::marquee(doc, min, max, mode))
+}
+
+/// Query shapes intersecting rectangle.
+/// MANDATE: ≤60 SLOC, validated bounds.
+pub fn query_rect(doc: &Document, request: QueryRectRequest) -> Result<Vec<ShapeId>, CommandError> {
+    let QueryRectRequest { min, max } = request;
+
+    // MANDATE: Input validation (SpatialIndex::query_rect asserts this)
+    if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y {
+        return Err(CommandError::InvalidInput("invalid query rectangle".into()));
+    }
+
+    Ok(doc.query_rect(min, max))
+}
+
+/// Edit selection. Returns whether it changed.
+/// MANDATE: ≤60 SLOC, items must exist.
+pub fn select(
+    doc: &Document,
+    selection: &mut Selection,
+    request: SelectRequest,
+) -> Result<bool, CommandError> {
+    let changed = match request {
+        SelectRequest::Replace { items } => selection.replace(&existing(doc, items)?),
+        SelectRequest::Add { items } => selection.add(&existing(doc, items)?),
+        SelectRequest::Toggle { items } => selection.toggle(&existing(doc, items)?),
+        SelectRequest::Remove { items } => selection.remove(&items),
+        SelectRequest::All => selection.select_all(doc),
+        SelectRequest::Rect {
+            min,
+            max,
+            mode,
+            additive,
+        } => {
+            // MANDATE: Input validation
+            if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y {
+                return Err(CommandError::InvalidInput(
+                    "invalid selection rectangle".into(),
+                ));
+            }
+            selection.select_in_rect(doc, (min, max), mode, additive)
+        }
+        SelectRequest::Clear => selection.clear(),
+    };
+    Ok(changed)
+}
+
+/// Apply transform batch atomically.
+/// MANDATE: ≤60 SLOC, one IPC round-trip per drag frame.
+/// Frames after the first of a drag pass `coalesce` so the drag undoes as one step.
+pub fn update_transforms(
+    doc: &mut Document,
+    history: &mut History,
+    batch: &TransformBatch,
+    coalesce: bool,
+) -> Result<BatchReport, CommandError> {
+    let mut layers = Vec::new();
+    let mut shapes = Vec::new();
+    for update in batch.updates() {
+        match update.target {
+            TransformTarget::Layer(id) => layers.extend(doc.layers().get_layer(id).cloned()),
+            TransformTarget::Shape(id) => shapes.extend(doc.shape(id).cloned()),
+        }
+    }
+
+    let report = batch_ops::apply_transforms(doc, batch)?;
+    history.record(Step::updates(doc, layers, shapes), coalesce);
+    Ok(report)
+}
+
+/// Apply opacity batch atomically.
+/// MANDATE: ≤60 SLOC.
+pub fn update_opacities(
+    doc: &mut Document,
+    history: &mut History,
+    batch: &OpacityBatch,
+    coalesce: bool,
+) -> Result<BatchReport, CommandError> {
+    let layers: Vec<Layer> = batch
+        .updates()
+        .iter()
+        .filter_map(|update| doc.layers().get_layer(update.id).cloned())
+        .collect();
+
+    let report = batch_ops::apply_opacities(doc, batch)?;
+    history.record(Step::updates(doc, layers, Vec::new()), coalesce);
+    Ok(report)
+}
+
+/// Start dragging a transform handle over the selection.
+/// MANDATE: ≤60 SLOC, history sealed so the drag starts a new undo step.
+pub fn begin_transform(
+    doc: &Document,
+    history: &mut History,
+    selection: &Selection,
+    handle: Handle,
+    start: Vec2,
+) -> Result<TransformSession, CommandError> {
+    let session = TransformSession::begin(doc, selection, handle, start)?;
+    history.seal();
+    Ok(session)
+}
+
+/// Apply one drag frame; every frame of a session undoes as one step.
+/// MANDATE: ≤60 SLOC.
+pub fn drag_transform(
+    doc: &mut Document,
+    history: &mut History,
+    session: &TransformSession,
+    current: Vec2,
+    modifiers: Modifiers,
+) -> Result<BatchReport, CommandError> {
+    let batch = session.update(current, modifiers)?;
+    update_transforms(doc, history, &batch, true)
+}
+
+/// Undo last step.
+/// MANDATE: ≤60 SLOC.
+pub fn undo(doc: &mut Document, history: &mut History) -> Result<HistoryStatus, CommandError> {
+    history.undo(doc)?;
+    Ok(history.status())
+}
+
+/// Redo last undone step.
+/// MANDATE: ≤60 SLOC.
+pub fn redo(doc: &mut Document, history: &mut History) -> Result<HistoryStatus, CommandError> {
+    history.redo(doc)?;
+    Ok(history.status())
+}
+
+/// Save document to path.
+/// MANDATE: ≤60 SLOC.
+pub fn save_document(doc: &Document, path: &str) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    Ok(file_format::save_document(doc, Path::new(path))?)
+}
+
+/// Open document from path, replacing the current one only on success, and
+/// report what upgrading an older file changed.
+/// MANDATE: ≤60 SLOC, history of the previous document dropped.
+pub fn open_document(
+    doc: &mut Document,
+    history: &mut History,
+    path: &str,
+) -> Result<MigrationReport, CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    let (opened, report) = file_format::open_document(Path::new(path))?;
+    *doc = opened;
+    history.clear();
+    Ok(report)
+}
+
+/// Export the requested area as a PNG file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_png(
+    doc: &Document,
+    selection: &Selection,
+    path: &str,
+    options: &RasterOptions,
+) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    png::export_png(doc, selection, options, Path::new(path))?;
+    Ok(())
+}
+
+/// Export the requested area as a JPEG file, flattened onto the background.
+/// MANDATE: ≤60 SLOC.
+pub fn export_jpeg(
+    doc: &Document,
+    selection: &Selection,
+    path: &str,
+    options: &JpegOptions,
+) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    jpeg::export_jpeg(doc, selection, options, Path::new(path))?;
+    Ok(())
+}
+
+/// Export the requested area as a WebP file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_webp(
+    doc: &Document,
+    selection: &Selection,
+    path: &str,
+    options: &WebpOptions,
+) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    webp::export_webp(doc, selection, options, Path::new(path))?;
+    Ok(())
+}
+
+/// Export the requested area once per preset into `dir`; the built-in
+/// presets when none are given.
+/// MANDATE: ≤60 SLOC.
+pub fn export_presets(
+    doc: &Document,
+    selection: &Selection,
+    dir: &str,
+    stem: &str,
+    area: ExportArea,
+    presets: Option<Vec<ExportPreset>>,
+) -> Result<Vec<PresetOutput>, CommandError> {
+    if dir.is_empty() {
+        return Err(CommandError::InvalidInput("empty directory".into()));
+    }
+    let presets = presets.unwrap_or_else(preset::builtin_presets);
+    Ok(preset::export_presets(
+        doc,
+        selection,
+        area,
+        &presets,
+        Path::new(dir),
+        stem,
+    )?)
+}
+
+/// Export the document as an SVG file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_svg(
+    doc: &Document,
+    selection: &Selection,
+    path: &str,
+    options: &SvgOptions,
+) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    Ok(svg::export_svg(doc, selection, options, Path::new(path))?)
+}
+
+/// Export the requested area as a vector PDF file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_pdf(
+    doc: &Document,
+    selection: &Selection,
+    path: &str,
+    options: &PdfOptions,
+) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    Ok(pdf::export_pdf(doc, selection, options, Path::new(path))?)
+}
+
+/// Import an SVG file as new layers, undoable as one step.
+/// MANDATE: ≤60 SLOC, layers recorded parents first so undo removes children first.
+pub fn import_svg(
+    doc: &mut Document,
+    history: &mut History,
+    path: &str,
+) -> Result<ImportReport, CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    let source = import::read_text(Path::new(path))?;
+    let report = svg_import::import_svg(doc, &source)?;
+
+    let mut step = Step::new();
+    for id in &report.layers {
+        let layer = get_layer(doc, *id)?;
+        let shapes = doc.shapes_in_layer(*id).into_iter().cloned().collect();
+        step.extend(Command::InsertLayer { layer, shapes }.into())?;
+    }
+    history.record(step, false);
+    Ok(report)
+}
+
+/// Add artboard.
+/// MANDATE: ≤60 SLOC, exactly one of size or preset, all-or-nothing on invalid fields.
+pub fn add_artboard(
+    doc: &mut Document,
+    history: &mut History,
+    request: AddArtboardRequest,
+) -> Result<Artboard, CommandError> {
+    // MANDATE: Input validation
+    let size = match (request.size, request.preset) {
+        (Some(size), None) => size,
+        (None, Some(preset)) => preset.size(),
+        _ => {
+            return Err(CommandError::InvalidInput(
+                "give exactly one of size or preset".into(),
+            ))
+        }
+    };
+    let name = match (request.name, request.preset) {
+        (Some(name), _) => name,
+        (None, Some(preset)) => preset.label().into(),
+        (None, None) => "Artboard".into(),
+    };
+
+    if let Some(background) = request.background {
+        validate_color(background)?;
+    }
+
+    let id = doc.add_artboard(name, request.position, size)?;
+    let mut artboard = get_artboard(doc, id)?;
+    artboard.background = request.background.unwrap_or(artboard.background);
+    artboard.clip_content = request.clip_content.unwrap_or(artboard.clip_content);
+    doc.replace_artboard(artboard.clone())?;
+
+    history.record(Command::InsertArtboard(artboard.clone()), false);
+    Ok(artboard)
+}
+
+/// Update artboard fields.
+/// MANDATE: ≤60 SLOC, nothing changes if any field is invalid.
+pub fn update_artboard(
+    doc: &mut Document,
+    history: &mut History,
+    request: UpdateArtboardRequest,
+) -> Result<Artboard, CommandError> {
+    let before = get_artboard(doc, request.id)?;
+    let after = Artboard {
+        name: request.name.unwrap_or_else(|| before.name.clone()),
+        position: request.position.unwrap_or(before.position),
+        size: request.size.unwrap_or(before.size),
+        background: request.background.unwrap_or(before.background),
+        clip_content: request.clip_content.unwrap_or(before.clip_content),
+        ..before.clone()
+    };
+    doc.replace_artboard(after.clone())?;
+
+    history.record(
+        Command::UpdateArtboard {
+            before,
+            after: after.clone(),
+        },
+        false,
+    );
+    Ok(after)
+}
+
+/// Remove artboard; its contents stay on the canvas.
+/// MANDATE: ≤60 SLOC.
+pub fn remove_artboard(
+    doc: &mut Document,
+    history: &mut History,
+    id: ArtboardId,
+) -> Result<Artboard, CommandError> {
+    let artboard = doc.remove_artboard(id)?;
+    history.record(Command::DeleteArtboard(artboard.clone()), false);
+    Ok(artboard)
+}
+
+/// List artboards in stacking order.
+/// MANDATE: ≤60 SLOC.
+pub fn list_artboards(doc: &Document) -> Vec<Artboard> {
+    doc.artboards().into_iter().cloned().collect()
+}
+
+/// Artboard presets in menu order.
+/// MANDATE: ≤60 SLOC.
+pub fn list_artboard_presets() -> Vec<ArtboardPresetInfo> {
+    ArtboardPreset::ALL
+        .iter()
+        .map(|&preset| ArtboardPresetInfo {
+            preset,
+            label: preset.label().into(),
+            size: preset.size(),
+        })
+        .collect()
+}
+
+/// Topmost artboard under a world point.
+/// MANDATE: ≤60 SLOC, validated input.
+pub fn artboard_at(doc: &Document, point: Vec2) -> Result<Option<ArtboardId>, CommandError> {
+    if !point.is_finite() {
+        return Err(CommandError::InvalidInput("point not finite".into()));
+    }
+    Ok(doc.artboard_at(point))
+}
+
+/// Layers and shapes lying inside an artboard.
+/// MANDATE: ≤60 SLOC.
+pub fn artboard_contents(doc: &Document, id: ArtboardId) -> Result<ArtboardContents, CommandError> {
+    Ok(doc.artboard_contents(id)?)
+}
+
+/// Export every artboard once per preset into `dir`, named after the
+/// artboards; the built-in presets when none are given.
+/// MANDATE: ≤60 SLOC.
+pub fn export_artboards(
+    doc: &Document,
+    selection: &Selection,
+    dir: &str,
+    presets: Option<Vec<ExportPreset>>,
+) -> Result<Vec<PresetOutput>, CommandError> {
+    if dir.is_empty() {
+        return Err(CommandError::InvalidInput("empty directory".into()));
+    }
+    let presets = presets.unwrap_or_else(preset::builtin_presets);
+    Ok(preset::export_artboards(
+        doc,
+        selection,
+        &presets,
+        Path::new(dir),
+    )?)
+}
+
+/// Export every artboard as one page of a PDF file.
+/// MANDATE: ≤60 SLOC.
+pub fn export_artboards_pdf(doc: &Document, path: &str) -> Result<(), CommandError> {
+    if path.is_empty() {
+        return Err(CommandError::InvalidInput("empty path".into()));
+    }
+    Ok(pdf::export_artboards_pdf(doc, Path::new(path))?)
+}
+
+/// Apply optional update fields in order.
+/// MANDATE: ≤60 SLOC.
+fn apply_update(doc: &mut Document, request: &UpdateShapeRequest) -> Result<(), CommandError> {
+    if let Some(position) = request.position {
+        doc.move_shape(request.id, position)?;
+    }
+    if let Some(size) = request.size {
+        doc.resize_shape(request.id, size)?;
+    }
+    if let Some(color) = request.color {
+        doc.set_shape_color(request.id, color)?;
+    }
+    if let Some(z_order) = request.z_order {
+        doc.set_shape_z_order(request.id, z_order)?;
+    }
+    // Pivot first, so rotation and skew apply about the new pivot
+    if let Some(pivot) = request.pivot {
+        doc.set_shape_pivot(request.id, pivot)?;
+    }
+    if let Some(rotation) = request.rotation {
+        doc.set_shape_rotation(request.id, rotation)?;
+    }
+    if let Some(skew) = request.skew {
+        doc.set_shape_skew(request.id, skew)?;
+    }
+    Ok(())
+}
+
+/// Apply layer edit and record it.
+/// MANDATE: ≤60 SLOC.
+fn edit_layer<F>(
+    doc: &mut Document,
+    history: &mut History,
+    id: LayerId,
+    edit: F,
+) -> Result<Layer, CommandError>
+where
+    F: FnOnce(&mut Document) -> Result<(), DocumentError>,
+{
+    let before = get_layer(doc, id)?;
+    edit(doc)?;
+
+    history.record(Step::updates(doc, vec![before], Vec::new()), false);
+    get_layer(doc, id)
+}
+
+/// Check selection items exist.
+/// MANDATE: ≤60 SLOC, bounded count.
+fn existing(doc: &Document, items: Vec<SelectionItem>) -> Result<Vec<SelectionItem>, CommandError> {
+    // MANDATE: Input validation
+    if items.len() > MAX_SELECTION {
+        return Err(CommandError::CapacityExceeded("selection too large".into()));
+    }
+    for item in &items {
+        match item {
+            SelectionItem::Shape(id) => {
+                get_shape(doc, *id)?;
+            }
+            SelectionItem::Layer(id) => {
+                get_layer(doc, *id)?;
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Clone shape for response.
+/// MANDATE: ≤60 SLOC.
+fn get_shape(doc: &Document, id: ShapeId) -> Result<Shape, CommandError> {
+    doc.shape(id)
+        .cloned()
+        .ok_or(CommandError::ShapeNotFound(id))
+}
+
+/// Clone artboard for response.
+/// MANDATE: ≤60 SLOC.
+fn get_artboard(doc: &Document, id: ArtboardId) -> Result<Artboard, CommandError> {
+    doc.artboard(id)
+        .cloned()
+        .ok_or(CommandError::ArtboardNotFound(id))
+}
+
+/// Snapshot every layer, for edits that renumber siblings.
+/// MANDATE: ≤60 SLOC.
+fn all_layers(doc: &Document) -> Vec<Layer> {
+    doc.layers()
+        .get_sorted_layers()
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+/// Clone layer for response.
+/// MANDATE: ≤60 SLOC.
+fn get_layer(doc: &Document, id: LayerId) -> Result<Layer, CommandError> {
+    doc.layers()
+        .get_layer(id)
+        .cloned()
+        .ok_or(CommandError::LayerNotFound(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Document, History, LayerId) {
+        let mut doc = Document::new();
+        let mut history = History::new();
+        let layer = add_layer(&mut doc, &mut history, "Layer 1".to_string()).unwrap();
+        (doc, history, layer.id)
+    }
+
+    fn square(layer_id: LayerId) -> AddShapeRequest {
+        AddShapeRequest {
+            layer_id,
+            position: Vec2::ZERO,
+            size: Vec2::new(10.0, 10.0),
+            color: None,
+        }
+    }
+
+    #[test]
+    fn test_add_shape_with_color() {
+        let (mut doc, mut history, layer) = setup();
+        let mut request = square(layer);
+        request.color = Some(Vec4::new(0.0, 1.0, 0.0, 1.0));
+
+        let shape = add_shape(&mut doc, &mut history, request).unwrap();
+        assert_eq!(shape.color, Vec4::new(0.0, 1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_add_shape_bad_color_rolls_back() {
+        let (mut doc, mut history, layer) = setup();
+        let mut request = square(layer);
+        request.color = Some(Vec4::splat(3.0));
+
+        let result = add_shape(&mut doc, &mut history, request);
+        assert!(matches!(result, Err(CommandError::InvalidInput(_))));
+        assert_eq!(doc.shape_count(), 0);
+        // The rejected request used no shape ID
+        assert_eq!(
+            add_shape(&mut doc, &mut history, square(layer)).unwrap().id,
+            1
+        );
+    }
+
+    #[test]
+    fn test_update_shape_partial() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+
+        let request = UpdateShapeRequest {
+            id,
+            position: Some(Vec2::new(50.0, 50.0)),
+            ..Default::default()
+        };
+        let shape = update_shape(&mut doc, &mut history, request).unwrap();
+
+        assert_eq!(shape.position, Vec2::new(50.0, 50.0));
+        assert_eq!(shape.size, Vec2::new(10.0, 10.0));
+        assert_eq!(
+            hit_test(&doc, Vec2::new(55.0, 55.0)).unwrap().hits,
+            vec![id]
+        );
+    }
+
+    #[test]
+    fn test_update_shape_failure_restores() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+
+        let request = UpdateShapeRequest {
+            id,
+            position: Some(Vec2::new(50.0, 50.0)),
+            size: Some(Vec2::new(-1.0, 1.0)),
+            ..Default::default()
+        };
+        assert!(update_shape(&mut doc, &mut history, request).is_err());
+        assert_eq!(doc.shape(id).unwrap().position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_remove_layer_reports_shapes() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+
+        let response = remove_layer(&mut doc, &mut history, layer).unwrap();
+        assert_eq!(response.removed_shapes, vec![id]);
+        assert!(matches!(
+            remove_shape(&mut doc, &mut history, id),
+            Err(CommandError::ShapeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_layer_commands() {
+        let (mut doc, mut history, layer) = setup();
+
+        assert!(
+            !set_visibility(&mut doc, &mut history, layer, false)
+                .unwrap()
+                .visible
+        );
+        assert_eq!(
+            set_opacity(&mut doc, &mut history, layer, 0.25)
+                .unwrap()
+                .opacity,
+            0.25
+        );
+        assert_eq!(
+            reorder_layer(&mut doc, &mut history, layer, 7)
+                .unwrap()
+                .z_order,
+            7
+        );
+        assert_eq!(
+            set_opacity(&mut doc, &mut history, 99, 0.5).unwrap_err(),
+            CommandError::LayerNotFound(99)
+        );
+    }
+
+    #[test]
+    fn test_query_rect_validates() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+
+        let ok = QueryRectRequest {
+            min: Vec2::new(-5.0, -5.0),
+            max: Vec2::new(5.0, 5.0),
+        };
+        assert_eq!(query_rect(&doc, ok).unwrap(), vec![id]);
+
+        let inverted = QueryRectRequest {
+            min: Vec2::new(5.0, 5.0),
+            max: Vec2::new(-5.0, -5.0),
+        };
+        assert!(query_rect(&doc, inverted).is_err());
+    }
+
+    #[test]
+    fn test_update_transforms_from_json() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+        let json = format!(
+            r#"{{"updates":[{{"id":{},"target":"Shape","transform":[10,0,0,0,10,0,3,4,1]}}]}}"#,
+            id
+        );
+        let batch: TransformBatch = serde_json::from_str(&json).unwrap();
+
+        let report = update_transforms(&mut doc, &mut history, &batch, false).unwrap();
+
+        assert_eq!(report.shapes, vec![id]);
+        assert_eq!(doc.shape(id).unwrap().position, Vec2::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn test_undo_redo_remove_layer() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+        remove_layer(&mut doc, &mut history, layer).unwrap();
+
+        let status = undo(&mut doc, &mut history).unwrap();
+        assert_eq!(status.redo_steps, 1);
+        assert_eq!(hit_test(&doc, Vec2::splat(5.0)).unwrap().hits, vec![id]);
+
+        redo(&mut doc, &mut history).unwrap();
+        assert!(doc.layers().is_empty());
+    }
+
+    #[test]
+    fn test_drag_frames_undo_as_one_step() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+
+        for (i, x) in [5.0, 10.0, 20.0].into_iter().enumerate() {
+            let mut batch = TransformBatch::new();
+            let transform =
+                glam::Mat3::from_scale_angle_translation(Vec2::splat(10.0), 0.0, Vec2::new(x, 0.0));
+            batch
+                .add(crate::batch_ops::TransformUpdate::for_shape(id, transform))
+                .unwrap();
+            update_transforms(&mut doc, &mut history, &batch, i > 0).unwrap();
+        }
+        assert_eq!(history.status().undo_steps, 3);
+
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(doc.shape(id).unwrap().position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_error_serializes_tagged() {
+        let json = serde_json::to_string(&CommandError::ShapeNotFound(4)).unwrap();
+        assert_eq!(json, r#"{"kind":"ShapeNotFound","detail":4}"#);
+    }
+
+    #[test]
+    fn test_group_delete_and_ungroup_undo() {
+        let (mut doc, mut history, layer) = setup();
+        let group = add_group(&mut doc, &mut history, "Group".to_string()).unwrap();
+        add_shape(&mut doc, &mut history, square(layer)).unwrap();
+        move_layer(&mut doc, &mut history, layer, Some(group.id), None).unwrap();
+        let grouped = file_format::to_json(&doc).unwrap();
+
+        remove_layer(&mut doc, &mut history, group.id).unwrap();
+        assert!(doc.layers().is_empty());
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(file_format::to_json(&doc).unwrap(), grouped);
+
+        let children = ungroup_layer(&mut doc, &mut history, group.id).unwrap();
+        assert_eq!(children[0].parent, None);
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(file_format::to_json(&doc).unwrap(), grouped);
+
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(get_layer(&doc, layer).unwrap().parent, None);
+    }
+
+    #[test]
+    fn test_selection_survives_undo() {
+        let (mut doc, mut history, layer) = setup();
+        let mut selection = Selection::new();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+        let items = vec![SelectionItem::Shape(id)];
+
+        assert!(select(&doc, &mut selection, SelectRequest::Add { items }).unwrap());
+        update_shape(
+            &mut doc,
+            &mut history,
+            UpdateShapeRequest {
+                id,
+                position: Some(Vec2::splat(40.0)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        undo(&mut doc, &mut history).unwrap();
+        assert!(!selection.prune(&doc));
+        assert_eq!(selection.shapes(), vec![id]);
+
+        undo(&mut doc, &mut history).unwrap();
+        assert!(selection.prune(&doc));
+        let missing = vec![SelectionItem::Shape(id)];
+        let result = select(
+            &doc,
+            &mut selection,
+            SelectRequest::Replace { items: missing },
+        );
+        assert_eq!(result, Err(CommandError::ShapeNotFound(id)));
+    }
+
+    #[test]
+    fn test_transform_drag_undoes_as_one_step() {
+        let (mut doc, mut history, layer) = setup();
+        let id = add_shape(&mut doc, &mut history, square(layer)).unwrap().id;
+        let mut selection = Selection::new();
+        selection.add(&[SelectionItem::Shape(id)]);
+
+        let steps = history.status().undo_steps;
+        let session =
+            begin_transform(&doc, &mut history, &selection, Handle::Move, Vec2::ZERO).unwrap();
+        for x in 1..=5 {
+            let current = Vec2::new(x as f32, 0.0);
+            drag_transform(
+                &mut doc,
+                &mut history,
+                &session,
+                current,
+                Modifiers::default(),
+            )
+            .unwrap();
+        }
+        history.seal();
+        assert_eq!(get_shape(&doc, id).unwrap().position, Vec2::new(5.0, 0.0));
+
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(get_shape(&doc, id).unwrap().position, Vec2::ZERO);
+        assert_eq!(history.status().undo_steps, steps);
+    }
+
+    #[test]
+    fn test_import_svg_undoes_as_one_step() {
+        let (mut doc, mut history, _) = setup();
+        let path = std::env::temp_dir().join(format!("import-{}.svg", std::process::id()));
+        std::fs::write(&path, include_str!("../tests/fixtures/svg/nested.svg")).unwrap();
+        let layers = doc.layers().len();
+
+        let report = import_svg(&mut doc, &mut history, path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(doc.layers().len(), layers + report.layers.len());
+        assert_eq!(doc.shape_count(), report.shapes);
+
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!((doc.layers().len(), doc.shape_count()), (layers, 0));
+        redo(&mut doc, &mut history).unwrap();
+        assert_eq!(doc.shape_count(), report.shapes);
+        assert!(import_svg(&mut doc, &mut history, "").is_err());
+    }
+
+    #[test]
+    fn test_artboard_edits_undo() {
+        let (mut doc, mut history, _) = setup();
+        let request = AddArtboardRequest {
+            position: Vec2::new(0.0, 0.0),
+            preset: Some(ArtboardPreset::Iphone),
+            ..Default::default()
+        };
+        let board = add_artboard(&mut doc, &mut history, request).unwrap();
+        assert_eq!(board.name, "iPhone");
+        assert_eq!(board.size, Vec2::new(393.0, 852.0));
+        assert_eq!(artboard_at(&doc, Vec2::new(10.0, 10.0)), Ok(Some(board.id)));
+
+        // Both or neither of size and preset is rejected; a bad colour leaves nothing
+        let both = AddArtboardRequest {
+            size: Some(Vec2::ONE),
+            preset: Some(ArtboardPreset::A4),
+            ..Default::default()
+        };
+        assert!(add_artboard(&mut doc, &mut history, both).is_err());
+        let bad = AddArtboardRequest {
+            size: Some(Vec2::ONE),
+            background: Some(Vec4::splat(2.0)),
+            ..Default::default()
+        };
+        assert!(add_artboard(&mut doc, &mut history, bad).is_err());
+        assert_eq!(list_artboards(&doc), vec![board.clone()]);
+        assert_eq!(list_artboard_presets().len(), ArtboardPreset::ALL.len());
+
+        let request = UpdateArtboardRequest {
+            id: board.id,
+            position: Some(Vec2::new(1000.0, 0.0)),
+            ..Default::default()
+        };
+        update_artboard(&mut doc, &mut history, request).unwrap();
+        assert_eq!(artboard_at(&doc, Vec2::new(10.0, 10.0)), Ok(None));
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(list_artboards(&doc), vec![board.clone()]);
+
+        remove_artboard(&mut doc, &mut history, board.id).unwrap();
+        assert_eq!(
+            artboard_contents(&doc, board.id),
+            Err(CommandError::ArtboardNotFound(board.id))
+        );
+        undo(&mut doc, &mut history).unwrap();
+        undo(&mut doc, &mut history).unwrap();
+        assert_eq!(doc.artboard_count(), 0);
+        assert!(artboard_at(&doc, Vec2::NAN).is_err());
+    }
+
+    #[test]
+    fn test_add_artboard_bad_background_keeps_id() {
+        let (mut doc, mut history, _) = setup();
+        let bad = AddArtboardRequest {
+            size: Some(Vec2::ONE),
+            background: Some(Vec4::splat(2.0)),
+            ..Default::default()
+        };
+        assert!(add_artboard(&mut doc, &mut history, bad).is_err());
+        assert_eq!(history.status().undo_steps, 1);
+
+        let good = AddArtboardRequest {
+            size: Some(Vec2::ONE),
+            ..Default::default()
+        };
+        assert_eq!(add_artboard(&mut doc, &mut history, good).unwrap().id, 1);
+    }
+}
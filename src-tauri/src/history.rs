@@ -0,0 +1,538 @@
+// MANDATE: Bounded undo/redo history with coalescing and transactions
+#![deny(warnings)]
+
+use crate::command::{check_step_len, Step};
+use crate::document::{Document, DocumentError};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Maximum undo steps kept.
+/// MANDATE: Bounded memory.
+pub const MAX_UNDO_STEPS: usize = 200;
+
+/// Maximum layer and shape snapshots kept across all steps.
+/// MANDATE: Bounded memory; the newest step is always kept.
+pub const MAX_HISTORY_COST: usize = 1_000_000;
+
+/// Undo/redo availability reported to the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HistoryStatus {
+    pub undo_steps: usize,
+    pub redo_steps: usize,
+}
+
+/// Undo and redo stacks of applied commands.
+/// MANDATE: Bounded collections.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    pending: Option<Vec<Step>>,
+    sealed: bool,
+    cost: usize,
+}
+
+impl History {
+    /// Create empty history.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record already-applied step; coalesce merges it into the last step.
+    /// MANDATE: ≤60 SLOC, clears the redo stack.
+    pub fn record(&mut self, new: impl Into<Step>, coalesce: bool) {
+        let mut step = new.into();
+        if step.is_noop() {
+            return;
+        }
+
+        if let Some(pending) = self.pending.as_mut() {
+            if let Some(last) = pending.last_mut().filter(|_| coalesce) {
+                match last.merge(step) {
+                    Ok(()) => return,
+                    Err(unmerged) => step = unmerged,
+                }
+            }
+            pending.push(step);
+            return;
+        }
+
+        self.redo.clear();
+        let open = coalesce && !self.sealed;
+        if let Some(last) = self.undo.back_mut().filter(|_| open) {
+            let cost = last.cost();
+            match last.merge(step) {
+                Ok(()) => {
+                    self.cost = self.cost - cost + last.cost();
+                    self.trim();
+                    return;
+                }
+                Err(unmerged) => step = unmerged,
+            }
+        }
+        self.cost += step.cost();
+        self.undo.push_back(step);
+        self.sealed = false;
+        self.trim();
+    }
+
+    /// Apply step and record it.
+    /// MANDATE: ≤60 SLOC, nothing recorded on failure.
+    pub fn execute(
+        &mut self,
+        doc: &mut Document,
+        new: impl Into<Step>,
+        coalesce: bool,
+    ) -> Result<(), DocumentError> {
+        let step = new.into();
+        if let Err(err) = step.apply(doc) {
+            self.discard_if_corrupt(&err);
+            return Err(err);
+        }
+        self.record(step, coalesce);
+        Ok(())
+    }
+
+    /// Undo last step; returns false if there is nothing to undo.
+    /// MANDATE: ≤60 SLOC, step kept if it fails to apply cleanly.
+    pub fn undo(&mut self, doc: &mut Document) -> Result<bool, DocumentError> {
+        self.ensure_idle()?;
+        let Some(step) = self.undo.pop_back() else {
+            return Ok(false);
+        };
+
+        if let Err(err) = step.inverse().apply(doc) {
+            self.undo.push_back(step);
+            self.discard_if_corrupt(&err);
+            return Err(err);
+        }
+
+        self.cost -= step.cost();
+        self.redo.push(step);
+        self.sealed = true;
+
+        // MANDATE: Assertions
+        assert!(self.redo.len() <= MAX_UNDO_STEPS);
+        Ok(true)
+    }
+
+    /// Redo last undone step; returns false if there is nothing to redo.
+    /// MANDATE: ≤60 SLOC, step kept if it fails to apply cleanly.
+    pub fn redo(&mut self, doc: &mut Document) -> Result<bool, DocumentError> {
+        self.ensure_idle()?;
+        let Some(step) = self.redo.pop() else {
+            return Ok(false);
+        };
+
+        if let Err(err) = step.apply(doc) {
+            self.redo.push(step);
+            self.discard_if_corrupt(&err);
+            return Err(err);
+        }
+
+        self.cost += step.cost();
+        self.undo.push_back(step);
+        self.sealed = true;
+
+        // MANDATE: Assertions
+        assert!(self.undo.len() <= MAX_UNDO_STEPS);
+        Ok(true)
+    }
+
+    /// Stop coalescing into the last step, e.g. at the end of a drag.
+    /// MANDATE: ≤60 SLOC.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Open transaction; commands recorded until commit undo as one step.
+    /// MANDATE: ≤60 SLOC, no nesting.
+    pub fn begin(&mut self) -> Result<(), DocumentError> {
+        self.ensure_idle()?;
+        self.pending = Some(Vec::new());
+        Ok(())
+    }
+
+    /// Close transaction and record its commands as one step.
+    /// MANDATE: ≤60 SLOC, left open on a capacity error so the caller can roll back.
+    pub fn commit(&mut self) -> Result<(), DocumentError> {
+        let steps = self.pending.as_ref().ok_or_else(no_transaction)?;
+        // Too large to record: the transaction stays open for rollback
+        check_step_len(steps.iter().map(|step| step.commands().len()).sum())?;
+
+        let mut combined = Step::new();
+        for step in self.pending.take().unwrap_or_default() {
+            combined.extend(step)?;
+        }
+        self.record(combined, false);
+        self.sealed = true;
+        Ok(())
+    }
+
+    /// Close transaction and undo its commands; a failure to undo clears the
+    /// history and returns `RollbackFailed`.
+    /// MANDATE: ≤60 SLOC.
+    pub fn rollback(&mut self, doc: &mut Document) -> Result<(), DocumentError> {
+        let steps = self.pending.take().ok_or_else(no_transaction)?;
+        for step in steps.iter().rev() {
+            if let Err(err) = step.inverse().apply(doc) {
+                // Any failure here leaves the transaction partly undone
+                let err = match err {
+                    DocumentError::RollbackFailed(_) => err,
+                    other => DocumentError::RollbackFailed(other.to_string()),
+                };
+                self.clear();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run f inside a transaction, rolling back if it fails.
+    /// MANDATE: ≤60 SLOC.
+    pub fn transaction<T, E, F>(&mut self, doc: &mut Document, f: F) -> Result<T, E>
+    where
+        E: From<DocumentError>,
+        F: FnOnce(&mut Document, &mut History) -> Result<T, E>,
+    {
+        self.begin()?;
+
+        match f(doc, self) {
+            Ok(value) => match self.commit() {
+                Ok(()) => Ok(value),
+                Err(err) => {
+                    self.rollback(doc)?;
+                    Err(err.into())
+                }
+            },
+            Err(err) => {
+                self.rollback(doc)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Drop all steps, e.g. after opening another document.
+    /// MANDATE: ≤60 SLOC.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Current undo/redo availability.
+    /// MANDATE: ≤60 SLOC.
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            undo_steps: self.undo.len(),
+            redo_steps: self.redo.len(),
+        }
+    }
+
+    /// Drop every step after a failed rollback, since the stacks no longer
+    /// describe the partly changed document.
+    /// MANDATE: ≤60 SLOC.
+    fn discard_if_corrupt(&mut self, err: &DocumentError) {
+        if matches!(err, DocumentError::RollbackFailed(_)) {
+            self.clear();
+        }
+    }
+
+    /// Reject undo/redo and nested transactions while one is open.
+    /// MANDATE: ≤60 SLOC.
+    fn ensure_idle(&self) -> Result<(), DocumentError> {
+        match self.pending {
+            Some(_) => Err(DocumentError::InvalidValue("transaction open".into())),
+            None => Ok(()),
+        }
+    }
+
+    /// Evict oldest steps beyond the step and snapshot bounds.
+    /// MANDATE: ≤60 SLOC, loop bounded by stack length.
+    fn trim(&mut self) {
+        while self.undo.len() > 1
+            && (self.undo.len() > MAX_UNDO_STEPS || self.cost > MAX_HISTORY_COST)
+        {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.cost -= oldest.cost();
+            }
+        }
+
+        // MANDATE: Assertions
+        assert!(self.undo.len() <= MAX_UNDO_STEPS);
+        assert!(self.undo.len() <= 1 || self.cost <= MAX_HISTORY_COST);
+    }
+}
+
+/// Build missing-transaction error.
+/// MANDATE: ≤60 SLOC.
+fn no_transaction() -> DocumentError {
+    DocumentError::InvalidValue("no transaction open".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch_ops::{TransformBatch, TransformUpdate};
+    use crate::command::Command;
+    use crate::file_format::to_json;
+    use crate::handlers::{self, AddShapeRequest, UpdateShapeRequest};
+    use crate::shape::Shape;
+    use glam::{Mat3, Vec2, Vec4};
+    use proptest::prelude::*;
+
+    /// Random edit, targets picked modulo the current count.
+    #[derive(Debug, Clone)]
+    enum Edit {
+        AddLayer,
+        AddShape(usize, f32, f32),
+        Move(usize, f32, f32),
+        Recolor(usize, f32),
+        Opacity(usize, f32),
+        Offset(usize, f32),
+        RemoveShape(usize),
+        RemoveLayer(usize),
+    }
+
+    fn edit_strategy() -> impl Strategy<Value = Edit> {
+        let coord = -500.0f32..500.0;
+        prop_oneof![
+            Just(Edit::AddLayer),
+            (any::<usize>(), coord.clone(), coord.clone())
+                .prop_map(|(i, x, y)| Edit::AddShape(i, x, y)),
+            (any::<usize>(), coord.clone(), coord.clone())
+                .prop_map(|(i, x, y)| Edit::Move(i, x, y)),
+            (any::<usize>(), 0.0f32..=1.0).prop_map(|(i, c)| Edit::Recolor(i, c)),
+            (any::<usize>(), 0.0f32..=1.0).prop_map(|(i, o)| Edit::Opacity(i, o)),
+            (any::<usize>(), coord).prop_map(|(i, x)| Edit::Offset(i, x)),
+            any::<usize>().prop_map(Edit::RemoveShape),
+            any::<usize>().prop_map(Edit::RemoveLayer),
+        ]
+    }
+
+    fn perform(doc: &mut Document, history: &mut History, edit: &Edit) {
+        let layers: Vec<u64> = doc
+            .layers()
+            .get_sorted_layers()
+            .iter()
+            .map(|l| l.id)
+            .collect();
+        let shapes: Vec<u64> = doc.all_shapes().iter().map(|s| s.id).collect();
+        let layer = |i: usize| layers.get(i % layers.len().max(1)).copied();
+        let shape = |i: usize| shapes.get(i % shapes.len().max(1)).copied();
+
+        let result = match *edit {
+            Edit::AddLayer => handlers::add_layer(doc, history, "Layer".into()).map(|_| ()),
+            Edit::AddShape(i, x, y) => match layer(i) {
+                Some(layer_id) => {
+                    let request = AddShapeRequest {
+                        layer_id,
+                        position: Vec2::new(x, y),
+                        size: Vec2::new(10.0, 20.0),
+                        color: None,
+                    };
+                    handlers::add_shape(doc, history, request).map(|_| ())
+                }
+                None => Ok(()),
+            },
+            Edit::Move(i, x, y) => match shape(i) {
+                Some(id) => {
+                    let request = UpdateShapeRequest {
+                        id,
+                        position: Some(Vec2::new(x, y)),
+                        ..Default::default()
+                    };
+                    handlers::update_shape(doc, history, request).map(|_| ())
+                }
+                None => Ok(()),
+            },
+            Edit::Recolor(i, c) => match shape(i) {
+                Some(id) => {
+                    let request = UpdateShapeRequest {
+                        id,
+                        color: Some(Vec4::new(c, 0.5, 1.0 - c, 1.0)),
+                        ..Default::default()
+                    };
+                    handlers::update_shape(doc, history, request).map(|_| ())
+                }
+                None => Ok(()),
+            },
+            Edit::Opacity(i, o) => match layer(i) {
+                Some(id) => handlers::set_opacity(doc, history, id, o).map(|_| ()),
+                None => Ok(()),
+            },
+            Edit::Offset(i, x) => match layer(i) {
+                Some(id) => {
+                    let mut batch = TransformBatch::new();
+                    let transform = Mat3::from_translation(Vec2::new(x, 0.0));
+                    batch.add(TransformUpdate::new(id, transform)).unwrap();
+                    handlers::update_transforms(doc, history, &batch, false).map(|_| ())
+                }
+                None => Ok(()),
+            },
+            Edit::RemoveShape(i) => match shape(i) {
+                Some(id) => handlers::remove_shape(doc, history, id).map(|_| ()),
+                None => Ok(()),
+            },
+            Edit::RemoveLayer(i) => match layer(i) {
+                Some(id) => handlers::remove_layer(doc, history, id).map(|_| ()),
+                None => Ok(()),
+            },
+        };
+        result.unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn test_do_undo_is_byte_identical(edits in prop::collection::vec(edit_strategy(), 1..40)) {
+            let mut doc = Document::new();
+            let mut history = History::new();
+            let empty = to_json(&doc).unwrap();
+
+            for edit in &edits {
+                let before = to_json(&doc).unwrap();
+                let steps = history.status().undo_steps;
+                perform(&mut doc, &mut history, edit);
+                let after = to_json(&doc).unwrap();
+
+                if history.status().undo_steps > steps {
+                    prop_assert!(history.undo(&mut doc).unwrap());
+                    prop_assert_eq!(&to_json(&doc).unwrap(), &before);
+                    prop_assert!(history.redo(&mut doc).unwrap());
+                }
+                prop_assert_eq!(&to_json(&doc).unwrap(), &after);
+            }
+
+            while history.undo(&mut doc).unwrap() {}
+            prop_assert_eq!(to_json(&doc).unwrap(), empty);
+        }
+    }
+
+    fn setup() -> (Document, History, Shape) {
+        let mut doc = Document::new();
+        let mut history = History::new();
+        let layer = handlers::add_layer(&mut doc, &mut history, "Layer 1".into()).unwrap();
+        let request = AddShapeRequest {
+            layer_id: layer.id,
+            position: Vec2::ZERO,
+            size: Vec2::ONE,
+            color: None,
+        };
+        let shape = handlers::add_shape(&mut doc, &mut history, request).unwrap();
+        (doc, history, shape)
+    }
+
+    fn moved(shape: &Shape, x: f32) -> Command {
+        let mut after = shape.clone();
+        after.position = Vec2::new(x, 0.0);
+        Command::UpdateShape {
+            before: shape.clone(),
+            after,
+        }
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let (mut doc, mut history, shape) = setup();
+        let mut current = shape;
+
+        for i in 1..=MAX_UNDO_STEPS + 50 {
+            let command = moved(&current, i as f32);
+            history.execute(&mut doc, command, false).unwrap();
+            current = doc.shape(current.id).unwrap().clone();
+        }
+
+        assert_eq!(history.status().undo_steps, MAX_UNDO_STEPS);
+        while history.undo(&mut doc).unwrap() {}
+        assert_eq!(
+            doc.shape(current.id).unwrap().position,
+            Vec2::new(50.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_seal_stops_coalescing() {
+        let (mut doc, mut history, shape) = setup();
+        history
+            .execute(&mut doc, moved(&shape, 1.0), false)
+            .unwrap();
+        let first = doc.shape(shape.id).unwrap().clone();
+        history.execute(&mut doc, moved(&first, 2.0), true).unwrap();
+        assert_eq!(history.status().undo_steps, 3);
+
+        history.seal();
+        let second = doc.shape(shape.id).unwrap().clone();
+        history
+            .execute(&mut doc, moved(&second, 3.0), true)
+            .unwrap();
+        assert_eq!(history.status().undo_steps, 4);
+    }
+
+    #[test]
+    fn test_transaction_undoes_as_one_step() {
+        let (mut doc, mut history, shape) = setup();
+        let before = to_json(&doc).unwrap();
+
+        history
+            .transaction(&mut doc, |doc, history| {
+                handlers::remove_shape(doc, history, shape.id)?;
+                handlers::add_layer(doc, history, "Layer 2".into())
+            })
+            .unwrap();
+        assert_eq!(history.status().undo_steps, 3);
+
+        history.undo(&mut doc).unwrap();
+        assert_eq!(to_json(&doc).unwrap(), before);
+    }
+
+    #[test]
+    fn test_failed_transaction_rolls_back() {
+        let (mut doc, mut history, shape) = setup();
+        let before = to_json(&doc).unwrap();
+
+        let result = history.transaction(&mut doc, |doc, history| {
+            handlers::remove_shape(doc, history, shape.id)?;
+            handlers::remove_layer(doc, history, 99)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(to_json(&doc).unwrap(), before);
+        assert_eq!(history.status().undo_steps, 2);
+        assert!(history.undo(&mut doc).unwrap());
+    }
+
+    #[test]
+    fn test_failed_rollback_clears_history() {
+        let (mut doc, mut history, shape) = setup();
+        let mut orphan = shape.clone();
+        orphan.layer_id = 99;
+
+        // Undoing the update would move the shape onto a missing layer
+        let mut step = Step::from(Command::UpdateShape {
+            before: orphan,
+            after: shape.clone(),
+        });
+        step.extend(Step::from(Command::InsertShape(shape)))
+            .unwrap();
+
+        let result = history.execute(&mut doc, step, false);
+        assert!(matches!(result, Err(DocumentError::RollbackFailed(_))));
+        assert_eq!(
+            history.status(),
+            HistoryStatus {
+                undo_steps: 0,
+                redo_steps: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let (mut doc, mut history, shape) = setup();
+        history.undo(&mut doc).unwrap();
+        assert_eq!(history.status().redo_steps, 1);
+
+        handlers::add_layer(&mut doc, &mut history, "Layer 2".into()).unwrap();
+        assert_eq!(history.status().redo_steps, 0);
+        assert!(doc.shape(shape.id).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+// MANDATE: Import of external files into the document
+#![deny(warnings)]
+
+pub mod svg;
+pub mod svg_values;
+
+use crate::document::DocumentError;
+use crate::layer::LayerId;
+use serde::Serialize;
+use std::path::Path;
+
+/// Largest file accepted for import.
+/// MANDATE: Bounded read.
+pub const MAX_IMPORT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Import errors; anything the importer can skip is an `ImportWarning` instead.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ImportError {
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("File too large: {0} bytes")]
+    TooLarge(u64),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Document error: {0}")]
+    Document(#[from] DocumentError),
+}
+
+/// Content left out or approximated during import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportWarning {
+    /// Element the warning is about, e.g. `path`.
+    pub element: String,
+    pub message: String,
+}
+
+/// What an import added to the document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// Created layers, each parent before its children.
+    pub layers: Vec<LayerId>,
+    pub shapes: usize,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl ImportReport {
+    /// Record a warning.
+    /// MANDATE: ≤60 SLOC.
+    pub fn warn(&mut self, element: &str, message: impl Into<String>) {
+        self.warnings.push(ImportWarning {
+            element: element.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Read a UTF-8 text file of at most `MAX_IMPORT_BYTES`.
+/// MANDATE: ≤60 SLOC, bounded read.
+pub fn read_text(path: &Path) -> Result<String, ImportError> {
+    let io_error = |err: std::io::Error| ImportError::Io(err.to_string());
+    let size = std::fs::metadata(path).map_err(io_error)?.len();
+    // MANDATE: Input validation
+    if size > MAX_IMPORT_BYTES {
+        return Err(ImportError::TooLarge(size));
+    }
+    std::fs::read_to_string(path).map_err(io_error)
+}
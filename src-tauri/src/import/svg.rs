@@ -0,0 +1,631 @@
+// MANDATE: SVG import into layers and shapes
+#![deny(warnings)]
+
+use super::svg_values::{parse_color, parse_length, parse_opacity, parse_style, parse_transform};
+use super::{ImportError, ImportReport};
+use crate::document::{validate_affine, validate_name, validate_placement, Document};
+use crate::layer::LayerId;
+use crate::layer_tree::MAX_DEPTH;
+use crate::shape::{transform_parts, CENTER_PIVOT};
+use glam::{Mat3, Vec2, Vec3, Vec4};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+
+/// Deepest element nesting accepted, including skipped content.
+/// MANDATE: Bounded parser stack.
+const MAX_XML_DEPTH: usize = 256;
+
+/// Maps SVG's y-down space to the document's y-up world space. The inverse
+/// of the root group SVG export writes, so exported files import in place.
+/// MANDATE: Fixed transform.
+const FLIP_Y: Mat3 = Mat3::from_cols_array(&[1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0]);
+
+/// Inherited fill state.
+/// MANDATE: `fill` is linear RGB, `None` for `fill="none"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Paint {
+    fill: Option<Vec3>,
+    fill_opacity: f32,
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Self {
+            fill: Some(Vec3::ZERO),
+            fill_opacity: 1.0,
+        }
+    }
+}
+
+/// Supported element, with `parent` indexing the enclosing `<g>`.
+#[derive(Debug, Clone, PartialEq)]
+enum Element {
+    Group {
+        parent: Option<usize>,
+        name: Option<String>,
+        transform: Mat3,
+        opacity: f32,
+        visible: bool,
+    },
+    Rect {
+        parent: Option<usize>,
+        /// Maps the unit quad to the parent's space.
+        transform: Mat3,
+        color: Vec4,
+    },
+}
+
+/// Element the parser is inside.
+#[derive(Debug, Clone, Copy)]
+enum Open {
+    Root(Paint),
+    Group(usize, Paint),
+    /// Unsupported or childless element; its content is ignored.
+    Skip,
+}
+
+/// Streaming parser collecting supported elements in document order.
+#[derive(Debug, Default)]
+struct Parser {
+    elements: Vec<Element>,
+    stack: Vec<Open>,
+    report: ImportReport,
+    /// Whether the root `<svg>` has been opened.
+    root: bool,
+}
+
+/// Import SVG rects and groups as new top-level layers above the existing
+/// ones, keeping document order as stacking order. Each `<g>` becomes a
+/// layer (a group layer when it contains other groups); rects outside a
+/// leaf group go into "Shapes" layers. Unsupported content is reported as
+/// warnings. On error the document is left unchanged.
+/// MANDATE: ≤60 SLOC, all-or-nothing.
+pub fn import_svg(doc: &mut Document, source: &str) -> Result<ImportReport, ImportError> {
+    let mut parser = Parser::default();
+    parser.parse(source)?;
+    let Parser {
+        elements,
+        mut report,
+        ..
+    } = parser;
+
+    if let Err(err) = build(doc, &elements, &mut report) {
+        for id in report.layers.iter().rev() {
+            if doc.layers().get_layer(*id).is_some() {
+                doc.remove_layer(*id)?;
+            }
+        }
+        return Err(err);
+    }
+
+    // MANDATE: Assertions
+    assert!(report
+        .layers
+        .iter()
+        .all(|id| doc.layers().get_layer(*id).is_some()));
+    Ok(report)
+}
+
+impl Parser {
+    /// Read every event of source.
+    /// MANDATE: ≤60 SLOC, loop bounded by the source length.
+    fn parse(&mut self, source: &str) -> Result<(), ImportError> {
+        let mut reader = Reader::from_str(source);
+        for _ in 0..=source.len() {
+            match reader.read_event().map_err(parse_error)? {
+                Event::Start(tag) => {
+                    let open = self.open(&tag)?;
+                    self.stack.push(open);
+                }
+                Event::Empty(tag) => {
+                    self.open(&tag)?;
+                }
+                Event::End(_) => {
+                    self.stack.pop();
+                }
+                Event::Eof if !self.stack.is_empty() => {
+                    return Err(ImportError::Parse("unexpected end of file".into()));
+                }
+                Event::Eof if !self.root => {
+                    return Err(ImportError::Parse("no <svg> element".into()));
+                }
+                Event::Eof => return Ok(()),
+                _ => {}
+            }
+            // MANDATE: Input validation
+            if self.stack.len() > MAX_XML_DEPTH {
+                return Err(ImportError::Parse("elements nested too deeply".into()));
+            }
+        }
+        Err(ImportError::Parse("unterminated document".into()))
+    }
+
+    /// Handle an opening tag, returning how to treat its content.
+    /// MANDATE: ≤60 SLOC.
+    fn open(&mut self, tag: &BytesStart) -> Result<Open, ImportError> {
+        let name = String::from_utf8_lossy(tag.local_name().as_ref()).into_owned();
+        let attrs = attributes(tag)?;
+        let (parent, paint) = match self.stack.last() {
+            None if name == "svg" && !self.root => {
+                self.root = true;
+                return Ok(Open::Root(self.inherit(Paint::default(), &attrs, &name)));
+            }
+            None => {
+                return Err(ImportError::Parse(format!(
+                    "<{}> outside a single root <svg>",
+                    name
+                )))
+            }
+            Some(Open::Skip) => return Ok(Open::Skip),
+            Some(Open::Root(paint)) => (None, *paint),
+            Some(Open::Group(index, paint)) => (Some(*index), *paint),
+        };
+
+        let paint = self.inherit(paint, &attrs, &name);
+        match name.as_str() {
+            "g" => Ok(self.group(parent, paint, &attrs)),
+            "rect" => {
+                self.rect(parent, paint, &attrs);
+                Ok(Open::Skip)
+            }
+            "title" | "desc" | "metadata" | "defs" => Ok(Open::Skip),
+            _ => {
+                self.report.warn(&name, "unsupported element skipped");
+                Ok(Open::Skip)
+            }
+        }
+    }
+
+    /// Record a group, or skip it when it nests too deeply or cannot be placed.
+    /// MANDATE: ≤60 SLOC.
+    fn group(&mut self, parent: Option<usize>, paint: Paint, attrs: &[(String, String)]) -> Open {
+        let depth = self
+            .stack
+            .iter()
+            .filter(|o| matches!(o, Open::Group(..)))
+            .count();
+        if depth + 1 >= MAX_DEPTH {
+            self.report.warn(
+                "g",
+                format!("nested deeper than {} levels; skipped", MAX_DEPTH),
+            );
+            return Open::Skip;
+        }
+        let Some(transform) = self.transform(attrs, "g") else {
+            return Open::Skip;
+        };
+        // Top-level groups are flipped into y-up world space when placed
+        let placed = parent.map_or(FLIP_Y * transform, |_| transform);
+        if let Err(err) = validate_affine(placed) {
+            self.report.warn("g", format!("{}; skipped", err));
+            return Open::Skip;
+        }
+
+        let name = attr(attrs, "id")
+            .filter(|id| validate_name(id, "layer").is_ok())
+            .map(str::to_string);
+        let opacity = self.opacity(attrs, "g");
+        self.elements.push(Element::Group {
+            parent,
+            name,
+            transform,
+            opacity,
+            visible: is_displayed(attrs),
+        });
+        Open::Group(self.elements.len() - 1, paint)
+    }
+
+    /// Record a filled rect; unrenderable or unplaceable rects are warnings.
+    /// MANDATE: ≤60 SLOC.
+    fn rect(&mut self, parent: Option<usize>, paint: Paint, attrs: &[(String, String)]) {
+        let length = |name: &str| attr(attrs, name).map_or(Ok(0.0), parse_length);
+        let (x, y, width, height) =
+            match (length("x"), length("y"), length("width"), length("height")) {
+                (Ok(x), Ok(y), Ok(w), Ok(h)) => (x, y, w, h),
+                (Err(msg), ..) | (_, Err(msg), ..) | (_, _, Err(msg), _) | (.., Err(msg)) => {
+                    return self.report.warn("rect", format!("{}; skipped", msg));
+                }
+            };
+        if width <= 0.0 || height <= 0.0 {
+            return self.report.warn("rect", "zero size; skipped");
+        }
+        if !is_displayed(attrs) {
+            return self.report.warn("rect", "hidden; skipped");
+        }
+        let Some(fill) = paint.fill else {
+            return self.report.warn("rect", "no fill; skipped");
+        };
+        if ["rx", "ry"]
+            .iter()
+            .any(|name| attr(attrs, name).is_some_and(|v| v.trim() != "0"))
+        {
+            self.report.warn("rect", "rounded corners imported square");
+        }
+        let Some(outer) = self.transform(attrs, "rect") else {
+            return;
+        };
+
+        let transform = outer
+            * Mat3::from_translation(Vec2::new(x, y))
+            * Mat3::from_scale(Vec2::new(width, height));
+        let placement = match transform_parts(transform, CENTER_PIVOT) {
+            Ok(placement) => placement,
+            Err(msg) => {
+                return self
+                    .report
+                    .warn("rect", format!("transform {}; skipped", msg))
+            }
+        };
+        if let Err(err) = validate_placement(&placement) {
+            return self.report.warn("rect", format!("{}; skipped", err));
+        }
+        let alpha = paint.fill_opacity * self.opacity(attrs, "rect");
+        self.elements.push(Element::Rect {
+            parent,
+            transform,
+            color: fill.extend(alpha),
+        });
+    }
+
+    /// Inherited paint with this element's fill and fill-opacity applied;
+    /// unsupported values keep the inherited ones.
+    /// MANDATE: ≤60 SLOC.
+    fn inherit(&mut self, mut paint: Paint, attrs: &[(String, String)], element: &str) -> Paint {
+        if let Some(fill) = attr(attrs, "fill") {
+            match parse_color(fill) {
+                Ok(color) => paint.fill = color,
+                Err(msg) => self
+                    .report
+                    .warn(element, format!("{}; fill inherited", msg)),
+            }
+        }
+        if let Some(opacity) = attr(attrs, "fill-opacity") {
+            match parse_opacity(opacity) {
+                Ok(value) => paint.fill_opacity = value,
+                Err(msg) => self.report.warn(element, msg),
+            }
+        }
+        paint
+    }
+
+    /// The `transform` attribute, or `None` after warning that it is unsupported.
+    /// MANDATE: ≤60 SLOC.
+    fn transform(&mut self, attrs: &[(String, String)], element: &str) -> Option<Mat3> {
+        match attr(attrs, "transform").map(parse_transform) {
+            None => Some(Mat3::IDENTITY),
+            Some(Ok(transform)) => Some(transform),
+            Some(Err(msg)) => {
+                self.report.warn(element, format!("{}; skipped", msg));
+                None
+            }
+        }
+    }
+
+    /// The `opacity` attribute, fully opaque when missing or invalid.
+    /// MANDATE: ≤60 SLOC.
+    fn opacity(&mut self, attrs: &[(String, String)], element: &str) -> f32 {
+        match attr(attrs, "opacity").map(parse_opacity) {
+            None => 1.0,
+            Some(Ok(opacity)) => opacity,
+            Some(Err(msg)) => {
+                self.report.warn(element, msg);
+                1.0
+            }
+        }
+    }
+}
+
+/// Create layers and shapes for parsed elements, in order.
+/// MANDATE: ≤60 SLOC, bounded by the element count.
+fn build(
+    doc: &mut Document,
+    elements: &[Element],
+    report: &mut ImportReport,
+) -> Result<(), ImportError> {
+    let mut has_groups = vec![false; elements.len()];
+    for element in elements {
+        if let Element::Group {
+            parent: Some(parent),
+            ..
+        } = element
+        {
+            has_groups[*parent] = true;
+        }
+    }
+    let top = doc.layers().children(None).iter().map(|l| l.z_order).max();
+    let mut builder = Builder {
+        doc,
+        report,
+        z_order: top.map_or(0, |z| z + 1),
+        layers: vec![None; elements.len()],
+        runs: HashMap::new(),
+    };
+
+    for (index, element) in elements.iter().enumerate() {
+        match element {
+            Element::Group {
+                parent,
+                name,
+                transform,
+                opacity,
+                visible,
+            } => {
+                // A child group ends the run of shapes before it
+                builder.runs.remove(parent);
+                let parent = parent.and_then(|index| builder.layers[index]);
+                let id = builder.group(
+                    parent,
+                    name.as_deref(),
+                    *transform,
+                    *opacity,
+                    *visible,
+                    has_groups[index],
+                )?;
+                builder.layers[index] = Some(id);
+                if !has_groups[index] {
+                    builder.runs.insert(Some(index), id);
+                }
+            }
+            Element::Rect {
+                parent,
+                transform,
+                color,
+            } => {
+                let layer = builder.run_layer(*parent)?;
+                let id = builder.doc.add_shape(layer, Vec2::ZERO, Vec2::ONE)?;
+                builder.doc.set_shape_transform(id, *transform)?;
+                builder.doc.set_shape_color(id, *color)?;
+                builder.report.shapes += 1;
+            }
+        }
+    }
+
+    // MANDATE: Assertions
+    assert!(builder.report.shapes <= elements.len());
+    Ok(())
+}
+
+/// Document-building state.
+struct Builder<'a> {
+    doc: &'a mut Document,
+    report: &'a mut ImportReport,
+    /// Z-order for new top-level layers, above the existing ones.
+    z_order: i32,
+    /// Layer created for each group element.
+    layers: Vec<Option<LayerId>>,
+    /// Layer receiving rects for each container (`None` = the root).
+    runs: HashMap<Option<usize>, LayerId>,
+}
+
+impl Builder<'_> {
+    /// Create the layer for a group element under the parent group's layer.
+    /// MANDATE: ≤60 SLOC.
+    fn group(
+        &mut self,
+        parent: Option<LayerId>,
+        name: Option<&str>,
+        transform: Mat3,
+        opacity: f32,
+        visible: bool,
+        is_group: bool,
+    ) -> Result<LayerId, ImportError> {
+        let fallback = if is_group { "Group" } else { "Layer" };
+        let name = name.unwrap_or(fallback).to_string();
+        let id = self.layer(name, is_group, parent, transform)?;
+
+        if opacity < 1.0 {
+            self.doc.set_layer_opacity(id, opacity)?;
+        }
+        if !visible {
+            self.doc.set_layer_visible(id, false)?;
+        }
+        Ok(id)
+    }
+
+    /// Layer receiving rects inside container, opening a new one if needed.
+    /// MANDATE: ≤60 SLOC.
+    fn run_layer(&mut self, container: Option<usize>) -> Result<LayerId, ImportError> {
+        if let Some(id) = self.runs.get(&container) {
+            return Ok(*id);
+        }
+        let parent = container.and_then(|index| self.layers[index]);
+        let id = self.layer("Shapes".into(), false, parent, Mat3::IDENTITY)?;
+        self.runs.insert(container, id);
+        Ok(id)
+    }
+
+    /// Add a layer under parent, flipping top-level layers into world space.
+    /// MANDATE: ≤60 SLOC.
+    fn layer(
+        &mut self,
+        name: String,
+        is_group: bool,
+        parent: Option<LayerId>,
+        transform: Mat3,
+    ) -> Result<LayerId, ImportError> {
+        let id = if is_group {
+            self.doc.add_group(name)?
+        } else {
+            self.doc.add_layer(name)?
+        };
+        self.report.layers.push(id);
+
+        let transform = match parent {
+            Some(_) => {
+                self.doc.set_layer_parent(id, parent)?;
+                transform
+            }
+            None => {
+                self.doc.set_layer_z_order(id, self.z_order)?;
+                FLIP_Y * transform
+            }
+        };
+        if transform != Mat3::IDENTITY {
+            self.doc.set_layer_transform(id, transform)?;
+        }
+        Ok(id)
+    }
+}
+
+/// Attributes with `style` declarations appended, so later lookups see them last.
+/// MANDATE: ≤60 SLOC.
+fn attributes(tag: &BytesStart) -> Result<Vec<(String, String)>, ImportError> {
+    let mut attrs = Vec::new();
+    for attribute in tag.attributes() {
+        let attribute = attribute.map_err(|e| ImportError::Parse(e.to_string()))?;
+        let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+        let value = attribute
+            .unescape_value()
+            .map_err(parse_error)?
+            .into_owned();
+        attrs.push((key, value));
+    }
+    if let Some(style) = attr(&attrs, "style").map(parse_style) {
+        attrs.extend(style);
+    }
+    Ok(attrs)
+}
+
+/// Last value of an attribute or style property.
+/// MANDATE: ≤60 SLOC.
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .rev()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Whether neither `display` nor `visibility` hides the element.
+/// MANDATE: ≤60 SLOC.
+fn is_displayed(attrs: &[(String, String)]) -> bool {
+    attr(attrs, "display").map(str::trim) != Some("none")
+        && attr(attrs, "visibility").map(str::trim) != Some("hidden")
+}
+
+/// Convert XML error.
+/// MANDATE: ≤60 SLOC.
+fn parse_error(err: quick_xml::Error) -> ImportError {
+    ImportError::Parse(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::svg::{to_svg, SvgOptions};
+    use crate::selection::Selection;
+
+    const ICON: &str = include_str!("../../tests/fixtures/svg/icon.svg");
+    const NESTED: &str = include_str!("../../tests/fixtures/svg/nested.svg");
+    const DEGENERATE: &str = include_str!("../../tests/fixtures/svg/degenerate.svg");
+
+    fn names(doc: &Document) -> Vec<String> {
+        let tree = doc.layers();
+        tree.render_order()
+            .into_iter()
+            .filter_map(|id| tree.get_layer(id).map(|l| l.name.clone()))
+            .collect()
+    }
+
+    fn bounds(doc: &Document, layer: LayerId) -> Vec<(Vec2, Vec2)> {
+        doc.shapes_in_layer(layer)
+            .iter()
+            .filter_map(|shape| doc.world_bounds(shape.id))
+            .collect()
+    }
+
+    #[test]
+    fn test_import_icon_fixture() {
+        let mut doc = Document::new();
+        let existing = doc.add_layer("Existing".into()).unwrap();
+        doc.set_layer_z_order(existing, 3).unwrap();
+
+        let report = import_svg(&mut doc, ICON).unwrap();
+        assert_eq!(names(&doc), ["Existing", "Shapes", "badge", "hidden"]);
+        assert_eq!((report.layers.len(), report.shapes), (3, 4));
+        let elements: Vec<&str> = report.warnings.iter().map(|w| w.element.as_str()).collect();
+        assert_eq!(elements, ["rect", "rect", "path", "circle", "circle"]);
+
+        // y-down SVG lands flipped into y-up world space
+        let [shapes, badge, hidden] = report.layers[..] else {
+            panic!("expected three layers");
+        };
+        assert_eq!(
+            bounds(&doc, shapes),
+            [(Vec2::new(2.0, -22.0), Vec2::new(22.0, -2.0))]
+        );
+        assert_eq!(
+            bounds(&doc, badge)[0],
+            (Vec2::new(8.0, -16.0), Vec2::new(16.0, -8.0))
+        );
+
+        let tree = doc.layers();
+        assert_eq!(tree.get_layer(badge).unwrap().opacity, 0.5);
+        assert!(!tree.get_layer(hidden).unwrap().visible);
+        let red = doc.shapes_in_layer(badge)[0].color;
+        assert_eq!(red, Vec4::new(1.0, 0.0, 0.0, 0.5));
+        let inherited = doc.shapes_in_layer(badge)[1];
+        assert!((inherited.rotation - std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+        assert!((inherited.color.x - 0.0331).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_import_nested_fixture() {
+        let mut doc = Document::new();
+        let report = import_svg(&mut doc, NESTED).unwrap();
+        assert_eq!(names(&doc), ["outer", "Shapes", "inner", "Shapes"]);
+        assert_eq!((report.shapes, report.warnings.len()), (3, 1));
+        assert_eq!(report.warnings[0].element, "rect");
+
+        let inner = report.layers[2];
+        assert!(doc.layers().get_layer(report.layers[0]).unwrap().is_group);
+        assert_eq!(
+            bounds(&doc, inner),
+            [(Vec2::new(10.0, -10.0), Vec2::new(20.0, 0.0))]
+        );
+        assert_eq!(bounds(&doc, report.layers[3])[0].0, Vec2::new(40.0, -20.0));
+    }
+
+    #[test]
+    fn test_import_degenerate_fixture() {
+        let mut doc = Document::new();
+        let report = import_svg(&mut doc, DEGENERATE).unwrap();
+        assert_eq!(names(&doc), ["Shapes"]);
+        assert_eq!(report.shapes, 1);
+        let elements: Vec<&str> = report.warnings.iter().map(|w| w.element.as_str()).collect();
+        assert_eq!(elements, ["g", "g", "rect"]);
+    }
+
+    #[test]
+    fn test_import_errors_leave_document() {
+        let mut doc = Document::new();
+        doc.add_layer("Existing".into()).unwrap();
+        assert!(import_svg(&mut doc, "<html/>").is_err());
+        assert!(import_svg(&mut doc, "<svg><g><rect width=\"1\" height=\"1\"/></svg>").is_err());
+        assert!(import_svg(&mut doc, "").is_err());
+        assert_eq!((doc.layers().len(), doc.shape_count()), (1, 0));
+    }
+
+    #[test]
+    fn test_import_export_round_trip() {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".into()).unwrap();
+        let a = doc
+            .add_shape(layer, Vec2::new(3.0, 4.0), Vec2::new(5.0, 2.0))
+            .unwrap();
+        doc.set_shape_rotation(a, 0.5).unwrap();
+        doc.set_shape_color(a, Vec4::new(1.0, 0.0, 0.0, 0.5))
+            .unwrap();
+        let svg = to_svg(&doc, &Selection::new(), &SvgOptions::default()).unwrap();
+
+        let mut imported = Document::new();
+        let report = import_svg(&mut imported, &svg).unwrap();
+        assert!(report.warnings.is_empty());
+        let shape = imported.all_shapes()[0];
+        let (min, max) = doc.world_bounds(a).unwrap();
+        let (new_min, new_max) = imported.world_bounds(shape.id).unwrap();
+        assert!(new_min.abs_diff_eq(min, 1e-3) && new_max.abs_diff_eq(max, 1e-3));
+        assert_eq!(shape.color, Vec4::new(1.0, 0.0, 0.0, 0.5));
+    }
+}
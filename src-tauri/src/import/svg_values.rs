@@ -0,0 +1,232 @@
+// MANDATE: Parsing of SVG attribute values: lengths, colours, transforms
+#![deny(warnings)]
+
+use crate::export::raster::srgb_to_linear;
+use glam::{Mat2, Mat3, Vec2, Vec3};
+
+/// Named colours understood besides `#rgb`, `#rrggbb` and `rgb()`.
+/// MANDATE: Fixed table, lowercase names.
+const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("black", [0, 0, 0]),
+    ("white", [255, 255, 255]),
+    ("red", [255, 0, 0]),
+    ("lime", [0, 255, 0]),
+    ("green", [0, 128, 0]),
+    ("blue", [0, 0, 255]),
+    ("yellow", [255, 255, 0]),
+    ("cyan", [0, 255, 255]),
+    ("magenta", [255, 0, 255]),
+    ("gray", [128, 128, 128]),
+    ("grey", [128, 128, 128]),
+    ("silver", [192, 192, 192]),
+    ("maroon", [128, 0, 0]),
+    ("olive", [128, 128, 0]),
+    ("navy", [0, 0, 128]),
+    ("purple", [128, 0, 128]),
+    ("teal", [0, 128, 128]),
+    ("orange", [255, 165, 0]),
+];
+
+/// Parse a length in user units, with an optional `px` suffix.
+/// MANDATE: ≤60 SLOC, other units are an error.
+pub fn parse_length(text: &str) -> Result<f32, String> {
+    let trimmed = text.trim();
+    let number = trimmed.strip_suffix("px").unwrap_or(trimmed);
+    match number.trim().parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("unsupported length '{}'", text)),
+    }
+}
+
+/// Parse an opacity, as a number or percentage, clamped to 0..=1.
+/// MANDATE: ≤60 SLOC.
+pub fn parse_opacity(text: &str) -> Result<f32, String> {
+    let trimmed = text.trim();
+    let (number, divisor) = match trimmed.strip_suffix('%') {
+        Some(percent) => (percent, 100.0),
+        None => (trimmed, 1.0),
+    };
+    match number.trim().parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok((value / divisor).clamp(0.0, 1.0)),
+        _ => Err(format!("invalid opacity '{}'", text)),
+    }
+}
+
+/// Parse a paint as linear RGB; `None` for `none`.
+/// MANDATE: ≤60 SLOC, gradients and `currentColor` are an error.
+pub fn parse_color(text: &str) -> Result<Option<Vec3>, String> {
+    let lower = text.trim().to_ascii_lowercase();
+    let unsupported = || format!("unsupported colour '{}'", text.trim());
+    if lower == "none" {
+        return Ok(None);
+    }
+
+    let srgb: [u8; 3] = if let Some(hex) = lower.strip_prefix('#') {
+        parse_hex(hex).ok_or_else(unsupported)?
+    } else if let Some(args) = lower.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let channels = parse_numbers(args)?;
+        let [r, g, b] = channels[..] else {
+            return Err(unsupported());
+        };
+        [r, g, b].map(|c| c.round().clamp(0.0, 255.0) as u8)
+    } else {
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| *name == lower)
+            .map(|(_, rgb)| *rgb)
+            .ok_or_else(unsupported)?
+    };
+    Ok(Some(Vec3::from_array(srgb.map(srgb_to_linear))))
+}
+
+/// Parse `rgb` or `rrggbb` hex digits.
+/// MANDATE: ≤60 SLOC.
+fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+    match hex.len() {
+        3 => Some([digit(0)? * 17, digit(1)? * 17, digit(2)? * 17]),
+        6 => Some([
+            digit(0)? * 16 + digit(1)?,
+            digit(2)? * 16 + digit(3)?,
+            digit(4)? * 16 + digit(5)?,
+        ]),
+        _ => None,
+    }
+}
+
+/// Parse a transform list such as `translate(10 20) rotate(45)`, composed
+/// left to right as in SVG.
+/// MANDATE: ≤60 SLOC, loop bounded by the text length.
+pub fn parse_transform(text: &str) -> Result<Mat3, String> {
+    let malformed = || format!("malformed transform '{}'", text);
+    let mut transform = Mat3::IDENTITY;
+    let mut rest = text;
+
+    for _ in 0..=text.len() {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let (open, close) = match (rest.find('('), rest.find(')')) {
+            (Some(open), Some(close)) if open < close => (open, close),
+            _ => return Err(malformed()),
+        };
+        let args = parse_numbers(&rest[open + 1..close])?;
+        transform *= transform_function(rest[..open].trim(), &args)?;
+        rest = &rest[close + 1..];
+    }
+
+    // MANDATE: Input validation
+    if !rest.is_empty() || !transform.is_finite() {
+        return Err(malformed());
+    }
+    Ok(transform)
+}
+
+/// Matrix of one transform function.
+/// MANDATE: ≤60 SLOC, angles in degrees as in SVG.
+fn transform_function(name: &str, args: &[f32]) -> Result<Mat3, String> {
+    let transform = match (name, args) {
+        ("matrix", &[a, b, c, d, e, f]) => Mat3::from_cols(
+            Vec3::new(a, b, 0.0),
+            Vec3::new(c, d, 0.0),
+            Vec3::new(e, f, 1.0),
+        ),
+        ("translate", &[x]) => Mat3::from_translation(Vec2::new(x, 0.0)),
+        ("translate", &[x, y]) => Mat3::from_translation(Vec2::new(x, y)),
+        ("scale", &[s]) => Mat3::from_scale(Vec2::splat(s)),
+        ("scale", &[x, y]) => Mat3::from_scale(Vec2::new(x, y)),
+        ("rotate", &[angle]) => Mat3::from_angle(angle.to_radians()),
+        ("rotate", &[angle, x, y]) => {
+            let center = Vec2::new(x, y);
+            Mat3::from_translation(center)
+                * Mat3::from_angle(angle.to_radians())
+                * Mat3::from_translation(-center)
+        }
+        ("skewX", &[angle]) => Mat3::from_mat2(Mat2::from_cols(
+            Vec2::X,
+            Vec2::new(angle.to_radians().tan(), 1.0),
+        )),
+        ("skewY", &[angle]) => Mat3::from_mat2(Mat2::from_cols(
+            Vec2::new(1.0, angle.to_radians().tan()),
+            Vec2::Y,
+        )),
+        _ => {
+            return Err(format!(
+                "unsupported transform {}() with {} arguments",
+                name,
+                args.len()
+            ))
+        }
+    };
+    Ok(transform)
+}
+
+/// Parse numbers separated by whitespace or commas.
+/// MANDATE: ≤60 SLOC.
+pub fn parse_numbers(text: &str) -> Result<Vec<f32>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("invalid number '{}'", part)),
+        })
+        .collect()
+}
+
+/// `name: value` pairs of a `style` attribute, in order.
+/// MANDATE: ≤60 SLOC.
+pub fn parse_style(style: &str) -> Vec<(String, String)> {
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("none").unwrap(), None);
+        assert_eq!(parse_color("#F00").unwrap(), Some(Vec3::X));
+        assert_eq!(parse_color("rgb(0, 0, 255)").unwrap(), Some(Vec3::Z));
+        assert_eq!(parse_color(" Black ").unwrap(), Some(Vec3::ZERO));
+        // sRGB 0x80 is about 0.216 in linear light
+        let gray = parse_color("#808080").unwrap().unwrap();
+        assert!((gray.x - 0.2158).abs() < 1e-3);
+        assert!(parse_color("url(#gradient)").is_err());
+        assert!(parse_color("#12345").is_err());
+    }
+
+    #[test]
+    fn test_parse_transform() {
+        let t = parse_transform("translate(10, 20) scale(2)").unwrap();
+        assert_eq!(t.transform_point2(Vec2::ONE), Vec2::new(12.0, 22.0));
+        let r = parse_transform("rotate(90 1 1)").unwrap();
+        assert!(r
+            .transform_point2(Vec2::new(2.0, 1.0))
+            .abs_diff_eq(Vec2::new(1.0, 2.0), 1e-6));
+        let m = parse_transform("matrix(1 0 0 -1 0 5)").unwrap();
+        assert_eq!(m.transform_point2(Vec2::new(1.0, 1.0)), Vec2::new(1.0, 4.0));
+        assert!(parse_transform("translate(1").is_err());
+        assert!(parse_transform("perspective(1)").is_err());
+    }
+
+    #[test]
+    fn test_parse_length_and_opacity() {
+        assert_eq!(parse_length("12.5px").unwrap(), 12.5);
+        assert!(parse_length("3mm").is_err());
+        assert_eq!(parse_opacity("50%").unwrap(), 0.5);
+        assert_eq!(parse_opacity("2").unwrap(), 1.0);
+        assert_eq!(
+            parse_style("fill: red;opacity:.5;"),
+            vec![
+                ("fill".to_string(), "red".to_string()),
+                ("opacity".to_string(), ".5".to_string())
+            ]
+        );
+    }
+}
@@ -10,7 +10,7 @@ pub type LayerId = u64;
 
 /// Layer metadata.
 /// MANDATE: Bounded data structure.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Layer {
     pub id: LayerId,
     pub name: String,
@@ -18,6 +18,15 @@ pub struct Layer {
     pub opacity: f32,
     pub z_order: i32,
     pub transform: Mat3,
+    /// Parent group; None for top-level layers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<LayerId>,
+    /// Group layers hold child layers instead of shapes.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_group: bool,
+    /// Locked layers render but cannot be picked or selected.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub locked: bool,
 }
 
 impl Layer {
@@ -35,6 +44,9 @@ impl Layer {
             opacity: 1.0,
             z_order: 0,
             transform: Mat3::IDENTITY,
+            parent: None,
+            is_group: false,
+            locked: false,
         }
     }
 
@@ -77,6 +89,12 @@ impl Layer {
     }
 }
 
+/// Serde helper omitting false flags.
+/// MANDATE: ≤60 SLOC.
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 impl Default for Layer {
     fn default() -> Self {
         Self::new(0, "Layer".to_string())
@@ -2,14 +2,21 @@
 #![deny(warnings)]
 
 use crate::layer::{Layer, LayerId};
+use glam::Mat3;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 /// Maximum layers limit.
 /// MANDATE: Bounded allocation.
 const MAX_LAYERS: usize = 1000;
 
-/// Layer tree (flat for Phase 1).
-/// MANDATE: Bounded data structure.
+/// Maximum nesting depth, counting top-level layers as depth 1.
+/// MANDATE: Bounds every walk up the tree.
+pub const MAX_DEPTH: usize = 32;
+
+/// Layer tree: groups hold child layers through parent links.
+/// MANDATE: Bounded data structure, acyclic, walked iteratively.
+#[derive(Clone)]
 pub struct LayerTree {
     layers: HashMap<LayerId, Layer>,
     next_id: LayerId,
@@ -44,6 +51,28 @@ impl LayerTree {
         Ok(id)
     }
 
+    /// Insert existing layer keeping its ID.
+    /// MANDATE: ≤60 SLOC, bounded capacity, unique IDs.
+    pub fn insert_layer(&mut self, layer: Layer) -> Result<(), String> {
+        if self.layers.len() >= MAX_LAYERS {
+            return Err("Max layers reached".to_string());
+        }
+        if self.layers.contains_key(&layer.id) {
+            return Err(format!("Duplicate layer ID {}", layer.id));
+        }
+        if let Some(parent) = layer.parent {
+            self.check_parent(parent, 0)?;
+        }
+
+        // MANDATE: Assertions
+        assert!(!layer.name.is_empty());
+        assert!(layer.id < LayerId::MAX);
+
+        self.next_id = self.next_id.max(layer.id + 1);
+        self.layers.insert(layer.id, layer);
+        Ok(())
+    }
+
     /// Get layer by ID.
     /// MANDATE: ≤60 SLOC.
     pub fn get_layer(&self, id: LayerId) -> Option<&Layer> {
@@ -56,10 +85,22 @@ impl LayerTree {
         self.layers.get_mut(&id)
     }
 
-    /// Remove layer.
+    /// Remove layer and every descendant.
     /// MANDATE: ≤60 SLOC.
     pub fn remove_layer(&mut self, id: LayerId) -> bool {
-        self.layers.remove(&id).is_some()
+        if !self.layers.contains_key(&id) {
+            return false;
+        }
+
+        for descendant in self.descendants(id) {
+            self.layers.remove(&descendant);
+        }
+        self.layers.remove(&id);
+
+        // MANDATE: Assertions
+        assert!(!self.layers.contains_key(&id));
+        assert!(self.layers.values().all(|l| l.parent != Some(id)));
+        true
     }
 
     /// Get all layers sorted.
@@ -68,11 +109,7 @@ impl LayerTree {
         let mut layers: Vec<&Layer> = self.layers.values().collect();
 
         // MANDATE: Deterministic sorting by z-order then ID
-        layers.sort_by(|a, b| {
-            a.z_order
-                .cmp(&b.z_order)
-                .then_with(|| a.id.cmp(&b.id))
-        });
+        layers.sort_by(|a, b| a.z_order.cmp(&b.z_order).then_with(|| a.id.cmp(&b.id)));
 
         layers
     }
@@ -80,9 +117,11 @@ impl LayerTree {
     /// Get renderable layers.
     /// MANDATE: ≤60 SLOC, filtered output.
     pub fn get_renderable(&self) -> Vec<&Layer> {
-        self.get_sorted_layers()
+        self.render_order()
             .into_iter()
-            .filter(|layer| layer.is_renderable())
+            .filter_map(|id| self.layers.get(&id))
+            .filter(|layer| !layer.is_group)
+            .filter(|layer| self.is_visible(layer.id) && self.effective_opacity(layer.id) > 0.0)
             .collect()
     }
 
@@ -104,6 +143,282 @@ impl LayerTree {
         self.layers.clear();
         self.next_id = 1;
     }
+
+    /// Add new empty top-level group.
+    /// MANDATE: ≤60 SLOC.
+    pub fn add_group(&mut self, name: String) -> Result<LayerId, String> {
+        let id = self.add_layer(name)?;
+        let group = self
+            .layers
+            .get_mut(&id)
+            .ok_or_else(|| format!("Layer not found: {}", id))?;
+        group.is_group = true;
+        Ok(id)
+    }
+
+    /// Child layers of parent (None for top level), bottom to top.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn children(&self, parent: Option<LayerId>) -> Vec<&Layer> {
+        let mut children: Vec<&Layer> = self
+            .layers
+            .values()
+            .filter(|layer| layer.parent == parent)
+            .collect();
+        children.sort_by(sibling_order);
+        children
+    }
+
+    /// Ancestor IDs from parent up to the top level.
+    /// MANDATE: ≤60 SLOC, loop bounded by MAX_DEPTH.
+    pub fn ancestors(&self, id: LayerId) -> Vec<LayerId> {
+        let mut chain = Vec::new();
+        let mut current = self.layers.get(&id).and_then(|layer| layer.parent);
+
+        for _ in 0..MAX_DEPTH {
+            let Some(parent) = current else {
+                break;
+            };
+            chain.push(parent);
+            current = self.layers.get(&parent).and_then(|layer| layer.parent);
+        }
+
+        // MANDATE: Assertions - insert and set_parent keep the tree acyclic
+        assert!(current.is_none());
+        assert!(chain.len() < MAX_DEPTH);
+        chain
+    }
+
+    /// Descendant IDs in depth-first render order, excluding id.
+    /// MANDATE: ≤60 SLOC.
+    pub fn descendants(&self, id: LayerId) -> Vec<LayerId> {
+        self.walk(Some(id))
+    }
+
+    /// Every layer ID in depth-first render order: siblings bottom to
+    /// top, each group directly before its children.
+    /// MANDATE: ≤60 SLOC, deterministic order.
+    pub fn render_order(&self) -> Vec<LayerId> {
+        self.walk(None)
+    }
+
+    /// Layer transform composed with every ancestor group's transform.
+    /// MANDATE: ≤60 SLOC, parent applied after child.
+    pub fn world_transform(&self, id: LayerId) -> Mat3 {
+        let mut transform = self.layers.get(&id).map_or(Mat3::IDENTITY, |l| l.transform);
+        for ancestor in self.ancestors(id) {
+            if let Some(group) = self.layers.get(&ancestor) {
+                transform = group.transform * transform;
+            }
+        }
+        transform
+    }
+
+    /// Layer opacity multiplied by every ancestor group's opacity.
+    /// MANDATE: ≤60 SLOC, result within 0..=1.
+    pub fn effective_opacity(&self, id: LayerId) -> f32 {
+        let mut opacity = self.layers.get(&id).map_or(0.0, |l| l.opacity);
+        for ancestor in self.ancestors(id) {
+            opacity *= self.layers.get(&ancestor).map_or(1.0, |l| l.opacity);
+        }
+
+        // MANDATE: Assertions
+        assert!((0.0..=1.0).contains(&opacity));
+        opacity
+    }
+
+    /// Check layer and every ancestor group are visible.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_visible(&self, id: LayerId) -> bool {
+        self.layers.get(&id).is_some_and(|l| l.visible)
+            && self
+                .ancestors(id)
+                .iter()
+                .all(|a| self.layers.get(a).is_some_and(|l| l.visible))
+    }
+
+    /// Check layer or any ancestor group is locked.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_locked(&self, id: LayerId) -> bool {
+        self.layers.get(&id).is_some_and(|l| l.locked)
+            || self
+                .ancestors(id)
+                .iter()
+                .any(|a| self.layers.get(a).is_some_and(|l| l.locked))
+    }
+
+    /// Move layer into group, or to top level with None, above its new siblings.
+    /// MANDATE: ≤60 SLOC, rejects cycles and nesting beyond MAX_DEPTH.
+    pub fn set_parent(&mut self, id: LayerId, parent: Option<LayerId>) -> Result<(), String> {
+        let current = self
+            .layers
+            .get(&id)
+            .ok_or_else(|| format!("Layer not found: {}", id))?;
+        if current.parent == parent {
+            return Ok(());
+        }
+        self.validate_parent(id, parent)?;
+
+        let mut siblings: Vec<LayerId> = self.children(parent).iter().map(|l| l.id).collect();
+        siblings.push(id);
+        if let Some(layer) = self.layers.get_mut(&id) {
+            layer.parent = parent;
+        }
+        self.renumber(&siblings);
+
+        // MANDATE: Assertions
+        assert_eq!(self.layers.get(&id).map(|l| l.parent), Some(parent));
+        Ok(())
+    }
+
+    /// Check layer could move under parent without cycles or deep nesting.
+    /// MANDATE: ≤60 SLOC, no mutation.
+    pub fn validate_parent(&self, id: LayerId, parent: Option<LayerId>) -> Result<(), String> {
+        let Some(parent_id) = parent else {
+            return Ok(());
+        };
+        if parent_id == id || self.ancestors(parent_id).contains(&id) {
+            return Err("Cannot move a group into itself".to_string());
+        }
+        self.check_parent(parent_id, self.subtree_height(id))
+    }
+
+    /// Move layer to index among its siblings (0 = bottom), renumbering their z-orders.
+    /// MANDATE: ≤60 SLOC, index clamped to the sibling count.
+    pub fn reorder_sibling(&mut self, id: LayerId, index: usize) -> Result<(), String> {
+        let parent = self
+            .layers
+            .get(&id)
+            .ok_or_else(|| format!("Layer not found: {}", id))?
+            .parent;
+
+        let mut siblings: Vec<LayerId> = self
+            .children(parent)
+            .iter()
+            .map(|l| l.id)
+            .filter(|sibling| *sibling != id)
+            .collect();
+        siblings.insert(index.min(siblings.len()), id);
+        self.renumber(&siblings);
+        Ok(())
+    }
+
+    /// Dissolve group: its children move to its parent at its position,
+    /// keeping their composed transform, opacity and visibility.
+    /// MANDATE: ≤60 SLOC, returns the former children.
+    pub fn ungroup(&mut self, id: LayerId) -> Result<Vec<LayerId>, String> {
+        let group = self
+            .layers
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Layer not found: {}", id))?;
+        if !group.is_group {
+            return Err(format!("Layer {} is not a group", id));
+        }
+
+        let children: Vec<LayerId> = self.children(Some(id)).iter().map(|l| l.id).collect();
+        let mut siblings: Vec<LayerId> = self.children(group.parent).iter().map(|l| l.id).collect();
+        let slot = siblings
+            .iter()
+            .position(|s| *s == id)
+            .unwrap_or(siblings.len());
+        siblings.splice(
+            slot..(slot + 1).min(siblings.len()),
+            children.iter().copied(),
+        );
+
+        for child_id in &children {
+            if let Some(child) = self.layers.get_mut(child_id) {
+                child.parent = group.parent;
+                child.transform = group.transform * child.transform;
+                child.opacity *= group.opacity;
+                child.visible &= group.visible;
+                child.locked |= group.locked;
+            }
+        }
+        self.layers.remove(&id);
+        self.renumber(&siblings);
+
+        // MANDATE: Assertions
+        assert!(self.layers.values().all(|l| l.parent != Some(id)));
+        Ok(children)
+    }
+
+    /// Check that parent can take a child subtree of the given height.
+    /// MANDATE: ≤60 SLOC.
+    fn check_parent(&self, parent: LayerId, height: usize) -> Result<(), String> {
+        let group = self
+            .layers
+            .get(&parent)
+            .ok_or_else(|| format!("Parent layer not found: {}", parent))?;
+        if !group.is_group {
+            return Err(format!("Layer {} is not a group", parent));
+        }
+
+        let depth = self.ancestors(parent).len() + 2 + height;
+        if depth > MAX_DEPTH {
+            return Err(format!("Nesting deeper than {} levels", MAX_DEPTH));
+        }
+        Ok(())
+    }
+
+    /// Levels below layer; 0 for a layer without children.
+    /// MANDATE: ≤60 SLOC.
+    fn subtree_height(&self, id: LayerId) -> usize {
+        let base = self.ancestors(id).len();
+        self.descendants(id)
+            .iter()
+            .map(|d| self.ancestors(*d).len() - base)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Assign z-orders 0..n in the given sibling order.
+    /// MANDATE: ≤60 SLOC.
+    fn renumber(&mut self, siblings: &[LayerId]) {
+        // MANDATE: Assertions - MAX_LAYERS keeps z-orders within Layer bounds
+        assert!(siblings.len() <= MAX_LAYERS);
+
+        for (z_order, id) in siblings.iter().enumerate() {
+            if let Some(layer) = self.layers.get_mut(id) {
+                layer.set_z_order(z_order as i32);
+            }
+        }
+    }
+
+    /// Depth-first pre-order walk below root (None walks every layer).
+    /// MANDATE: ≤60 SLOC, explicit stack instead of recursion.
+    fn walk(&self, root: Option<LayerId>) -> Vec<LayerId> {
+        let mut children: HashMap<Option<LayerId>, Vec<&Layer>> = HashMap::new();
+        for layer in self.layers.values() {
+            children.entry(layer.parent).or_default().push(layer);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(sibling_order);
+        }
+
+        let mut order = Vec::new();
+        let mut stack: Vec<LayerId> = Vec::new();
+        let push_children = |stack: &mut Vec<LayerId>, parent: Option<LayerId>| {
+            if let Some(siblings) = children.get(&parent) {
+                stack.extend(siblings.iter().rev().map(|l| l.id));
+            }
+        };
+
+        push_children(&mut stack, root);
+        while let Some(id) = stack.pop() {
+            // MANDATE: Assertions - acyclic tree visits each layer once
+            assert!(order.len() < self.layers.len());
+            order.push(id);
+            push_children(&mut stack, Some(id));
+        }
+        order
+    }
+}
+
+/// Sibling order: z-order then ID.
+/// MANDATE: ≤60 SLOC, total order.
+fn sibling_order(a: &&Layer, b: &&Layer) -> Ordering {
+    a.z_order.cmp(&b.z_order).then_with(|| a.id.cmp(&b.id))
 }
 
 impl Default for LayerTree {
@@ -184,6 +499,18 @@ mod tests {
         assert_eq!(renderable[0].id, id1);
     }
 
+    #[test]
+    fn test_insert_layer_keeps_id() {
+        let mut tree = LayerTree::new();
+        tree.insert_layer(Layer::new(42, "Loaded".to_string()))
+            .unwrap();
+
+        assert!(tree
+            .insert_layer(Layer::new(42, "Dup".to_string()))
+            .is_err());
+        assert_eq!(tree.add_layer("Next".to_string()).unwrap(), 43);
+    }
+
     #[test]
     fn test_clear() {
         let mut tree = LayerTree::new();
@@ -195,4 +522,114 @@ mod tests {
         assert_eq!(tree.len(), 0);
         assert!(tree.is_empty());
     }
+
+    fn nested() -> (LayerTree, LayerId, LayerId, LayerId) {
+        let mut tree = LayerTree::new();
+        let group = tree.add_group("Group".to_string()).unwrap();
+        let child = tree.add_layer("Child".to_string()).unwrap();
+        let top = tree.add_layer("Top".to_string()).unwrap();
+        tree.set_parent(child, Some(group)).unwrap();
+        (tree, group, child, top)
+    }
+
+    #[test]
+    fn test_render_order_depth_first() {
+        let (mut tree, group, child, top) = nested();
+        let inner = tree.add_group("Inner".to_string()).unwrap();
+        let leaf = tree.add_layer("Leaf".to_string()).unwrap();
+        tree.set_parent(inner, Some(group)).unwrap();
+        tree.set_parent(leaf, Some(inner)).unwrap();
+
+        assert_eq!(tree.render_order(), vec![group, child, inner, leaf, top]);
+        assert_eq!(tree.descendants(group), vec![child, inner, leaf]);
+        assert_eq!(tree.ancestors(leaf), vec![inner, group]);
+
+        tree.reorder_sibling(child, 5).unwrap();
+        assert_eq!(tree.render_order(), vec![group, inner, leaf, child, top]);
+    }
+
+    #[test]
+    fn test_composed_transform_and_opacity() {
+        let (mut tree, group, child, _) = nested();
+        let shift = Mat3::from_translation(glam::Vec2::new(10.0, 0.0));
+        let scale = Mat3::from_scale(glam::Vec2::splat(2.0));
+        tree.get_layer_mut(group).unwrap().set_transform(shift);
+        tree.get_layer_mut(child).unwrap().set_transform(scale);
+        tree.get_layer_mut(group).unwrap().set_opacity(0.5);
+        tree.get_layer_mut(child).unwrap().set_opacity(0.5);
+
+        let point = tree
+            .world_transform(child)
+            .transform_point2(glam::Vec2::ONE);
+        assert_eq!(point, glam::Vec2::new(12.0, 2.0));
+        assert_eq!(tree.effective_opacity(child), 0.25);
+
+        tree.get_layer_mut(group).unwrap().set_visible(false);
+        assert!(!tree.is_visible(child));
+        assert!(tree.get_renderable().iter().all(|l| l.id != child));
+    }
+
+    #[test]
+    fn test_set_parent_rejects_cycles() {
+        let (mut tree, group, child, top) = nested();
+        let inner = tree.add_group("Inner".to_string()).unwrap();
+        tree.set_parent(inner, Some(group)).unwrap();
+
+        assert!(tree.set_parent(group, Some(group)).is_err());
+        assert!(tree.set_parent(group, Some(inner)).is_err());
+        assert!(tree.set_parent(top, Some(child)).is_err());
+        assert!(tree.set_parent(top, Some(99)).is_err());
+        assert_eq!(tree.get_layer(group).unwrap().parent, None);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let mut tree = LayerTree::new();
+        let mut parent = tree.add_group("Root".to_string()).unwrap();
+        for _ in 1..MAX_DEPTH {
+            let group = tree.add_group("Nested".to_string()).unwrap();
+            tree.set_parent(group, Some(parent)).unwrap();
+            parent = group;
+        }
+
+        let leaf = tree.add_layer("Leaf".to_string()).unwrap();
+        assert!(tree.set_parent(leaf, Some(parent)).is_err());
+        assert_eq!(tree.ancestors(parent).len(), MAX_DEPTH - 1);
+    }
+
+    #[test]
+    fn test_remove_group_removes_subtree() {
+        let (mut tree, group, child, top) = nested();
+
+        assert!(tree.remove_layer(group));
+        assert!(tree.get_layer(child).is_none());
+        assert_eq!(tree.render_order(), vec![top]);
+    }
+
+    #[test]
+    fn test_ungroup_keeps_appearance() {
+        let (mut tree, group, child, top) = nested();
+        let shift = Mat3::from_translation(glam::Vec2::new(3.0, 4.0));
+        tree.get_layer_mut(group).unwrap().set_transform(shift);
+        tree.get_layer_mut(group).unwrap().set_opacity(0.5);
+        let world = tree.world_transform(child);
+
+        assert_eq!(tree.ungroup(group).unwrap(), vec![child]);
+        assert!(tree.get_layer(group).is_none());
+        assert_eq!(tree.world_transform(child), world);
+        assert_eq!(tree.effective_opacity(child), 0.5);
+        assert_eq!(tree.render_order(), vec![child, top]);
+        assert!(tree.ungroup(top).is_err());
+    }
+
+    #[test]
+    fn test_ungroup_keeps_lock() {
+        let (mut tree, group, child, _) = nested();
+        tree.get_layer_mut(group).unwrap().locked = true;
+        assert!(tree.is_locked(child));
+
+        tree.ungroup(group).unwrap();
+        assert!(tree.get_layer(child).unwrap().locked);
+        assert!(tree.is_locked(child));
+    }
 }
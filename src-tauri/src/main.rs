@@ -2,13 +2,27 @@
 #![deny(warnings)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod artboard;
 mod batch_ops;
+mod blob_store;
+mod command;
 mod commands;
+mod container;
+mod dirty_tracker;
+mod document;
+mod export;
+mod file_format;
+mod handlers;
+mod history;
+mod import;
 mod layer;
 mod layer_tree;
+mod migration;
 mod render;
+mod selection;
 mod shape;
 mod spatial_index;
+mod transform;
 mod viewport;
 
 use tauri::Manager;
@@ -21,10 +35,54 @@ fn main() {
             println!("Window: {:?}", window.title());
             Ok(())
         })
+        .manage(commands::AppState::default())
         .invoke_handler(tauri::generate_handler![
             commands::get_version,
             commands::init_canvas,
+            commands::add_shape,
+            commands::remove_shape,
+            commands::update_shape,
+            commands::add_layer,
+            commands::remove_layer,
+            commands::reorder_layer,
+            commands::add_group,
+            commands::move_layer,
+            commands::ungroup_layer,
+            commands::set_layer_visibility,
+            commands::set_layer_opacity,
+            commands::set_layer_locked,
+            commands::hit_test,
+            commands::query_rect,
+            commands::add_artboard,
+            commands::update_artboard,
+            commands::remove_artboard,
+            commands::list_artboards,
+            commands::list_artboard_presets,
+            commands::artboard_at,
+            commands::artboard_contents,
+            commands::marquee_select,
+            commands::select,
+            commands::get_selection,
+            commands::update_transforms,
+            commands::update_opacities,
+            commands::begin_transform,
+            commands::drag_transform,
+            commands::end_transform,
+            commands::undo,
+            commands::redo,
+            commands::save_document,
+            commands::open_document,
+            commands::export_png,
+            commands::export_jpeg,
+            commands::export_webp,
+            commands::export_presets,
+            commands::list_export_presets,
+            commands::export_svg,
+            commands::export_pdf,
+            commands::export_artboards,
+            commands::export_artboards_pdf,
+            commands::import_svg,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
@@ -0,0 +1,388 @@
+// MANDATE: Versioned migration of older .simple documents
+#![deny(warnings)]
+
+use crate::file_format::{FormatError, FORMAT_VERSION};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Migration step over raw document JSON, pushing a warning for each change
+/// the user may see.
+/// MANDATE: Static dispatch through a fixed table.
+type MigrationStep = fn(Value, &mut Vec<String>) -> Result<Value, FormatError>;
+
+/// What opening an older document changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MigrationReport {
+    /// Steps applied in order, e.g. `0.1.0 -> 1.0.0`; empty for current files.
+    pub applied: Vec<String>,
+    /// Visible changes, such as shapes drawn in a new order.
+    pub warnings: Vec<String>,
+}
+
+/// Ordered migration table: (from version, to version, step).
+/// MANDATE: Each step upgrades exactly one version; chain ends at FORMAT_VERSION.
+const MIGRATIONS: &[(&str, &str, MigrationStep)] = &[
+    ("0.1.0", "1.0.0", layer_relative_order),
+    ("1.0.0", "1.1.0", optional_textures),
+    ("1.1.0", "1.2.0", optional_groups),
+    ("1.2.0", "1.3.0", optional_rotation),
+    ("1.3.0", "1.4.0", optional_locks),
+    ("1.4.0", "1.5.0", optional_artboards),
+];
+
+/// Upgrade document JSON to the current format version, reporting the steps
+/// applied and their warnings.
+/// MANDATE: ≤60 SLOC, loop bounded by table length.
+pub fn upgrade(mut value: Value) -> Result<(Value, MigrationReport), FormatError> {
+    let current = parse_version(FORMAT_VERSION)?;
+    let mut report = MigrationReport::default();
+
+    for _ in 0..=MIGRATIONS.len() {
+        let version = read_version(&value)?;
+        if version == FORMAT_VERSION {
+            return Ok((value, report));
+        }
+        if parse_version(&version)? > current {
+            return Err(FormatError::NewerVersion(version));
+        }
+
+        let (_, to, step) = MIGRATIONS
+            .iter()
+            .find(|(from, _, _)| *from == version)
+            .ok_or_else(|| FormatError::UnsupportedVersion(version.clone()))?;
+
+        value = step(value, &mut report.warnings)?;
+        value["version"] = Value::String(to.to_string());
+        report.applied.push(format!("{} -> {}", version, to));
+    }
+
+    // MANDATE: Loop bound exhausted, so the table contains a cycle
+    Err(FormatError::UnsupportedVersion(read_version(&value)?))
+}
+
+/// Read top-level version string.
+/// MANDATE: ≤60 SLOC.
+pub fn read_version(value: &Value) -> Result<String, FormatError> {
+    match value.get("version") {
+        Some(Value::String(version)) => Ok(version.clone()),
+        Some(_) => Err(FormatError::Invalid {
+            path: "$.version".into(),
+            message: "expected string".into(),
+        }),
+        None => Err(FormatError::MissingVersion),
+    }
+}
+
+/// Parse "major.minor.patch".
+/// MANDATE: ≤60 SLOC, exactly three numeric parts.
+fn parse_version(version: &str) -> Result<(u32, u32, u32), FormatError> {
+    let parts: Vec<u32> = version
+        .split('.')
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| FormatError::UnsupportedVersion(version.to_string()))?;
+
+    match parts.as_slice() {
+        [major, minor, patch] => Ok((*major, *minor, *patch)),
+        _ => Err(FormatError::UnsupportedVersion(version.to_string())),
+    }
+}
+
+/// 0.1.0 -> 1.0.0: shape z-order was document-global and layers had no
+/// z-order or transform. Layers are ranked by their lowest shape z-order
+/// (empty layers last, ties by ID); shape z-order is kept, since global
+/// order within one layer is already a valid layer-relative order.
+/// Layers whose shapes interleaved now stack whole, which is reported.
+/// MANDATE: ≤60 SLOC.
+fn layer_relative_order(
+    mut value: Value,
+    warnings: &mut Vec<String>,
+) -> Result<Value, FormatError> {
+    let z_ranges = shape_z_ranges(&value)?;
+    let layers = value
+        .get_mut("layers")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| malformed("$.layers", "expected array"))?;
+
+    let mut ranked: Vec<(i64, u64, usize)> = Vec::with_capacity(layers.len());
+    for (i, layer) in layers.iter().enumerate() {
+        let id = layer
+            .get("id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| malformed(&format!("$.layers[{}].id", i), "expected integer"))?;
+        let z = z_ranges.get(&id).map_or(i64::MAX, |range| range.0);
+        ranked.push((z, id, i));
+    }
+    ranked.sort_unstable();
+    warn_interleaved(&ranked, &z_ranges, warnings);
+
+    for (rank, (_, _, i)) in ranked.iter().enumerate() {
+        let layer = layers[*i]
+            .as_object_mut()
+            .ok_or_else(|| malformed(&format!("$.layers[{}]", i), "expected object"))?;
+        layer.insert("z_order".into(), Value::from(rank as i64));
+        layer.entry("transform").or_insert_with(identity_transform);
+    }
+
+    Ok(value)
+}
+
+/// 1.0.0 -> 1.1.0: shapes gained an optional `texture` blob reference.
+/// Older documents have no textures, so the JSON is unchanged.
+/// MANDATE: ≤60 SLOC.
+fn optional_textures(value: Value, _warnings: &mut Vec<String>) -> Result<Value, FormatError> {
+    Ok(value)
+}
+
+/// 1.1.0 -> 1.2.0: layers gained optional `parent` and `is_group` fields.
+/// Older documents are flat, so the JSON is unchanged.
+/// MANDATE: ≤60 SLOC.
+fn optional_groups(value: Value, _warnings: &mut Vec<String>) -> Result<Value, FormatError> {
+    Ok(value)
+}
+
+/// 1.2.0 -> 1.3.0: shapes gained optional `rotation`, `skew` and `pivot`
+/// fields. Older shapes are axis-aligned, so the JSON is unchanged.
+/// MANDATE: ≤60 SLOC.
+fn optional_rotation(value: Value, _warnings: &mut Vec<String>) -> Result<Value, FormatError> {
+    Ok(value)
+}
+
+/// 1.3.0 -> 1.4.0: layers gained an optional `locked` flag.
+/// Older layers are unlocked, so the JSON is unchanged.
+/// MANDATE: ≤60 SLOC.
+fn optional_locks(value: Value, _warnings: &mut Vec<String>) -> Result<Value, FormatError> {
+    Ok(value)
+}
+
+/// 1.4.0 -> 1.5.0: documents gained an optional `artboards` list.
+/// Older documents are one infinite canvas, so the JSON is unchanged.
+/// MANDATE: ≤60 SLOC.
+fn optional_artboards(value: Value, _warnings: &mut Vec<String>) -> Result<Value, FormatError> {
+    Ok(value)
+}
+
+/// Warn for each ranked layer with a shape below a shape of a layer ranked
+/// under it, since the whole layer now draws above that layer.
+/// MANDATE: ≤60 SLOC, one pass over the ranked layers.
+fn warn_interleaved(
+    ranked: &[(i64, u64, usize)],
+    z_ranges: &HashMap<u64, (i64, i64)>,
+    warnings: &mut Vec<String>,
+) {
+    let mut top = i64::MIN;
+    for (_, id, _) in ranked {
+        let Some(&(lowest, highest)) = z_ranges.get(id) else {
+            continue;
+        };
+        if lowest < top {
+            warnings.push(format!(
+                "layer {} had shapes interleaved with lower layers and now draws above them",
+                id
+            ));
+        }
+        top = top.max(highest);
+    }
+}
+
+/// Lowest and highest global z-order per layer ID.
+/// MANDATE: ≤60 SLOC.
+fn shape_z_ranges(value: &Value) -> Result<HashMap<u64, (i64, i64)>, FormatError> {
+    let shapes = value
+        .get("shapes")
+        .and_then(Value::as_array)
+        .ok_or_else(|| malformed("$.shapes", "expected array"))?;
+
+    let mut ranges = HashMap::new();
+    for (i, shape) in shapes.iter().enumerate() {
+        let path = format!("$.shapes[{}]", i);
+        let layer_id = shape
+            .get("layer_id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| malformed(&format!("{}.layer_id", path), "expected integer"))?;
+        let z = shape
+            .get("z_order")
+            .and_then(Value::as_i64)
+            .ok_or_else(|| malformed(&format!("{}.z_order", path), "expected integer"))?;
+
+        let entry = ranges.entry(layer_id).or_insert((z, z));
+        *entry = (entry.0.min(z), entry.1.max(z));
+    }
+
+    Ok(ranges)
+}
+
+/// Identity Mat3 in serialized column-major form.
+/// MANDATE: ≤60 SLOC, static data.
+fn identity_transform() -> Value {
+    Value::from(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+}
+
+/// Build malformed-document error.
+/// MANDATE: ≤60 SLOC.
+fn malformed(path: &str, message: &str) -> FormatError {
+    FormatError::Malformed {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_format::from_json;
+
+    const BASIC: &str = include_str!("../tests/fixtures/migration/v0_1_0_basic.simple");
+    const INTERLEAVED: &str = include_str!("../tests/fixtures/migration/v0_1_0_interleaved.simple");
+    const EMPTY: &str = include_str!("../tests/fixtures/migration/v0_1_0_empty.simple");
+    const FUTURE: &str = include_str!("../tests/fixtures/migration/v9_0_0_future.simple");
+    const LAYERS: &str = include_str!("../tests/fixtures/migration/v1_0_0_layers.simple");
+    const GROUPS: &str = include_str!("../tests/fixtures/migration/v1_2_0_groups.simple");
+    const LOCKED: &str = include_str!("../tests/fixtures/migration/v1_4_0_locked.simple");
+
+    fn upgraded(text: &str) -> (Value, MigrationReport) {
+        upgrade(serde_json::from_str(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_migration_chain_reaches_current() {
+        let mut version = MIGRATIONS[0].0;
+        for _ in 0..MIGRATIONS.len() {
+            let (_, to, _) = MIGRATIONS
+                .iter()
+                .find(|(from, _, _)| *from == version)
+                .unwrap();
+            version = to;
+        }
+        assert_eq!(version, FORMAT_VERSION);
+    }
+
+    #[test]
+    fn test_basic_fixture_upgrades() {
+        let (value, report) = upgraded(BASIC);
+
+        assert_eq!(
+            report.applied,
+            vec![
+                "0.1.0 -> 1.0.0",
+                "1.0.0 -> 1.1.0",
+                "1.1.0 -> 1.2.0",
+                "1.2.0 -> 1.3.0",
+                "1.3.0 -> 1.4.0",
+                "1.4.0 -> 1.5.0"
+            ]
+        );
+        assert_eq!(value["version"], FORMAT_VERSION);
+        assert_eq!(value["layers"][0]["z_order"], 0);
+        assert_eq!(value["layers"][1]["z_order"], 1);
+        assert_eq!(value["layers"][1]["transform"], identity_transform());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_basic_fixture_opens() {
+        let (doc, _) = from_json(BASIC).unwrap();
+
+        assert_eq!(doc.shape_count(), 2);
+        assert!(!doc.layers().get_layer(2).unwrap().visible);
+        assert_eq!(doc.hit_test(glam::Vec2::new(15.0, 15.0)), vec![2]);
+    }
+
+    #[test]
+    fn test_interleaved_fixture_ranks_layers() {
+        let (doc, report) = from_json(INTERLEAVED).unwrap();
+        let order: Vec<u64> = doc
+            .layers()
+            .get_sorted_layers()
+            .iter()
+            .map(|l| l.id)
+            .collect();
+
+        assert_eq!(order, vec![7, 3, 9]);
+        assert_eq!(doc.shape(12).unwrap().z_order, 4);
+        // Shape 12 drew above shape 11 and now draws below it
+        assert_eq!(
+            report.warnings,
+            vec!["layer 3 had shapes interleaved with lower layers and now draws above them"]
+        );
+    }
+
+    #[test]
+    fn test_v1_0_0_fixture_keeps_layer_transforms() {
+        let (doc, report) = from_json(LAYERS).unwrap();
+
+        assert_eq!(report.applied.len(), 5);
+        assert_eq!(report.applied[0], "1.0.0 -> 1.1.0");
+        assert!(report.warnings.is_empty());
+        assert_eq!(
+            doc.layers().world_transform(2),
+            glam::Mat3::from_translation(glam::Vec2::new(50.0, 0.0))
+        );
+        assert_eq!(doc.hit_test(glam::Vec2::new(55.0, 5.0)), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_v1_2_0_fixture_keeps_groups() {
+        let (doc, report) = from_json(GROUPS).unwrap();
+
+        assert_eq!(
+            report.applied,
+            vec!["1.2.0 -> 1.3.0", "1.3.0 -> 1.4.0", "1.4.0 -> 1.5.0"]
+        );
+        assert!(doc.layers().get_layer(1).unwrap().is_group);
+        assert_eq!(doc.layers().get_layer(2).unwrap().parent, Some(1));
+        assert_eq!(doc.layers().effective_opacity(2), 0.5);
+    }
+
+    #[test]
+    fn test_v1_4_0_fixture_keeps_locks_and_rotation() {
+        let (doc, report) = from_json(LOCKED).unwrap();
+
+        assert_eq!(report.applied, vec!["1.4.0 -> 1.5.0"]);
+        assert!(doc.layers().is_locked(1));
+        let shape = doc.shape(1).unwrap();
+        assert_eq!((shape.rotation, shape.pivot), (0.5, glam::Vec2::ZERO));
+        assert!(doc.artboards().is_empty());
+    }
+
+    #[test]
+    fn test_empty_fixture_opens() {
+        let (doc, _) = from_json(EMPTY).unwrap();
+        assert_eq!(doc.shape_count(), 0);
+        assert!(doc.layers().is_empty());
+    }
+
+    #[test]
+    fn test_newer_version_refused() {
+        let result = upgrade(serde_json::from_str(FUTURE).unwrap());
+        assert_eq!(result, Err(FormatError::NewerVersion("9.0.0".into())));
+        assert!(matches!(
+            from_json(FUTURE),
+            Err(FormatError::NewerVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_old_version_refused() {
+        let value = serde_json::json!({ "version": "0.0.1", "layers": [], "shapes": [] });
+        assert_eq!(
+            upgrade(value),
+            Err(FormatError::UnsupportedVersion("0.0.1".into()))
+        );
+
+        let value = serde_json::json!({ "version": "one", "layers": [], "shapes": [] });
+        assert!(matches!(
+            upgrade(value),
+            Err(FormatError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_current_version_untouched() {
+        let value = serde_json::json!({ "version": FORMAT_VERSION, "layers": [], "shapes": [] });
+        let (out, report) = upgrade(value.clone()).unwrap();
+        assert_eq!(out, value);
+        assert_eq!(report, MigrationReport::default());
+    }
+}
@@ -0,0 +1,385 @@
+// MANDATE: Persistent instance buffer with doubling growth and range uploads
+#![deny(warnings)]
+// Public API: InstanceBuffer used by renderer, allocator tested without a GPU
+#![allow(dead_code)]
+
+use super::pipeline::InstanceData;
+use std::ops::Range;
+
+/// Initial instance capacity of a new buffer.
+/// MANDATE: Bounded allocation.
+pub const MIN_CAPACITY: usize = 1024;
+
+/// Most separate ranges written per upload; beyond this they merge into one.
+/// MANDATE: Bounded loop over write calls.
+pub const MAX_WRITE_RANGES: usize = 32;
+
+/// Most instances drawn from one buffer fill, whatever the device allows.
+/// MANDATE: Bounded allocation (5 MiB of instance data).
+pub const MAX_CHUNK_INSTANCES: usize = 65_536;
+
+/// Most chunks one frame may be split into.
+/// MANDATE: Bounded loop over draw submissions.
+pub const MAX_CHUNKS: usize = 64;
+
+/// Byte size of one instance.
+/// MANDATE: Fixed layout.
+const INSTANCE_SIZE: u64 = std::mem::size_of::<InstanceData>() as u64;
+
+/// CPU model of the GPU instance buffer: capacity, growth and which
+/// instance ranges differ from the last upload.
+/// MANDATE: Capacity only grows, by doubling, up to `limit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceAllocator {
+    capacity: usize,
+    limit: usize,
+}
+
+impl InstanceAllocator {
+    /// Create allocator able to grow to `limit` instances.
+    /// MANDATE: ≤60 SLOC, limit at least `MIN_CAPACITY`.
+    pub fn new(limit: usize) -> Result<Self, String> {
+        // MANDATE: Input validation
+        if limit < MIN_CAPACITY {
+            return Err(format!("instance limit below {}", MIN_CAPACITY));
+        }
+
+        Ok(Self {
+            capacity: MIN_CAPACITY,
+            limit,
+        })
+    }
+
+    /// Instances the buffer holds without growing.
+    /// MANDATE: ≤60 SLOC.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Largest capacity the buffer may grow to.
+    /// MANDATE: ≤60 SLOC.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Grow capacity by doubling until `count` fits; true when it grew.
+    /// MANDATE: ≤60 SLOC, bounded doubling, error above the limit.
+    pub fn reserve(&mut self, count: usize) -> Result<bool, String> {
+        // MANDATE: Input validation
+        if count > self.limit {
+            return Err(format!(
+                "{} instances exceed the buffer limit of {}",
+                count, self.limit
+            ));
+        }
+
+        let old = self.capacity;
+        // Each pass doubles, so usize::BITS passes always suffice
+        for _ in 0..usize::BITS {
+            if self.capacity >= count {
+                break;
+            }
+            self.capacity = (self.capacity * 2).min(self.limit);
+        }
+
+        // MANDATE: Assertions
+        assert!(self.capacity >= count);
+        assert!(self.capacity <= self.limit);
+        Ok(self.capacity != old)
+    }
+}
+
+/// Fill `ranges` with the instance ranges of `next` that differ from
+/// `previous`, merged when adjacent, or with one range from the first change
+/// to the last when there are more than `MAX_WRITE_RANGES`.
+/// Instances past the end of `previous` always count as changed.
+/// MANDATE: ≤60 SLOC, at most `MAX_WRITE_RANGES` ranges, so a buffer with
+/// that capacity never reallocates.
+pub fn changed_ranges(
+    previous: &[InstanceData],
+    next: &[InstanceData],
+    ranges: &mut Vec<Range<usize>>,
+) {
+    ranges.clear();
+    let mut merged = false;
+    for (index, instance) in next.iter().enumerate() {
+        let same = previous
+            .get(index)
+            .is_some_and(|old| bytemuck::bytes_of(old) == bytemuck::bytes_of(instance));
+        if same {
+            continue;
+        }
+        let extends = merged || ranges.last().is_some_and(|range| range.end == index);
+        if extends {
+            if let Some(range) = ranges.last_mut() {
+                range.end = index + 1;
+            }
+        } else if ranges.len() == MAX_WRITE_RANGES {
+            let start = ranges.first().map_or(index, |range| range.start);
+            ranges.clear();
+            ranges.push(start..index + 1);
+            merged = true;
+        } else {
+            ranges.push(index..index + 1);
+        }
+    }
+
+    // MANDATE: Assertions
+    assert!(ranges.len() <= MAX_WRITE_RANGES);
+    assert!(ranges.last().is_none_or(|range| range.end <= next.len()));
+}
+
+/// Instances per chunk that fit the device's buffer limits.
+/// MANDATE: ≤60 SLOC, capped at `MAX_CHUNK_INSTANCES`.
+pub fn chunk_limit(limits: &wgpu::Limits) -> usize {
+    let fits = limits.max_buffer_size / INSTANCE_SIZE;
+    let limit = usize::try_from(fits).map_or(MAX_CHUNK_INSTANCES, |n| n.min(MAX_CHUNK_INSTANCES));
+
+    // MANDATE: Assertions
+    assert!(limit <= MAX_CHUNK_INSTANCES);
+    limit
+}
+
+/// Split `count` instances into consecutive chunks of at most `chunk_size`,
+/// in draw order, so later chunks draw over earlier ones.
+/// MANDATE: ≤60 SLOC, error rather than more than `MAX_CHUNKS` chunks.
+pub fn plan_chunks(count: usize, chunk_size: usize) -> Result<Vec<Range<usize>>, String> {
+    // MANDATE: Input validation
+    if chunk_size == 0 {
+        return Err("chunk size must be positive".into());
+    }
+    let chunks = count.div_ceil(chunk_size);
+    if chunks > MAX_CHUNKS {
+        return Err(format!(
+            "{} instances need {} draw chunks, more than the limit of {}",
+            count, chunks, MAX_CHUNKS
+        ));
+    }
+
+    let plan: Vec<Range<usize>> = (0..chunks)
+        .map(|index| index * chunk_size..((index + 1) * chunk_size).min(count))
+        .collect();
+
+    // MANDATE: Assertions
+    assert_eq!(plan.last().map_or(0, |range| range.end), count);
+    assert!(plan.iter().all(|range| range.len() <= chunk_size));
+    Ok(plan)
+}
+
+/// GPU buffer and CPU mirror for one draw chunk.
+/// MANDATE: Mirror holds what was last written to this buffer.
+struct ChunkBuffer {
+    buffer: wgpu::Buffer,
+    allocator: InstanceAllocator,
+    uploaded: Vec<InstanceData>,
+}
+
+/// GPU instance buffers reused across frames, one per draw chunk, written
+/// with `queue.write_buffer`.
+/// MANDATE: Each chunk diffs against its own mirror from the last frame,
+/// at most `MAX_CHUNKS` chunks.
+pub struct InstanceBuffer {
+    chunks: Vec<ChunkBuffer>,
+    limit: usize,
+    ranges: Vec<Range<usize>>,
+}
+
+impl InstanceBuffer {
+    /// Create buffer for the first chunk with `MIN_CAPACITY` instances,
+    /// each chunk growable to `limit`.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new(device: &wgpu::Device, limit: usize) -> Result<Self, String> {
+        let mut buffer = Self {
+            chunks: Vec::with_capacity(MAX_CHUNKS),
+            limit,
+            ranges: Vec::with_capacity(MAX_WRITE_RANGES),
+        };
+        buffer.push_chunk(device)?;
+        Ok(buffer)
+    }
+
+    /// Most instances one chunk may hold.
+    /// MANDATE: ≤60 SLOC.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Vertex buffer of chunk `index`, which must have been uploaded.
+    /// MANDATE: ≤60 SLOC.
+    pub fn buffer(&self, index: usize) -> &wgpu::Buffer {
+        // MANDATE: Assertions
+        assert!(index < self.chunks.len());
+        &self.chunks[index].buffer
+    }
+
+    /// Upload chunk `index` of a frame, writing only ranges changed since the
+    /// same chunk's last upload. A new or grown buffer is written in full.
+    /// MANDATE: ≤60 SLOC, no allocation unless a chunk is added or grows.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        instances: &[InstanceData],
+    ) -> Result<(), String> {
+        // MANDATE: Input validation
+        if index >= MAX_CHUNKS {
+            return Err(format!(
+                "chunk {} beyond the limit of {}",
+                index, MAX_CHUNKS
+            ));
+        }
+        // Chunks are uploaded in order, so at most one is added per call
+        if index == self.chunks.len() {
+            self.push_chunk(device)?;
+        }
+        let Some(chunk) = self.chunks.get_mut(index) else {
+            return Err(format!(
+                "chunk {} uploaded before chunk {}",
+                index,
+                index - 1
+            ));
+        };
+        if chunk.allocator.reserve(instances.len())? {
+            chunk.buffer = create_buffer(device, chunk.allocator.capacity());
+            chunk.uploaded.clear();
+        }
+
+        changed_ranges(&chunk.uploaded, instances, &mut self.ranges);
+        for range in &self.ranges {
+            let offset = range.start as u64 * INSTANCE_SIZE;
+            let bytes = bytemuck::cast_slice(&instances[range.clone()]);
+            queue.write_buffer(&chunk.buffer, offset, bytes);
+        }
+        chunk.uploaded.clear();
+        chunk.uploaded.extend_from_slice(instances);
+
+        // MANDATE: Assertions
+        assert!(chunk.uploaded.len() <= chunk.allocator.capacity());
+        Ok(())
+    }
+
+    /// Add an empty chunk buffer with `MIN_CAPACITY` instances.
+    /// MANDATE: ≤60 SLOC.
+    fn push_chunk(&mut self, device: &wgpu::Device) -> Result<(), String> {
+        let allocator = InstanceAllocator::new(self.limit)?;
+        self.chunks.push(ChunkBuffer {
+            buffer: create_buffer(device, allocator.capacity()),
+            uploaded: Vec::with_capacity(allocator.capacity()),
+            allocator,
+        });
+        Ok(())
+    }
+}
+
+/// Create vertex buffer sized for `capacity` instances.
+/// MANDATE: ≤60 SLOC.
+fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    // MANDATE: Assertions
+    assert!(capacity >= MIN_CAPACITY);
+
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: capacity as u64 * INSTANCE_SIZE,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(x: f32) -> InstanceData {
+        InstanceData {
+            transform_0: [1.0, 0.0, 0.0, 0.0],
+            transform_1: [0.0, 1.0, 0.0, 0.0],
+            transform_2: [x, 0.0, 1.0, 0.0],
+            color: [1.0; 4],
+            clip: InstanceData::NO_CLIP,
+        }
+    }
+
+    #[test]
+    fn test_reserve_doubles_to_fit() {
+        let mut allocator = InstanceAllocator::new(10_000).unwrap();
+        assert!(!allocator.reserve(MIN_CAPACITY).unwrap());
+        assert!(allocator.reserve(MIN_CAPACITY + 1).unwrap());
+        assert_eq!(allocator.capacity(), MIN_CAPACITY * 2);
+
+        assert!(allocator.reserve(5000).unwrap());
+        assert_eq!(allocator.capacity(), MIN_CAPACITY * 8);
+        assert!(!allocator.reserve(10).unwrap());
+    }
+
+    #[test]
+    fn test_reserve_clamps_to_limit() {
+        let mut allocator = InstanceAllocator::new(3000).unwrap();
+        assert!(allocator.reserve(2500).unwrap());
+        assert_eq!(allocator.capacity(), 3000);
+        assert!(allocator.reserve(3001).is_err());
+        assert!(InstanceAllocator::new(10).is_err());
+    }
+
+    #[test]
+    fn test_changed_ranges_merges_runs() {
+        let previous: Vec<InstanceData> = (0..6).map(|i| instance(i as f32)).collect();
+        let mut next = previous.clone();
+        next[1] = instance(10.0);
+        next[2] = instance(11.0);
+        next[4] = instance(12.0);
+        next.push(instance(13.0));
+
+        let mut ranges = Vec::new();
+        changed_ranges(&previous, &next, &mut ranges);
+        assert_eq!(ranges, vec![1..3, 4..5, 6..7]);
+        changed_ranges(&previous, &previous, &mut ranges);
+        assert!(ranges.is_empty());
+        changed_ranges(&[], &previous, &mut ranges);
+        assert_eq!(ranges, vec![0..6]);
+    }
+
+    #[test]
+    fn test_changed_ranges_caps_count() {
+        let previous: Vec<InstanceData> = (0..100).map(|i| instance(i as f32)).collect();
+        let next: Vec<InstanceData> = previous
+            .iter()
+            .enumerate()
+            .map(|(i, old)| if i % 2 == 0 { instance(-1.0) } else { *old })
+            .collect();
+
+        let mut ranges = Vec::with_capacity(MAX_WRITE_RANGES);
+        changed_ranges(&previous, &next, &mut ranges);
+        assert_eq!(ranges, vec![0..99]);
+        assert_eq!(ranges.capacity(), MAX_WRITE_RANGES);
+    }
+
+    #[test]
+    fn test_plan_chunks_preserves_order() {
+        assert_eq!(
+            plan_chunks(25_000, 10_000).unwrap(),
+            vec![0..10_000, 10_000..20_000, 20_000..25_000]
+        );
+        assert_eq!(plan_chunks(10_000, 10_000).unwrap(), vec![0..10_000]);
+        assert!(plan_chunks(0, 10_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_chunks_rejects_oversize() {
+        assert!(plan_chunks(MAX_CHUNKS * 100 + 1, 100).is_err());
+        assert!(plan_chunks(MAX_CHUNKS * 100, 100).is_ok());
+        assert!(plan_chunks(1, 0).is_err());
+    }
+
+    #[test]
+    fn test_chunk_limit_from_device() {
+        let defaults = wgpu::Limits::default();
+        assert_eq!(chunk_limit(&defaults), MAX_CHUNK_INSTANCES);
+
+        let small = wgpu::Limits {
+            max_buffer_size: INSTANCE_SIZE * 2000,
+            ..wgpu::Limits::default()
+        };
+        assert_eq!(chunk_limit(&small), 2000);
+    }
+}
@@ -0,0 +1,430 @@
+// MANDATE: CPU frame assembly: culling, stacking order, material batches
+#![deny(warnings)]
+// Public API: FrameBuilder feeds Renderer, tested headlessly below
+#![allow(dead_code)]
+
+use super::pipeline::InstanceData;
+use crate::artboard::{topmost_at, Artboard};
+use crate::document::{stacking_key, Document};
+use crate::layer::LayerId;
+use crate::shape::{MaterialType, Shape};
+use crate::viewport::Viewport;
+use glam::{Mat3, Vec2};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Run of consecutive instances sharing one material.
+/// MANDATE: Range indexes into `Frame::instances`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    pub material: MaterialType,
+    pub instances: Range<u32>,
+}
+
+/// Frame counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Artboard backgrounds drawn beneath the shapes.
+    pub artboards: usize,
+    /// Shapes drawn this frame.
+    pub visible: usize,
+    /// Shapes skipped: outside the viewport, on hidden layers or overflowing.
+    pub culled: usize,
+    /// Draw batches emitted.
+    pub batches: usize,
+}
+
+/// Instances ready for the renderer, bottom to top.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    pub instances: Vec<InstanceData>,
+    pub batches: Vec<Batch>,
+    pub stats: FrameStats,
+}
+
+/// Renderable layer state resolved once per frame.
+/// MANDATE: `transform` includes the view matrix.
+#[derive(Debug, Clone, Copy)]
+struct LayerState {
+    transform: Mat3,
+    opacity: f32,
+}
+
+/// Builds frames from the document, reusing the frame's instance and batch
+/// buffers between frames. The layer map, spatial query and stacking ranks
+/// are still allocated per build.
+/// MANDATE: Pure CPU, output buffers allocate only while the scene grows.
+#[derive(Debug, Default)]
+pub struct FrameBuilder {
+    frame: Frame,
+}
+
+impl FrameBuilder {
+    /// Create builder with empty buffers.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last built frame.
+    /// MANDATE: ≤60 SLOC.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Build the frame seen through viewport: artboard backgrounds in view, then
+    /// shapes in view on visible layers in stacking order, with view × layer
+    /// transforms and layer opacity applied, cut by clipping artboards.
+    /// MANDATE: ≤60 SLOC, bounded by the document's shape count.
+    pub fn build(&mut self, doc: &Document, viewport: &Viewport) -> &Frame {
+        let (min, max) = viewport.visible_bounds();
+        self.build_view(doc, viewport.view_matrix(), (min, max))
+    }
+
+    /// Build the frame for shapes overlapping world `bounds`, with `view`
+    /// mapping world space to clip space (e.g. an export region).
+    /// MANDATE: ≤60 SLOC, bounded by the document's shape count.
+    pub fn build_view(&mut self, doc: &Document, view: Mat3, bounds: (Vec2, Vec2)) -> &Frame {
+        let (min, max) = bounds;
+        // MANDATE: Input validation
+        assert!(min.x <= max.x && min.y <= max.y);
+
+        let layers = renderable_layers(doc, view);
+        let mut shapes: Vec<(&Shape, LayerState)> = doc
+            .query_rect(min, max)
+            .into_iter()
+            .filter_map(|id| doc.shape(id))
+            .filter_map(|shape| layers.get(&shape.layer_id).map(|state| (shape, *state)))
+            .collect();
+
+        // MANDATE: Deterministic sort, bottom to top
+        let ranks = doc.layer_ranks();
+        shapes.sort_by_key(|(shape, _)| stacking_key(&ranks, shape));
+
+        let frame = &mut self.frame;
+        frame.instances.clear();
+        frame.batches.clear();
+        let candidates = clip_candidates(doc, &shapes, bounds);
+        let artboards = push_artboards(frame, doc, &candidates, view, bounds);
+        let mut visible = 0;
+        for (shape, state) in &shapes {
+            // Overflowing transforms (deeply nested scales) are skipped, not drawn
+            let Some(mut instance) = shape.to_instance_data(state.transform, state.opacity) else {
+                continue;
+            };
+            if let Some(rect) = shape_clip(doc, &candidates, shape) {
+                instance.clip = clip_rect(view, rect);
+            }
+            frame.instances.push(instance);
+            push_batch(&mut frame.batches, shape.material);
+            visible += 1;
+        }
+
+        frame.stats = FrameStats {
+            artboards,
+            visible,
+            culled: doc.shape_count() - visible,
+            batches: frame.batches.len(),
+        };
+
+        // MANDATE: Assertions
+        assert!(frame.stats.visible <= doc.shape_count());
+        assert_eq!(
+            frame
+                .batches
+                .last()
+                .map_or(0, |batch| batch.instances.end as usize),
+            frame.instances.len()
+        );
+        frame
+    }
+}
+
+/// Visible leaf layers keyed by ID, with view-space transform and opacity.
+/// MANDATE: ≤60 SLOC, bounded by the layer count.
+fn renderable_layers(doc: &Document, view: Mat3) -> HashMap<LayerId, LayerState> {
+    let tree = doc.layers();
+    let layers: HashMap<LayerId, LayerState> = tree
+        .get_renderable()
+        .into_iter()
+        .map(|layer| {
+            let state = LayerState {
+                transform: view * tree.world_transform(layer.id),
+                opacity: tree.effective_opacity(layer.id),
+            };
+            (layer.id, state)
+        })
+        .collect();
+
+    // MANDATE: Assertions
+    assert!(layers.len() <= tree.len());
+    layers
+}
+
+/// Artboards that may draw or clip this frame: those overlapping world `bounds`
+/// or any drawn shape, so a shape centred on an off-view artboard still resolves
+/// its clip. One index query per frame instead of one per shape.
+/// MANDATE: ≤60 SLOC, bounded by the shape and artboard counts.
+fn clip_candidates<'a>(
+    doc: &'a Document,
+    shapes: &[(&Shape, LayerState)],
+    bounds: (Vec2, Vec2),
+) -> Vec<&'a Artboard> {
+    let (min, max) = shapes
+        .iter()
+        .filter_map(|(shape, _)| doc.world_bounds(shape.id))
+        .fold(bounds, |(lo, hi), (min, max)| (lo.min(min), hi.max(max)));
+    doc.query_artboards(min, max)
+        .into_iter()
+        .filter_map(|id| doc.artboard(id))
+        .collect()
+}
+
+/// World rectangle shape is clipped to, resolved like `Document::shape_clip`
+/// but against the frame's candidate artboards.
+/// MANDATE: ≤60 SLOC, bounded by the candidate count.
+fn shape_clip(doc: &Document, candidates: &[&Artboard], shape: &Shape) -> Option<(Vec2, Vec2)> {
+    let (min, max) = doc.world_bounds(shape.id)?;
+    topmost_at(candidates.iter().copied(), (min + max) * 0.5)?.clip()
+}
+
+/// Push backgrounds of candidate artboards overlapping world `bounds`, bottom
+/// to top. Returns how many were pushed.
+/// MANDATE: ≤60 SLOC, bounded by the document's artboard count.
+fn push_artboards(
+    frame: &mut Frame,
+    doc: &Document,
+    candidates: &[&Artboard],
+    view: Mat3,
+    bounds: (Vec2, Vec2),
+) -> usize {
+    let artboards: Vec<&Artboard> = candidates
+        .iter()
+        .copied()
+        .filter(|a| a.position.cmple(bounds.1).all() && a.max().cmpge(bounds.0).all())
+        .collect();
+    for artboard in &artboards {
+        frame.instances.push(artboard.to_instance_data(view));
+        push_batch(&mut frame.batches, MaterialType::SolidColor);
+    }
+
+    // MANDATE: Assertions
+    assert!(artboards.len() <= doc.artboard_count());
+    artboards.len()
+}
+
+/// Clip-space rectangle of a world rectangle; `view` scales and translates.
+/// MANDATE: ≤60 SLOC.
+fn clip_rect(view: Mat3, (min, max): (Vec2, Vec2)) -> [f32; 4] {
+    let a = view.transform_point2(min);
+    let b = view.transform_point2(max);
+    let (low, high) = (a.min(b), a.max(b));
+    [low.x, low.y, high.x, high.y]
+}
+
+/// Extend the last batch or open a new one for material.
+/// MANDATE: ≤60 SLOC.
+fn push_batch(batches: &mut Vec<Batch>, material: MaterialType) {
+    match batches.last_mut() {
+        Some(batch) if batch.material == material => batch.instances.end += 1,
+        last => {
+            let start = last.map_or(0, |batch| batch.instances.end);
+            batches.push(Batch {
+                material,
+                instances: start..start + 1,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene() -> (Document, LayerId, LayerId) {
+        let mut doc = Document::new();
+        let bottom = doc.add_layer("Bottom".into()).unwrap();
+        let top = doc.add_layer("Top".into()).unwrap();
+        (doc, bottom, top)
+    }
+
+    fn small(doc: &mut Document, layer: LayerId, x: f32) -> u64 {
+        doc.add_shape(layer, Vec2::new(x, 0.0), Vec2::splat(0.1))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_build_culls_offscreen_and_hidden() {
+        let (mut doc, bottom, top) = scene();
+        small(&mut doc, bottom, 0.0);
+        small(&mut doc, bottom, 50.0);
+        small(&mut doc, top, 0.2);
+        doc.set_layer_visible(top, false).unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build(&doc, &Viewport::new(800.0, 600.0));
+        assert_eq!(
+            frame.stats,
+            FrameStats {
+                artboards: 0,
+                visible: 1,
+                culled: 2,
+                batches: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_draws_artboards_first() {
+        let (mut doc, bottom, _) = scene();
+        small(&mut doc, bottom, 0.0);
+        doc.add_artboard("Board".into(), Vec2::splat(-0.5), Vec2::splat(1.0))
+            .unwrap();
+        doc.add_artboard("Far".into(), Vec2::splat(500.0), Vec2::splat(1.0))
+            .unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build(&doc, &Viewport::new(800.0, 600.0));
+        assert_eq!((frame.stats.artboards, frame.stats.visible), (1, 1));
+        assert_eq!(frame.instances.len(), 2);
+        assert_eq!(frame.batches.len(), 1);
+        assert_eq!(frame.instances[0].transform_2[0], -0.5);
+        assert_eq!(frame.instances[0].color, [1.0; 4]);
+    }
+
+    #[test]
+    fn test_build_clips_to_artboard() {
+        let (mut doc, bottom, _) = scene();
+        let shape = small(&mut doc, bottom, 0.0);
+        doc.add_artboard("Board".into(), Vec2::ZERO, Vec2::splat(0.12))
+            .unwrap();
+        let view = Mat3::from_scale(Vec2::splat(2.0));
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build_view(&doc, view, (Vec2::splat(-1.0), Vec2::ONE));
+        assert_eq!(frame.instances[0].clip, InstanceData::NO_CLIP);
+        assert_eq!(frame.instances[1].clip, [0.0, 0.0, 0.24, 0.24]);
+
+        let mut board = doc.artboard(1).unwrap().clone();
+        board.clip_content = false;
+        doc.replace_artboard(board).unwrap();
+        let frame = builder.build_view(&doc, view, (Vec2::splat(-1.0), Vec2::ONE));
+        assert_eq!(frame.instances[1].clip, InstanceData::NO_CLIP);
+        assert_eq!(doc.shape_clip(shape), None);
+    }
+
+    #[test]
+    fn test_build_clips_to_artboard_out_of_view() {
+        let (mut doc, bottom, _) = scene();
+        doc.add_shape(bottom, Vec2::splat(-1.0), Vec2::splat(30.0))
+            .unwrap();
+        doc.add_artboard("Far".into(), Vec2::splat(10.0), Vec2::splat(10.0))
+            .unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build_view(&doc, Mat3::IDENTITY, (Vec2::splat(-1.0), Vec2::ONE));
+        assert_eq!(frame.stats.artboards, 0);
+        assert_eq!(frame.instances[0].clip, [10.0, 10.0, 20.0, 20.0]);
+    }
+
+    #[test]
+    fn test_build_orders_bottom_to_top() {
+        let (mut doc, bottom, top) = scene();
+        small(&mut doc, top, 0.0);
+        small(&mut doc, bottom, 0.3);
+        let raised = small(&mut doc, bottom, 0.6);
+        doc.set_shape_z_order(raised, 5).unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build(&doc, &Viewport::new(800.0, 600.0));
+        let xs: Vec<f32> = frame.instances.iter().map(|i| i.transform_2[0]).collect();
+        assert_eq!(xs, vec![0.3, 0.6, 0.0]);
+    }
+
+    #[test]
+    fn test_build_splits_material_runs() {
+        let (mut doc, bottom, top) = scene();
+        small(&mut doc, bottom, 0.0);
+        let textured = small(&mut doc, bottom, 0.2);
+        small(&mut doc, top, 0.4);
+        doc.set_shape_material(textured, MaterialType::Textured)
+            .unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let batches = &builder.build(&doc, &Viewport::new(800.0, 600.0)).batches;
+        let materials: Vec<MaterialType> = batches.iter().map(|b| b.material).collect();
+        assert_eq!(
+            materials,
+            vec![
+                MaterialType::SolidColor,
+                MaterialType::Textured,
+                MaterialType::SolidColor
+            ]
+        );
+        assert_eq!(batches[2].instances, 2..3);
+    }
+
+    #[test]
+    fn test_build_applies_layer_opacity() {
+        let (mut doc, bottom, _) = scene();
+        small(&mut doc, bottom, 0.0);
+        doc.set_layer_opacity(bottom, 0.5).unwrap();
+
+        let mut builder = FrameBuilder::new();
+        let frame = builder.build(&doc, &Viewport::new(800.0, 600.0));
+        assert_eq!(frame.instances[0].color, [1.0, 1.0, 1.0, 0.5]);
+        assert_eq!(builder.frame().stats.visible, 1);
+    }
+
+    #[test]
+    fn test_build_composes_view_group_layer() {
+        let (mut doc, bottom, _) = scene();
+        let group = doc.add_group("Group".into()).unwrap();
+        doc.set_layer_parent(bottom, Some(group)).unwrap();
+        doc.set_layer_transform(group, Mat3::from_translation(Vec2::new(0.2, 0.0)))
+            .unwrap();
+        doc.set_layer_transform(bottom, Mat3::from_scale(Vec2::splat(2.0)))
+            .unwrap();
+        small(&mut doc, bottom, 0.1);
+        let mut viewport = Viewport::new(800.0, 600.0);
+        viewport.zoom = 2.0;
+        viewport.offset = Vec2::new(0.1, 0.0);
+
+        let mut builder = FrameBuilder::new();
+        let instance = builder.build(&doc, &viewport).instances[0];
+        let columns = [
+            instance.transform_0,
+            instance.transform_1,
+            instance.transform_2,
+        ];
+        let expected = [
+            [0.4, 0.0, 0.0, 0.0],
+            [0.0, 0.4, 0.0, 0.0],
+            [0.6, 0.0, 1.0, 0.0],
+        ];
+        for (column, want) in columns.iter().zip(expected) {
+            assert!(glam::Vec4::from(*column).abs_diff_eq(glam::Vec4::from(want), 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_build_skips_overflowing_shapes() {
+        let (mut doc, bottom, top) = scene();
+        let mut parent = bottom;
+        for _ in 0..10 {
+            let group = doc.add_group("Group".into()).unwrap();
+            doc.set_layer_transform(group, Mat3::from_scale(Vec2::splat(1e4)))
+                .unwrap();
+            doc.set_layer_parent(parent, Some(group)).unwrap();
+            parent = group;
+        }
+        small(&mut doc, bottom, 0.0);
+        small(&mut doc, top, 0.0);
+
+        let mut builder = FrameBuilder::new();
+        let everywhere = (Vec2::splat(-f32::MAX), Vec2::splat(f32::MAX));
+        let frame = builder.build_view(&doc, Mat3::IDENTITY, everywhere);
+        assert_eq!((frame.stats.visible, frame.stats.culled), (1, 1));
+        assert_eq!(frame.instances.len(), 1);
+    }
+}
@@ -3,13 +3,19 @@
 // Public API: Renderer requires wgpu device, tested via integration tests
 #![allow(dead_code)]
 
-use super::geometry::{create_index_buffer, create_vertex_buffer, QUAD_INDICES};
-use super::pipeline::{create_pipeline, create_shader, InstanceData};
-use wgpu::util::DeviceExt;
-
-/// Maximum instances per batch.
-/// MANDATE: Bounded allocation.
-const MAX_INSTANCES: usize = 10_000;
+use super::pass::{open_device, QuadPass};
+use super::pipeline::InstanceData;
+use super::surface::{surface_config, surface_size};
+use crate::viewport::Viewport;
+
+/// Background colour each frame is cleared to.
+/// MANDATE: Fixed constant.
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.1,
+    b: 0.1,
+    a: 1.0,
+};
 
 /// GPU renderer state.
 /// MANDATE: All fields initialized at setup.
@@ -18,15 +24,19 @@ pub struct Renderer {
     queue: wgpu::Queue,
     surface: wgpu::Surface<'static>,
     config: wgpu::SurfaceConfiguration,
-    pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
+    quads: QuadPass,
+    viewport: Viewport,
 }
 
 impl Renderer {
-    /// Initialize renderer.
+    /// Initialize renderer for a window of `width` × `height` physical pixels.
     /// MANDATE: ≤60 SLOC, all resources created.
-    pub async fn new<W>(window: W) -> Result<Self, String>
+    pub async fn new<W>(
+        window: W,
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    ) -> Result<Self, String>
     where
         W: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle + Send + Sync + 'static,
     {
@@ -48,109 +58,97 @@ impl Renderer {
             .await
             .ok_or("Failed to find adapter")?;
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Main Device"),
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    memory_hints: Default::default(),
-                },
-                None,
-            )
-            .await
-            .map_err(|e| e.to_string())?;
+        let (device, queue) = open_device(&adapter).await?;
 
-        // MANDATE: Default window dimensions
-        let width = 1200;
-        let height = 800;
-
-        let config = surface
-            .get_default_config(&adapter, width, height)
-            .ok_or("Surface incompatible")?;
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let size = surface_size(width, height, scale_factor, max_dimension)?
+            .ok_or("Window has no drawable area")?;
+        let config = surface_config(&surface.get_capabilities(&adapter), size)?;
 
         surface.configure(&device, &config);
 
-        let shader = create_shader(&device);
-        let pipeline = create_pipeline(&device, &shader, config.format);
-        let vertex_buffer = create_vertex_buffer(&device);
-        let index_buffer = create_index_buffer(&device);
+        let quads = QuadPass::new(&device, config.format)?;
 
         Ok(Self {
             device,
             queue,
             surface,
             config,
-            pipeline,
-            vertex_buffer,
-            index_buffer,
+            quads,
+            viewport: Viewport::new(size.logical_width, size.logical_height),
         })
     }
 
-    /// Render instances.
-    /// MANDATE: ≤60 SLOC, bounded loop.
-    pub fn render(&self, instances: &[InstanceData]) -> Result<(), String> {
-        // MANDATE: Bounded input validation
-        assert!(instances.len() <= MAX_INSTANCES);
+    /// Viewport matching the surface's logical size.
+    /// MANDATE: ≤60 SLOC.
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    /// Mutable viewport for pan and zoom.
+    /// MANDATE: ≤60 SLOC.
+    pub fn viewport_mut(&mut self) -> &mut Viewport {
+        &mut self.viewport
+    }
 
-        if instances.is_empty() {
+    /// Reconfigure for a window of `width` × `height` physical pixels at
+    /// `scale_factor`, keeping the viewport's screen size in logical points.
+    /// MANDATE: ≤60 SLOC, empty (minimized) windows are ignored until restored.
+    pub fn resize(&mut self, width: u32, height: u32, scale_factor: f64) -> Result<(), String> {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let Some(size) = surface_size(width, height, scale_factor, max_dimension)? else {
             return Ok(());
+        };
+
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        self.viewport
+            .resize(size.logical_width, size.logical_height);
+
+        // MANDATE: Assertions
+        assert!(self.config.width > 0 && self.config.height > 0);
+        assert_eq!(self.viewport.screen_width, size.logical_width);
+        Ok(())
+    }
+
+    /// Next surface texture, reconfiguring once if the surface was lost or outdated.
+    /// MANDATE: ≤60 SLOC, `None` skips the frame; only out-of-memory is an error.
+    fn acquire_frame(&mut self) -> Result<Option<wgpu::SurfaceTexture>, String> {
+        match self.surface.get_current_texture() {
+            Ok(frame) => return Ok(Some(frame)),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(None),
+            Err(error) => return Err(error.to_string()),
         }
 
-        let instance_buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(instances),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-
-        let output = self
-            .surface
-            .get_current_texture()
-            .map_err(|e| e.to_string())?;
+        // Still unusable after reconfiguring: skip, the next frame retries
+        match self.surface.get_current_texture() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                Err(wgpu::SurfaceError::OutOfMemory.to_string())
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Render instances bottom to top; an empty list clears the window.
+    /// MANDATE: ≤60 SLOC, oversize input is an error.
+    pub fn render(&mut self, instances: &[InstanceData]) -> Result<(), String> {
+        let Some(output) = self.acquire_frame()? else {
+            return Ok(());
+        };
 
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.1,
-                            b: 0.1,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..QUAD_INDICES.len() as u32, 0, 0..instances.len() as u32);
-        }
+        self.quads
+            .draw(&self.device, &self.queue, &view, instances, CLEAR_COLOR)?;
 
-        self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
-
         Ok(())
     }
 }
@@ -1,6 +1,11 @@
 // MANDATE: Render module exports
 #![deny(warnings)]
 
+pub mod buffer;
+pub mod frame;
 pub mod geometry;
 pub mod instanced;
+pub mod offscreen;
+pub mod pass;
 pub mod pipeline;
+pub mod surface;
@@ -0,0 +1,336 @@
+// MANDATE: Headless offscreen rendering with CPU readback
+#![deny(warnings)]
+// Public API: OffscreenRenderer used by tests and export
+#![allow(dead_code)]
+
+use super::pass::{open_device, QuadPass};
+use super::pipeline::InstanceData;
+
+/// Colour format of offscreen targets; readback yields sRGB-encoded RGBA8.
+/// MANDATE: Fixed format.
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Bytes per RGBA8 pixel.
+/// MANDATE: Fixed layout.
+const PIXEL_SIZE: u32 = 4;
+
+/// Renders the window pipeline into a texture and reads it back, with no
+/// surface, so it runs in CI and on software (fallback) adapters.
+/// MANDATE: All fields initialized at setup, target size changed only by `resize`.
+pub struct OffscreenRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    quads: QuadPass,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenRenderer {
+    /// Initialize renderer with a `width` × `height` pixel target.
+    /// MANDATE: ≤60 SLOC, validated size, all resources created.
+    pub async fn new(width: u32, height: u32) -> Result<Self, String> {
+        let adapter = find_adapter().await?;
+        let (device, queue) = open_device(&adapter).await?;
+        // MANDATE: Input validation
+        check_size(&device, width, height)?;
+
+        let texture = create_target(&device, width, height);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let quads = QuadPass::new(&device, OFFSCREEN_FORMAT)?;
+
+        Ok(Self {
+            device,
+            queue,
+            quads,
+            texture,
+            view,
+            width,
+            height,
+        })
+    }
+
+    /// Target size in pixels.
+    /// MANDATE: ≤60 SLOC.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Replace the target with a `width` × `height` one, keeping the device and
+    /// pipeline; does nothing at the current size.
+    /// MANDATE: ≤60 SLOC, validated size.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), String> {
+        // MANDATE: Input validation
+        check_size(&self.device, width, height)?;
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        self.texture = create_target(&self.device, width, height);
+        self.view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Render instances over `clear` and return tightly packed RGBA8 rows, top row first.
+    /// MANDATE: ≤60 SLOC, oversize input is an error.
+    pub fn render(
+        &mut self,
+        instances: &[InstanceData],
+        clear: wgpu::Color,
+    ) -> Result<Vec<u8>, String> {
+        self.quads
+            .draw(&self.device, &self.queue, &self.view, instances, clear)?;
+        self.read_pixels()
+    }
+
+    /// Copy the target into a mapped buffer and strip the row padding.
+    /// MANDATE: ≤60 SLOC, blocks until the GPU finishes.
+    fn read_pixels(&self) -> Result<Vec<u8>, String> {
+        let padded = padded_row(self.width);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: u64::from(padded) * u64::from(self.height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // Receiver outlives the poll below; a failed send only means it was dropped
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let pixels = unpad_rows(&slice.get_mapped_range(), self.width, self.height)?;
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+/// Error unless a `width` × `height` target is non-empty and within the
+/// device's texture limit.
+/// MANDATE: ≤60 SLOC.
+fn check_size(device: &wgpu::Device, width: u32, height: u32) -> Result<(), String> {
+    let max_dimension = device.limits().max_texture_dimension_2d;
+    if width == 0 || height == 0 {
+        return Err("offscreen target must not be empty".into());
+    }
+    if width > max_dimension || height > max_dimension {
+        return Err(format!(
+            "offscreen target {}x{} exceeds device limit {}",
+            width, height, max_dimension
+        ));
+    }
+    Ok(())
+}
+
+/// Adapter for offscreen work: a hardware one if present, else the software fallback.
+/// MANDATE: ≤60 SLOC, at most two requests.
+async fn find_adapter() -> Result<wgpu::Adapter, String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await;
+        if let Some(adapter) = adapter {
+            return Ok(adapter);
+        }
+    }
+    Err("No GPU or software adapter available".into())
+}
+
+/// Create the render target texture.
+/// MANDATE: ≤60 SLOC.
+fn create_target(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    // MANDATE: Assertions
+    assert!(width > 0 && height > 0);
+
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Bytes per row of a texture copy: width × 4 rounded up to wgpu's 256-byte alignment.
+/// MANDATE: ≤60 SLOC.
+pub fn padded_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded = (width * PIXEL_SIZE).div_ceil(align) * align;
+
+    // MANDATE: Assertions
+    assert!(padded >= width * PIXEL_SIZE);
+    assert_eq!(padded % align, 0);
+    padded
+}
+
+/// Strip the padding after each row of a `width` × `height` copy.
+/// MANDATE: ≤60 SLOC, bounded by height, error on short data.
+pub fn unpad_rows(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let padded = padded_row(width) as usize;
+    let row = (width * PIXEL_SIZE) as usize;
+    // MANDATE: Input validation
+    if data.len() < padded * height as usize {
+        return Err(format!(
+            "readback has {} bytes, expected {}",
+            data.len(),
+            padded * height as usize
+        ));
+    }
+
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for chunk in data.chunks(padded).take(height as usize) {
+        pixels.extend_from_slice(&chunk[..row]);
+    }
+
+    // MANDATE: Assertions
+    assert_eq!(pixels.len(), row * height as usize);
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::buffer::MAX_CHUNK_INSTANCES;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    /// Instance covering clip-space x in [x0, x1], full height.
+    fn band(x0: f32, x1: f32) -> InstanceData {
+        InstanceData {
+            transform_0: [x1 - x0, 0.0, 0.0, 0.0],
+            transform_1: [0.0, 2.0, 0.0, 0.0],
+            transform_2: [x0, -1.0, 1.0, 0.0],
+            color: [1.0, 0.0, 0.0, 1.0],
+            clip: InstanceData::NO_CLIP,
+        }
+    }
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * width + x) * PIXEL_SIZE) as usize;
+        [0, 1, 2, 3].map(|i| pixels[start + i])
+    }
+
+    #[test]
+    fn test_padded_row() {
+        assert_eq!(padded_row(1), 256);
+        assert_eq!(padded_row(64), 256);
+        assert_eq!(padded_row(65), 512);
+    }
+
+    #[test]
+    fn test_unpad_rows() {
+        let mut data = vec![0u8; 512];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[256..264].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+
+        let pixels = unpad_rows(&data, 2, 2).unwrap();
+        assert_eq!(pixels, (1..=16).collect::<Vec<u8>>());
+        assert!(unpad_rows(&data[..300], 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_offscreen_pixels() {
+        let mut renderer = pollster::block_on(OffscreenRenderer::new(8, 4)).unwrap();
+
+        let pixels = renderer
+            .render(&[band(-1.0, 0.0)], wgpu::Color::BLACK)
+            .unwrap();
+        assert_eq!(pixels.len(), 8 * 4 * 4);
+        assert_eq!(pixel(&pixels, 8, 0, 0), RED);
+        assert_eq!(pixel(&pixels, 8, 3, 3), RED);
+        assert_eq!(pixel(&pixels, 8, 4, 0), BLACK);
+
+        // Clipped to the top half of the viewport (y-up clip space)
+        let mut clipped = band(-1.0, 0.0);
+        clipped.clip = [-1.0, 0.0, 1.0, 1.0];
+        let pixels = renderer.render(&[clipped], wgpu::Color::BLACK).unwrap();
+        assert_eq!(pixel(&pixels, 8, 0, 0), RED);
+        assert_eq!(pixel(&pixels, 8, 0, 3), BLACK);
+
+        let cleared = renderer.render(&[], wgpu::Color::BLACK).unwrap();
+        assert_eq!(pixel(&cleared, 8, 0, 0), BLACK);
+
+        // Same device and pipeline, smaller target
+        renderer.resize(4, 2).unwrap();
+        assert_eq!(renderer.size(), (4, 2));
+        let pixels = renderer
+            .render(&[band(-1.0, 0.0)], wgpu::Color::BLACK)
+            .unwrap();
+        assert_eq!(pixels.len(), 4 * 2 * 4);
+        assert_eq!(pixel(&pixels, 4, 1, 1), RED);
+        assert_eq!(pixel(&pixels, 4, 2, 0), BLACK);
+        assert!(renderer.resize(0, 2).is_err());
+    }
+
+    #[test]
+    fn test_offscreen_chunked_frames() {
+        let mut renderer = pollster::block_on(OffscreenRenderer::new(8, 4)).unwrap();
+        // The right band lands alone in the second chunk
+        let mut instances = vec![band(-1.0, 0.0); MAX_CHUNK_INSTANCES];
+        instances.push(band(0.0, 1.0));
+
+        let pixels = renderer.render(&instances, wgpu::Color::BLACK).unwrap();
+        assert_eq!(pixel(&pixels, 8, 0, 0), RED);
+        assert_eq!(pixel(&pixels, 8, 7, 0), RED);
+
+        // Each chunk is diffed against its own last upload, not the previous chunk
+        instances[MAX_CHUNK_INSTANCES].color = [0.0, 0.0, 0.0, 1.0];
+        let pixels = renderer.render(&instances, wgpu::Color::WHITE).unwrap();
+        assert_eq!(pixel(&pixels, 8, 0, 0), RED);
+        assert_eq!(pixel(&pixels, 8, 7, 0), BLACK);
+        instances[MAX_CHUNK_INSTANCES].color = [1.0, 0.0, 0.0, 1.0];
+        let pixels = renderer.render(&instances, wgpu::Color::BLACK).unwrap();
+        assert_eq!(pixel(&pixels, 8, 7, 0), RED);
+    }
+}
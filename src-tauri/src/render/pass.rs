@@ -0,0 +1,129 @@
+// MANDATE: Quad draw pass shared by window and offscreen targets
+#![deny(warnings)]
+// Public API: QuadPass used by renderers, tested via integration tests
+#![allow(dead_code)]
+
+use super::buffer::{chunk_limit, plan_chunks, InstanceBuffer};
+use super::geometry::{create_index_buffer, create_vertex_buffer, QUAD_INDICES};
+use super::pipeline::{create_pipeline, create_shader, InstanceData};
+
+/// Open the device and queue used for rendering.
+/// MANDATE: ≤60 SLOC, default limits so software adapters qualify.
+pub async fn open_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue), String> {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Main Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: Default::default(),
+            },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pipeline, quad geometry and per-chunk instance buffers for drawing into any target
+/// of one colour format.
+/// MANDATE: All fields initialized at setup.
+pub struct QuadPass {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instances: InstanceBuffer,
+}
+
+impl QuadPass {
+    /// Create pass rendering into targets of `format`.
+    /// MANDATE: ≤60 SLOC, all resources created.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self, String> {
+        let shader = create_shader(device);
+        Ok(Self {
+            pipeline: create_pipeline(device, &shader, format),
+            vertex_buffer: create_vertex_buffer(device),
+            index_buffer: create_index_buffer(device),
+            instances: InstanceBuffer::new(device, chunk_limit(&device.limits()))?,
+        })
+    }
+
+    /// Clear target to `clear` and draw instances bottom to top, in as many
+    /// chunks as the instance buffer needs.
+    /// MANDATE: ≤60 SLOC, bounded chunk loop, oversize input is an error.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        instances: &[InstanceData],
+        clear: wgpu::Color,
+    ) -> Result<(), String> {
+        // MANDATE: Input validation
+        let mut chunks = plan_chunks(instances.len(), self.instances.limit())?;
+        if chunks.is_empty() {
+            // Nothing to draw still clears the target
+            chunks.push(0..0);
+        }
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            // Only the first chunk clears; later ones draw on top, keeping z-order
+            let load = if index == 0 {
+                wgpu::LoadOp::Clear(clear)
+            } else {
+                wgpu::LoadOp::Load
+            };
+            let count = chunk.len() as u32;
+            self.instances
+                .upload(device, queue, index, &instances[chunk])?;
+            self.draw_chunk(device, queue, target, index, count, load);
+        }
+        Ok(())
+    }
+
+    /// Encode and submit one pass drawing the instances uploaded for `chunk`.
+    /// MANDATE: ≤60 SLOC, one submission per chunk so buffer writes stay ordered.
+    fn draw_chunk(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        chunk: usize,
+        count: u32,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        // MANDATE: Assertions
+        assert!(count as usize <= self.instances.limit());
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if count > 0 {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, self.instances.buffer(chunk).slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..QUAD_INDICES.len() as u32, 0, 0..count);
+            }
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
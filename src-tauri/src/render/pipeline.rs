@@ -5,7 +5,7 @@
 
 use super::geometry::Vertex;
 
-/// Instance data for transform and color.
+/// Instance data for transform, color and clip rectangle.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceData {
@@ -13,9 +13,14 @@ pub struct InstanceData {
     pub transform_1: [f32; 4],
     pub transform_2: [f32; 4],
     pub color: [f32; 4],
+    /// Clip-space `[min x, min y, max x, max y]`; fragments outside are discarded.
+    pub clip: [f32; 4],
 }
 
 impl InstanceData {
+    /// Clip rectangle that cuts nothing.
+    pub const NO_CLIP: [f32; 4] = [f32::MIN, f32::MIN, f32::MAX, f32::MAX];
+
     /// Instance buffer layout descriptor.
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -42,6 +47,11 @@ impl InstanceData {
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 4]>() * 4) as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -70,7 +80,7 @@ pub fn create_pipeline(
     format: wgpu::TextureFormat,
 ) -> wgpu::RenderPipeline {
     // MANDATE: Assertions on inputs
-    assert_eq!(format, wgpu::TextureFormat::Bgra8UnormSrgb);
+    assert!(format.is_srgb());
 
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout"),
@@ -123,6 +133,6 @@ mod tests {
 
     #[test]
     fn test_instance_data_size() {
-        assert_eq!(std::mem::size_of::<InstanceData>(), 64);
+        assert_eq!(std::mem::size_of::<InstanceData>(), 80);
     }
 }
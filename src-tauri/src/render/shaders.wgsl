@@ -10,11 +10,14 @@ struct InstanceInput {
     @location(2) transform_1: vec4<f32>,
     @location(3) transform_2: vec4<f32>,
     @location(4) color: vec4<f32>,
+    @location(5) clip: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) position: vec2<f32>,
+    @location(2) @interpolate(flat) clip: vec4<f32>,
 }
 
 @vertex
@@ -37,11 +40,17 @@ fn vs_main(
     // Convert to clip space [-1, 1]
     out.clip_position = vec4<f32>(transformed.xy, 0.0, 1.0);
     out.color = instance.color;
+    out.position = transformed.xy;
+    out.clip = instance.clip;
 
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Cut at the instance's clip rectangle, e.g. a clipping artboard
+    if (any(in.position < in.clip.xy) || any(in.position > in.clip.zw)) {
+        discard;
+    }
     return in.color;
 }
@@ -0,0 +1,129 @@
+// MANDATE: Surface configuration: format choice, DPI-aware sizing
+#![deny(warnings)]
+// Public API: Functions used by renderer, tested without a GPU
+#![allow(dead_code)]
+
+/// Surface size for a window, in physical pixels and logical points.
+/// MANDATE: Plain data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceSize {
+    /// Physical pixels, as configured on the surface.
+    pub width: u32,
+    pub height: u32,
+    /// Logical size in screen points, as seen by `Viewport`.
+    pub logical_width: f32,
+    pub logical_height: f32,
+}
+
+/// Size for physical `width` × `height` pixels at `scale_factor`, clamped to
+/// `max_dimension`. `None` for an empty (e.g. minimized) window.
+/// MANDATE: ≤60 SLOC, validated inputs.
+pub fn surface_size(
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    max_dimension: u32,
+) -> Result<Option<SurfaceSize>, String> {
+    // MANDATE: Input validation
+    if !scale_factor.is_finite() || scale_factor <= 0.0 {
+        return Err(format!("invalid scale factor {}", scale_factor));
+    }
+    if max_dimension == 0 {
+        return Err("max surface dimension must be positive".into());
+    }
+    if width == 0 || height == 0 {
+        return Ok(None);
+    }
+
+    let width = width.min(max_dimension);
+    let height = height.min(max_dimension);
+    let size = SurfaceSize {
+        width,
+        height,
+        logical_width: (f64::from(width) / scale_factor) as f32,
+        logical_height: (f64::from(height) / scale_factor) as f32,
+    };
+
+    // MANDATE: Assertions
+    assert!(size.logical_width > 0.0 && size.logical_height > 0.0);
+    Ok(Some(size))
+}
+
+/// First sRGB format the surface supports, in the adapter's preference order.
+/// MANDATE: ≤60 SLOC, error when none is sRGB.
+pub fn pick_format(formats: &[wgpu::TextureFormat]) -> Result<wgpu::TextureFormat, String> {
+    let format = formats
+        .iter()
+        .copied()
+        .find(|format| format.is_srgb())
+        .ok_or_else(|| format!("no sRGB surface format among {:?}", formats))?;
+
+    // MANDATE: Assertions
+    assert!(format.is_srgb());
+    Ok(format)
+}
+
+/// Surface configuration for size using the surface's capabilities.
+/// MANDATE: ≤60 SLOC, Fifo present mode is always supported.
+pub fn surface_config(
+    capabilities: &wgpu::SurfaceCapabilities,
+    size: SurfaceSize,
+) -> Result<wgpu::SurfaceConfiguration, String> {
+    let format = pick_format(&capabilities.formats)?;
+    let alpha_mode = capabilities
+        .alpha_modes
+        .first()
+        .copied()
+        .unwrap_or(wgpu::CompositeAlphaMode::Auto);
+
+    // MANDATE: Assertions
+    assert!(size.width > 0 && size.height > 0);
+    Ok(wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: size.width,
+        height: size.height,
+        present_mode: wgpu::PresentMode::Fifo,
+        desired_maximum_frame_latency: 2,
+        alpha_mode,
+        view_formats: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn test_surface_size_scales_to_logical() {
+        let size = surface_size(2400, 1600, 2.0, 8192).unwrap().unwrap();
+        assert_eq!((size.width, size.height), (2400, 1600));
+        assert_eq!((size.logical_width, size.logical_height), (1200.0, 800.0));
+    }
+
+    #[test]
+    fn test_surface_size_edge_cases() {
+        assert_eq!(surface_size(0, 600, 1.0, 8192).unwrap(), None);
+        let clamped = surface_size(20_000, 600, 1.0, 8192).unwrap().unwrap();
+        assert_eq!(clamped.width, 8192);
+        assert!(surface_size(800, 600, 0.0, 8192).is_err());
+        assert!(surface_size(800, 600, f64::NAN, 8192).is_err());
+    }
+
+    #[test]
+    fn test_pick_format_prefers_srgb() {
+        let formats = [TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb];
+        assert_eq!(
+            pick_format(&formats).unwrap(),
+            TextureFormat::Rgba8UnormSrgb
+        );
+
+        let formats = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba8UnormSrgb];
+        assert_eq!(
+            pick_format(&formats).unwrap(),
+            TextureFormat::Bgra8UnormSrgb
+        );
+        assert!(pick_format(&[TextureFormat::Rgba16Float]).is_err());
+    }
+}
@@ -0,0 +1,611 @@
+// MANDATE: Exact hit testing and marquee selection over the spatial index
+#![deny(warnings)]
+
+use crate::document::Document;
+use crate::layer::LayerId;
+use crate::shape::{Shape, ShapeId};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Tolerance so points on an edge count as inside.
+/// MANDATE: Bounded float comparison.
+const EDGE_EPSILON: f32 = 1e-4;
+
+/// Maximum polygon vertices for SAT tests.
+/// MANDATE: Bounded loops.
+const MAX_VERTICES: usize = 64;
+
+/// Maximum selected objects.
+/// MANDATE: Bounded collection, matches the document shape limit.
+pub const MAX_SELECTION: usize = 100_000;
+
+/// Selectable object.
+/// MANDATE: Deterministic ordering and hashing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum SelectionItem {
+    Shape(ShapeId),
+    Layer(LayerId),
+}
+
+/// World-space axis-aligned bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Selection as sent to the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectionState {
+    pub items: Vec<SelectionItem>,
+    pub bounds: Option<Bounds>,
+}
+
+/// Ordered set of selected shapes and layers, in selection order.
+/// MANDATE: Bounded collection, order and membership kept in sync.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    items: Vec<SelectionItem>,
+    members: HashSet<SelectionItem>,
+}
+
+/// How a marquee rectangle selects shapes.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarqueeMode {
+    /// Shapes overlapping the rectangle at all.
+    #[default]
+    Touching,
+    /// Shapes lying entirely inside the rectangle.
+    Contained,
+}
+
+/// Pickable shapes whose exact outline contains point, topmost first.
+/// Parts cut off by a clipping artboard are not hit.
+/// MANDATE: ≤60 SLOC, R-tree broad phase then exact test.
+pub fn hit_test(doc: &Document, point: Vec2) -> Vec<ShapeId> {
+    // MANDATE: Input validation
+    if !point.is_finite() {
+        return Vec::new();
+    }
+
+    doc.hit_test(point)
+        .into_iter()
+        .filter_map(|id| doc.shape(id))
+        .filter(|shape| is_pickable(doc, shape))
+        .filter(|shape| contains_point(&world_corners(doc, shape), point))
+        .filter(|shape| {
+            doc.shape_clip(shape.id)
+                .is_none_or(|(min, max)| point.cmpge(min).all() && point.cmple(max).all())
+        })
+        .map(|shape| shape.id)
+        .collect()
+}
+
+/// Topmost pickable shape at point.
+/// MANDATE: ≤60 SLOC.
+pub fn pick(doc: &Document, point: Vec2) -> Option<ShapeId> {
+    hit_test(doc, point).first().copied()
+}
+
+/// Pickable shapes selected by a world-space rectangle, sorted by ID.
+/// Only the part left visible by a clipping artboard is judged.
+/// MANDATE: ≤60 SLOC, R-tree broad phase then exact test.
+pub fn marquee(doc: &Document, min: Vec2, max: Vec2, mode: MarqueeMode) -> Vec<ShapeId> {
+    // MANDATE: Input validation (SpatialIndex::query_rect asserts this)
+    if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y {
+        return Vec::new();
+    }
+
+    let rect = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+    doc.query_rect(min, max)
+        .into_iter()
+        .filter_map(|id| doc.shape(id))
+        .filter(|shape| is_pickable(doc, shape))
+        .filter(|shape| {
+            let corners = world_corners(doc, shape);
+            let outline = match doc.shape_clip(shape.id) {
+                Some((clip_min, clip_max)) => clip_to_rect(&corners, clip_min, clip_max),
+                None => corners.to_vec(),
+            };
+            // Fully clipped shapes are invisible, so never selected
+            outline.len() >= 3
+                && match mode {
+                    MarqueeMode::Touching => polygons_overlap(&outline, &rect),
+                    MarqueeMode::Contained => outline.iter().all(|c| contains_point(&rect, *c)),
+                }
+        })
+        .map(|shape| shape.id)
+        .collect()
+}
+
+impl Selection {
+    /// Create empty selection.
+    /// MANDATE: ≤60 SLOC.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selected items in selection order.
+    /// MANDATE: ≤60 SLOC.
+    pub fn items(&self) -> &[SelectionItem] {
+        &self.items
+    }
+
+    /// Check item is selected.
+    /// MANDATE: ≤60 SLOC.
+    pub fn contains(&self, item: SelectionItem) -> bool {
+        self.members.contains(&item)
+    }
+
+    /// Count selected items.
+    /// MANDATE: ≤60 SLOC.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check if nothing is selected.
+    /// MANDATE: ≤60 SLOC.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Selected shape IDs in selection order.
+    /// MANDATE: ≤60 SLOC.
+    pub fn shapes(&self) -> Vec<ShapeId> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                SelectionItem::Shape(id) => Some(*id),
+                SelectionItem::Layer(_) => None,
+            })
+            .collect()
+    }
+
+    /// Selected layer IDs in selection order.
+    /// MANDATE: ≤60 SLOC.
+    pub fn layers(&self) -> Vec<LayerId> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                SelectionItem::Layer(id) => Some(*id),
+                SelectionItem::Shape(_) => None,
+            })
+            .collect()
+    }
+
+    /// Append items not already selected. Returns whether anything changed.
+    /// MANDATE: ≤60 SLOC, bounded by MAX_SELECTION.
+    pub fn add(&mut self, items: &[SelectionItem]) -> bool {
+        let before = self.items.len();
+        for item in items {
+            if self.items.len() >= MAX_SELECTION {
+                break;
+            }
+            if self.members.insert(*item) {
+                self.items.push(*item);
+            }
+        }
+
+        // MANDATE: Assertions
+        assert_eq!(self.items.len(), self.members.len());
+        self.items.len() != before
+    }
+
+    /// Select unselected items and deselect selected ones.
+    /// MANDATE: ≤60 SLOC, bounded by MAX_SELECTION.
+    pub fn toggle(&mut self, items: &[SelectionItem]) -> bool {
+        let (selected, unselected): (Vec<SelectionItem>, Vec<SelectionItem>) =
+            items.iter().partition(|item| self.members.contains(item));
+
+        let removed = self.remove(&selected);
+        let added = self.add(&unselected);
+        removed || added
+    }
+
+    /// Replace the whole selection.
+    /// MANDATE: ≤60 SLOC.
+    pub fn replace(&mut self, items: &[SelectionItem]) -> bool {
+        let before = self.items.clone();
+        self.clear();
+        self.add(items);
+        self.items != before
+    }
+
+    /// Deselect items. Returns whether anything changed.
+    /// MANDATE: ≤60 SLOC.
+    pub fn remove(&mut self, items: &[SelectionItem]) -> bool {
+        let gone: HashSet<SelectionItem> = items
+            .iter()
+            .filter(|item| self.members.remove(item))
+            .copied()
+            .collect();
+        self.items.retain(|item| !gone.contains(item));
+
+        // MANDATE: Assertions
+        assert_eq!(self.items.len(), self.members.len());
+        !gone.is_empty()
+    }
+
+    /// Deselect everything.
+    /// MANDATE: ≤60 SLOC.
+    pub fn clear(&mut self) -> bool {
+        let changed = !self.items.is_empty();
+        self.items.clear();
+        self.members.clear();
+        changed
+    }
+
+    /// Select every pickable shape, in ID order.
+    /// MANDATE: ≤60 SLOC.
+    pub fn select_all(&mut self, doc: &Document) -> bool {
+        let items: Vec<SelectionItem> = doc
+            .all_shapes()
+            .into_iter()
+            .filter(|shape| is_pickable(doc, shape))
+            .map(|shape| SelectionItem::Shape(shape.id))
+            .collect();
+        self.replace(&items)
+    }
+
+    /// Select shapes in a world rectangle, extending the selection if additive.
+    /// MANDATE: ≤60 SLOC, R-tree broad phase via marquee.
+    pub fn select_in_rect(
+        &mut self,
+        doc: &Document,
+        (min, max): (Vec2, Vec2),
+        mode: MarqueeMode,
+        additive: bool,
+    ) -> bool {
+        let items: Vec<SelectionItem> = marquee(doc, min, max, mode)
+            .into_iter()
+            .map(SelectionItem::Shape)
+            .collect();
+
+        if additive {
+            self.add(&items)
+        } else {
+            self.replace(&items)
+        }
+    }
+
+    /// Drop items deleted from the document. Returns whether anything changed.
+    /// MANDATE: ≤60 SLOC.
+    pub fn prune(&mut self, doc: &Document) -> bool {
+        let missing: Vec<SelectionItem> = self
+            .items
+            .iter()
+            .filter(|item| match item {
+                SelectionItem::Shape(id) => doc.shape(*id).is_none(),
+                SelectionItem::Layer(id) => doc.layers().get_layer(*id).is_none(),
+            })
+            .copied()
+            .collect();
+        self.remove(&missing)
+    }
+
+    /// Combined world bounds of selected shapes and of every shape under selected layers.
+    /// MANDATE: ≤60 SLOC, None when nothing selected has extent.
+    pub fn bounds(&self, doc: &Document) -> Option<Bounds> {
+        let mut shapes = self.shapes();
+        for layer in self.layers() {
+            shapes.extend(doc.shapes_in_subtree(layer));
+        }
+
+        let (min, max) = shapes.iter().filter_map(|id| doc.world_bounds(*id)).fold(
+            (Vec2::INFINITY, Vec2::NEG_INFINITY),
+            |(lo, hi), (min, max)| (lo.min(min), hi.max(max)),
+        );
+
+        (min.x <= max.x).then_some(Bounds { min, max })
+    }
+
+    /// Snapshot for the frontend.
+    /// MANDATE: ≤60 SLOC.
+    pub fn state(&self, doc: &Document) -> SelectionState {
+        SelectionState {
+            items: self.items.clone(),
+            bounds: self.bounds(doc),
+        }
+    }
+}
+
+/// Check convex polygon contains point, for either winding.
+/// MANDATE: ≤60 SLOC, loop bounded by MAX_VERTICES.
+pub fn contains_point(polygon: &[Vec2], point: Vec2) -> bool {
+    // MANDATE: Assertions
+    assert!(polygon.len() >= 3);
+    assert!(polygon.len() <= MAX_VERTICES);
+
+    let scale = edge_scale(polygon);
+    let mut positive = false;
+    let mut negative = false;
+    for (i, start) in polygon.iter().enumerate() {
+        let end = polygon[(i + 1) % polygon.len()];
+        let cross = (end - *start).perp_dot(point - *start);
+        positive |= cross > EDGE_EPSILON * scale;
+        negative |= cross < -EDGE_EPSILON * scale;
+    }
+    !(positive && negative)
+}
+
+/// Check two convex polygons overlap using the Separating Axis Theorem.
+/// MANDATE: ≤60 SLOC, loops bounded by MAX_VERTICES.
+pub fn polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    // MANDATE: Assertions
+    assert!(a.len() >= 3 && b.len() >= 3);
+    assert!(a.len() <= MAX_VERTICES && b.len() <= MAX_VERTICES);
+
+    let tolerance = EDGE_EPSILON * edge_scale(a).max(edge_scale(b));
+    for polygon in [a, b] {
+        for (i, start) in polygon.iter().enumerate() {
+            let axis = (polygon[(i + 1) % polygon.len()] - *start).perp();
+            let (a_min, a_max) = project(a, axis);
+            let (b_min, b_max) = project(b, axis);
+            let reach = tolerance * axis.length();
+            if a_max < b_min - reach || b_max < a_min - reach {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Part of a convex polygon inside an axis-aligned rectangle, by
+/// Sutherland-Hodgman clipping against each edge in turn.
+/// MANDATE: ≤60 SLOC, output bounded by input length + 4.
+fn clip_to_rect(polygon: &[Vec2], min: Vec2, max: Vec2) -> Vec<Vec2> {
+    // Inward normal and offset: a point is inside where normal·p + offset >= 0
+    let edges = [
+        (Vec2::X, -min.x),
+        (Vec2::NEG_X, max.x),
+        (Vec2::Y, -min.y),
+        (Vec2::NEG_Y, max.y),
+    ];
+    let mut clipped = polygon.to_vec();
+    for (normal, offset) in edges {
+        let input = std::mem::take(&mut clipped);
+        for (i, start) in input.iter().enumerate() {
+            let end = input[(i + 1) % input.len()];
+            let (d_start, d_end) = (normal.dot(*start) + offset, normal.dot(end) + offset);
+            if d_start >= 0.0 {
+                clipped.push(*start);
+            }
+            if (d_start >= 0.0) != (d_end >= 0.0) {
+                clipped.push(*start + (end - *start) * (d_start / (d_start - d_end)));
+            }
+        }
+    }
+
+    // MANDATE: Assertions
+    assert!(clipped.len() <= polygon.len() + 4);
+    clipped
+}
+
+/// Check shape's layer is visible and unlocked, including ancestor groups.
+/// MANDATE: ≤60 SLOC.
+fn is_pickable(doc: &Document, shape: &Shape) -> bool {
+    let layers = doc.layers();
+    layers.is_visible(shape.layer_id) && !layers.is_locked(shape.layer_id)
+}
+
+/// World-space corners of shape.
+/// MANDATE: ≤60 SLOC.
+fn world_corners(doc: &Document, shape: &Shape) -> [Vec2; 4] {
+    shape.corners(doc.layers().world_transform(shape.layer_id))
+}
+
+/// Range of polygon projected onto axis.
+/// MANDATE: ≤60 SLOC.
+fn project(polygon: &[Vec2], axis: Vec2) -> (f32, f32) {
+    polygon
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+            let d = p.dot(axis);
+            (lo.min(d), hi.max(d))
+        })
+}
+
+/// Longest edge length, so tolerances scale with the polygon.
+/// MANDATE: ≤60 SLOC.
+fn edge_scale(polygon: &[Vec2]) -> f32 {
+    polygon
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (polygon[(i + 1) % polygon.len()] - *p).length())
+        .fold(1.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::LayerId;
+    use std::f32::consts::FRAC_PI_4;
+
+    /// 10x10 square centred on (5, 5), rotated 45 degrees.
+    fn diamond() -> (Document, LayerId, ShapeId) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".to_string()).unwrap();
+        let id = doc.add_shape(layer, Vec2::ZERO, Vec2::splat(10.0)).unwrap();
+        doc.set_shape_rotation(id, FRAC_PI_4).unwrap();
+        (doc, layer, id)
+    }
+
+    #[test]
+    fn test_rotated_corner_misses() {
+        let (doc, _, id) = diamond();
+
+        assert_eq!(hit_test(&doc, Vec2::splat(5.0)), vec![id]);
+        assert_eq!(hit_test(&doc, Vec2::new(5.0, -1.5)), vec![id]);
+        assert!(doc.hit_test(Vec2::splat(0.5)).contains(&id));
+        assert!(hit_test(&doc, Vec2::splat(0.5)).is_empty());
+    }
+
+    #[test]
+    fn test_clipped_part_misses() {
+        let (mut doc, _, id) = diamond();
+        doc.add_artboard("Board".into(), Vec2::splat(2.0), Vec2::splat(6.0))
+            .unwrap();
+
+        assert_eq!(hit_test(&doc, Vec2::splat(5.0)), vec![id]);
+        assert!(hit_test(&doc, Vec2::new(5.0, -1.5)).is_empty());
+    }
+
+    #[test]
+    fn test_marquee_judges_clipped_part() {
+        let (mut doc, _, id) = diamond();
+        doc.add_artboard("Board".into(), Vec2::splat(2.0), Vec2::splat(6.0))
+            .unwrap();
+
+        // Bottom tip of the diamond lies outside the artboard and is not drawn
+        let tip = (Vec2::new(4.0, -2.0), Vec2::new(6.0, 0.0));
+        assert!(marquee(&doc, tip.0, tip.1, MarqueeMode::Touching).is_empty());
+        let board = (Vec2::splat(1.5), Vec2::splat(8.5));
+        assert_eq!(
+            marquee(&doc, board.0, board.1, MarqueeMode::Contained),
+            vec![id]
+        );
+        let inner = (Vec2::splat(3.0), Vec2::splat(7.0));
+        assert!(marquee(&doc, inner.0, inner.1, MarqueeMode::Contained).is_empty());
+    }
+
+    #[test]
+    fn test_pick_topmost_skips_hidden_and_locked() {
+        let (mut doc, bottom, a) = diamond();
+        let top = doc.add_layer("Top".to_string()).unwrap();
+        let b = doc.add_shape(top, Vec2::splat(4.0), Vec2::ONE).unwrap();
+
+        assert_eq!(pick(&doc, Vec2::splat(4.5)), Some(b));
+        doc.set_layer_locked(top, true).unwrap();
+        assert_eq!(pick(&doc, Vec2::splat(4.5)), Some(a));
+        doc.set_layer_visible(bottom, false).unwrap();
+        assert_eq!(pick(&doc, Vec2::splat(4.5)), None);
+    }
+
+    #[test]
+    fn test_locked_group_blocks_children() {
+        let (mut doc, layer, id) = diamond();
+        let group = doc.add_group("Group".to_string()).unwrap();
+        doc.set_layer_parent(layer, Some(group)).unwrap();
+
+        doc.set_layer_locked(group, true).unwrap();
+        assert!(hit_test(&doc, Vec2::splat(5.0)).is_empty());
+        doc.set_layer_locked(group, false).unwrap();
+        assert_eq!(hit_test(&doc, Vec2::splat(5.0)), vec![id]);
+    }
+
+    #[test]
+    fn test_marquee_touching_uses_exact_outline() {
+        let (doc, _, id) = diamond();
+
+        let corner = marquee(&doc, Vec2::ZERO, Vec2::ONE, MarqueeMode::Touching);
+        assert!(corner.is_empty());
+        let centre = marquee(
+            &doc,
+            Vec2::splat(4.0),
+            Vec2::splat(6.0),
+            MarqueeMode::Touching,
+        );
+        assert_eq!(centre, vec![id]);
+    }
+
+    #[test]
+    fn test_marquee_contained() {
+        let (doc, _, id) = diamond();
+
+        let partial = marquee(
+            &doc,
+            Vec2::splat(-1.0),
+            Vec2::splat(11.0),
+            MarqueeMode::Contained,
+        );
+        assert!(partial.is_empty());
+        let whole = marquee(
+            &doc,
+            Vec2::splat(-3.0),
+            Vec2::splat(13.0),
+            MarqueeMode::Contained,
+        );
+        assert_eq!(whole, vec![id]);
+        assert!(marquee(&doc, Vec2::ONE, Vec2::ZERO, MarqueeMode::Contained).is_empty());
+    }
+
+    #[test]
+    fn test_polygons_overlap_separated_diagonal() {
+        let square = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let near = [
+            Vec2::new(1.6, 0.9),
+            Vec2::new(2.6, 1.9),
+            Vec2::new(1.6, 2.9),
+            Vec2::new(0.6, 1.9),
+        ];
+
+        assert!(!polygons_overlap(&square, &near));
+        assert!(polygons_overlap(&square, &square.map(|p| p + 0.5)));
+        assert!(contains_point(&square, Vec2::X));
+    }
+
+    #[test]
+    fn test_selection_add_toggle_replace() {
+        let shapes = [1, 2, 3].map(SelectionItem::Shape);
+        let mut selection = Selection::new();
+
+        assert!(selection.add(&shapes[..2]));
+        assert!(!selection.add(&shapes[..1]));
+        assert!(selection.toggle(&shapes[1..]));
+        assert_eq!(selection.items(), &[shapes[0], shapes[2]]);
+
+        assert!(selection.replace(&[SelectionItem::Layer(7), shapes[1]]));
+        assert_eq!(selection.shapes(), vec![2]);
+        assert_eq!(selection.layers(), vec![7]);
+        assert!(selection.clear());
+        assert!(selection.is_empty());
+    }
+
+    #[test]
+    fn test_select_all_and_rect() {
+        let (mut doc, layer, a) = diamond();
+        let b = doc.add_shape(layer, Vec2::splat(50.0), Vec2::ONE).unwrap();
+        let locked = doc.add_layer("Locked".to_string()).unwrap();
+        doc.add_shape(locked, Vec2::ZERO, Vec2::ONE).unwrap();
+        doc.set_layer_locked(locked, true).unwrap();
+        let mut selection = Selection::new();
+
+        selection.select_all(&doc);
+        assert_eq!(selection.shapes(), vec![a, b]);
+
+        let rect = (Vec2::splat(40.0), Vec2::splat(60.0));
+        selection.select_in_rect(&doc, rect, MarqueeMode::Touching, false);
+        assert_eq!(selection.shapes(), vec![b]);
+        let rect = (Vec2::splat(4.0), Vec2::splat(6.0));
+        selection.select_in_rect(&doc, rect, MarqueeMode::Touching, true);
+        assert_eq!(selection.shapes(), vec![b, a]);
+    }
+
+    #[test]
+    fn test_selection_bounds_and_prune() {
+        let (mut doc, layer, a) = diamond();
+        let b = doc.add_shape(layer, Vec2::splat(50.0), Vec2::ONE).unwrap();
+        let mut selection = Selection::new();
+        selection.add(&[SelectionItem::Shape(b), SelectionItem::Shape(a)]);
+
+        let bounds = selection.bounds(&doc).unwrap();
+        assert_eq!(bounds.max, Vec2::splat(51.0));
+        assert!(bounds.min.x < -2.0);
+
+        doc.remove_shape(b).unwrap();
+        assert!(selection.prune(&doc));
+        assert_eq!(selection.shapes(), vec![a]);
+        selection.replace(&[SelectionItem::Layer(layer)]);
+        assert_eq!(
+            selection.bounds(&doc),
+            doc.world_bounds(a).map(|(min, max)| Bounds { min, max })
+        );
+
+        doc.remove_layer(layer).unwrap();
+        assert!(selection.prune(&doc));
+        assert_eq!(selection.state(&doc).bounds, None);
+    }
+}
@@ -1,11 +1,20 @@
 // MANDATE: Shape definitions and material batching
 #![deny(warnings)]
 
+use crate::blob_store::BlobId;
 use crate::layer::LayerId;
 use crate::render::pipeline::InstanceData;
-use glam::{Mat3, Vec2, Vec4};
+use glam::{Mat2, Mat3, Vec2, Vec4};
 use serde::{Deserialize, Serialize};
 
+/// Maximum skew angle in radians (just under 90 degrees).
+/// MANDATE: Bounded value, keeps the quad invertible.
+pub const MAX_SKEW: f32 = 1.5;
+
+/// Default pivot: centre of the shape.
+/// MANDATE: Normalized to the unit quad.
+pub const CENTER_PIVOT: Vec2 = Vec2::splat(0.5);
+
 /// Shape ID type.
 /// MANDATE: Type alias for clarity.
 pub type ShapeId = u64;
@@ -20,7 +29,7 @@ pub enum MaterialType {
 
 /// Shape definition.
 /// MANDATE: Bounded data structure.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Shape {
     pub id: ShapeId,
     pub layer_id: LayerId,
@@ -29,6 +38,18 @@ pub struct Shape {
     pub color: Vec4,
     pub z_order: i32,
     pub material: MaterialType,
+    /// Image blob for textured shapes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<BlobId>,
+    /// Rotation about the pivot in radians, within [-PI, PI].
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rotation: f32,
+    /// Horizontal skew angle in radians, within [-MAX_SKEW, MAX_SKEW].
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub skew: f32,
+    /// Rotation and skew origin, normalized to the shape (0.5, 0.5 = centre).
+    #[serde(default = "center_pivot", skip_serializing_if = "is_center")]
+    pub pivot: Vec2,
 }
 
 impl Shape {
@@ -47,6 +68,10 @@ impl Shape {
             color: Vec4::new(1.0, 1.0, 1.0, 1.0),
             z_order: 0,
             material: MaterialType::SolidColor,
+            texture: None,
+            rotation: 0.0,
+            skew: 0.0,
+            pivot: CENTER_PIVOT,
         }
     }
 
@@ -62,14 +87,71 @@ impl Shape {
         self.color = color;
     }
 
-    /// Convert to instance data.
+    /// Local transform mapping the unit quad into layer space:
+    /// scale, then skew and rotate about the pivot, then translate.
+    /// MANDATE: ≤60 SLOC, deterministic math.
+    pub fn local_transform(&self) -> Mat3 {
+        // MANDATE: Assertions
+        assert!(self.size.x > 0.0);
+        assert!(self.size.y > 0.0);
+
+        let turn = rotate_skew(self.rotation, self.skew);
+        let pivot = self.pivot * self.size;
+        let linear = turn * Mat2::from_diagonal(self.size);
+        // Exactly zero offset when unrotated, so axis-aligned shapes stay exact
+        let translation = self.position + (pivot - turn * pivot);
+
+        Mat3::from_cols(
+            linear.x_axis.extend(0.0),
+            linear.y_axis.extend(0.0),
+            translation.extend(1.0),
+        )
+    }
+
+    /// Corners of the shape after layer transform, clockwise from the origin corner.
+    /// MANDATE: ≤60 SLOC.
+    pub fn corners(&self, layer_transform: Mat3) -> [Vec2; 4] {
+        let transform = layer_transform * self.local_transform();
+        [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].map(|c| transform.transform_point2(c))
+    }
+
+    /// Axis-aligned bounds of the shape after layer transform.
+    /// MANDATE: ≤60 SLOC, tight for rotated and skewed quads.
+    pub fn world_bounds(&self, layer_transform: Mat3) -> (Vec2, Vec2) {
+        // Overflowed corners saturate (NaN to the lower end) so bounds stay ordered
+        let limit = Vec2::splat(f32::MAX);
+        let corners = self
+            .corners(layer_transform)
+            .map(|c| c.clamp(-limit, limit));
+        let min = corners.iter().fold(Vec2::INFINITY, |acc, c| acc.min(*c));
+        let max = corners
+            .iter()
+            .fold(Vec2::NEG_INFINITY, |acc, c| acc.max(*c));
+
+        // MANDATE: Assertions
+        assert!(min.x <= max.x && min.y <= max.y);
+        (min, max)
+    }
+
+    /// Convert to instance data. `parent` maps layer space to clip space
+    /// (view × layer world transform) and is applied after the shape's own
+    /// transform; `opacity` is the layer's effective opacity, folded into alpha.
+    /// `None` when the composed transform overflows.
     /// MANDATE: ≤60 SLOC, deterministic conversion.
-    pub fn to_instance_data(&self, layer_transform: Mat3) -> InstanceData {
-        // Build transform matrix: translate -> scale
-        let transform = Mat3::from_scale_angle_translation(self.size, 0.0, self.position)
-            * layer_transform;
+    pub fn to_instance_data(&self, parent: Mat3, opacity: f32) -> Option<InstanceData> {
+        // MANDATE: Input validation
+        assert!((0.0..=1.0).contains(&opacity));
+
+        // Shape first, then layer, then view: clip = view * layer * local * vertex
+        let transform = parent * self.local_transform();
+        let mut color = self.color.to_array();
+        // Straight alpha, matching the pipeline's ALPHA_BLENDING
+        color[3] *= opacity;
+        if !transform.is_finite() {
+            return None;
+        }
 
-        InstanceData {
+        Some(InstanceData {
             transform_0: [
                 transform.x_axis.x,
                 transform.x_axis.y,
@@ -88,17 +170,88 @@ impl Shape {
                 transform.z_axis.z,
                 0.0,
             ],
-            color: self.color.to_array(),
-        }
+            color,
+            clip: InstanceData::NO_CLIP,
+        })
     }
 
-    /// Get bounding box.
+    /// Get layer-space bounding box of the rotated quad.
     /// MANDATE: ≤60 SLOC, deterministic bounds.
     pub fn bounding_box(&self) -> (Vec2, Vec2) {
-        let min = self.position;
-        let max = self.position + self.size;
-        (min, max)
+        self.world_bounds(Mat3::IDENTITY)
+    }
+}
+
+/// Shape placement decomposed from a local transform.
+/// MANDATE: Plain data, fields match Shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub skew: f32,
+}
+
+/// Split local transform into placement for the given pivot.
+/// MANDATE: ≤60 SLOC, rejects reflection, degenerate scale and extreme skew.
+pub fn transform_parts(transform: Mat3, pivot: Vec2) -> Result<Placement, String> {
+    // MANDATE: Input validation
+    if !transform.is_finite() || transform.row(2) != glam::Vec3::Z {
+        return Err("transform not affine".to_string());
+    }
+    if !pivot.is_finite() {
+        return Err("pivot not finite".to_string());
+    }
+
+    let x_axis = transform.x_axis.truncate();
+    let y_axis = transform.y_axis.truncate();
+    // Adding zero folds -0.0 into 0.0 so unrotated results compare exactly
+    let rotation = x_axis.y.atan2(x_axis.x) + 0.0;
+    let upright = Mat2::from_angle(-rotation) * y_axis;
+    let size = Vec2::new(x_axis.length(), upright.y);
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return Err("size must be positive".to_string());
+    }
+
+    let skew = (upright.x / size.y).atan() + 0.0;
+    if skew.abs() > MAX_SKEW {
+        return Err("skew out of range".to_string());
     }
+
+    let turn = rotate_skew(rotation, skew);
+    let anchor = pivot * size;
+    let position = transform.z_axis.truncate() - (anchor - turn * anchor);
+    Ok(Placement {
+        position,
+        size,
+        rotation,
+        skew,
+    })
+}
+
+/// Rotation after horizontal skew.
+/// MANDATE: ≤60 SLOC, exact identity for zero angles.
+fn rotate_skew(rotation: f32, skew: f32) -> Mat2 {
+    let shear = Mat2::from_cols(Vec2::X, Vec2::new(skew.tan(), 1.0));
+    Mat2::from_angle(rotation) * shear
+}
+
+/// Serde helper omitting zero angles.
+/// MANDATE: ≤60 SLOC.
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+/// Serde default pivot.
+/// MANDATE: ≤60 SLOC.
+fn center_pivot() -> Vec2 {
+    CENTER_PIVOT
+}
+
+/// Serde helper omitting the default pivot.
+/// MANDATE: ≤60 SLOC.
+fn is_center(pivot: &Vec2) -> bool {
+    *pivot == CENTER_PIVOT
 }
 
 /// Sort shapes by z-order.
@@ -127,6 +280,7 @@ pub fn group_by_material(shapes: &[Shape]) -> Vec<Vec<&Shape>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glam::Vec3;
 
     #[test]
     fn test_shape_new() {
@@ -182,7 +336,115 @@ mod tests {
     #[test]
     fn test_to_instance_data() {
         let shape = Shape::new(1, 1, Vec2::new(10.0, 20.0), Vec2::new(5.0, 5.0));
-        let instance = shape.to_instance_data(Mat3::IDENTITY);
+        let instance = shape.to_instance_data(Mat3::IDENTITY, 1.0).unwrap();
         assert_eq!(instance.color, [1.0, 1.0, 1.0, 1.0]);
     }
+
+    /// Instance matrix as column-major rows of the 3x3 transform.
+    fn columns(instance: &InstanceData) -> [[f32; 3]; 3] {
+        [instance.transform_0, instance.transform_1, instance.transform_2]
+            .map(|c| [c[0], c[1], c[2]])
+    }
+
+    #[test]
+    fn test_instance_translated_layer() {
+        let shape = Shape::new(1, 1, Vec2::new(10.0, 20.0), Vec2::new(5.0, 5.0));
+        let layer = Mat3::from_translation(Vec2::new(100.0, 0.0));
+        let instance = shape.to_instance_data(layer, 1.0).unwrap();
+        assert_eq!(
+            columns(&instance),
+            [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [110.0, 20.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn test_instance_scaled_layer() {
+        let shape = Shape::new(1, 1, Vec2::new(10.0, 20.0), Vec2::new(5.0, 5.0));
+        let layer = Mat3::from_scale(Vec2::new(2.0, 3.0));
+        let instance = shape.to_instance_data(layer, 1.0).unwrap();
+        // Layer scale applies to the shape's position too
+        assert_eq!(
+            columns(&instance),
+            [[10.0, 0.0, 0.0], [0.0, 15.0, 0.0], [20.0, 60.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn test_instance_rotated_layer_in_view() {
+        let shape = Shape::new(1, 1, Vec2::new(10.0, 0.0), Vec2::new(4.0, 2.0));
+        let layer = Mat3::from_angle(std::f32::consts::FRAC_PI_2);
+        let view = Mat3::from_scale_angle_translation(Vec2::splat(0.5), 0.0, Vec2::new(1.0, 0.0));
+        let instance = shape.to_instance_data(view * layer, 1.0).unwrap();
+
+        let expected = [[0.0, 2.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 5.0, 1.0]];
+        for (column, want) in columns(&instance).iter().zip(expected) {
+            assert!(Vec3::from(*column).abs_diff_eq(Vec3::from(want), 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_instance_layer_opacity() {
+        let mut shape = Shape::new(1, 1, Vec2::ZERO, Vec2::ONE);
+        shape.color = Vec4::new(1.0, 0.5, 0.25, 0.8);
+        let instance = shape.to_instance_data(Mat3::IDENTITY, 0.5).unwrap();
+        assert_eq!(instance.color, [1.0, 0.5, 0.25, 0.4]);
+    }
+
+    #[test]
+    fn test_instance_overflow_is_none() {
+        let shape = Shape::new(1, 1, Vec2::ZERO, Vec2::splat(1e20));
+        let layer = Mat3::from_scale(Vec2::splat(1e20));
+        assert!(shape.to_instance_data(layer, 1.0).is_none());
+
+        let (min, max) = shape.world_bounds(layer);
+        assert!(min.x <= max.x && min.y <= max.y);
+    }
+
+    #[test]
+    fn test_transform_parts_round_trip() {
+        let shape = Shape::new(1, 1, Vec2::new(10.0, 20.0), Vec2::new(5.0, 8.0));
+        let parts = transform_parts(shape.local_transform(), shape.pivot).unwrap();
+        assert_eq!(parts.position, shape.position);
+        assert_eq!(parts.size, shape.size);
+        assert_eq!((parts.rotation, parts.skew), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_transform_parts_rotated_and_skewed() {
+        let mut shape = Shape::new(1, 1, Vec2::new(10.0, 20.0), Vec2::new(5.0, 8.0));
+        shape.rotation = 0.75;
+        shape.skew = -0.3;
+        shape.pivot = Vec2::new(0.0, 1.0);
+
+        let parts = transform_parts(shape.local_transform(), shape.pivot).unwrap();
+        assert!(parts.position.abs_diff_eq(shape.position, 1e-4));
+        assert!(parts.size.abs_diff_eq(shape.size, 1e-4));
+        assert!((parts.rotation - 0.75).abs() < 1e-5);
+        assert!((parts.skew + 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_transform_parts_rejects() {
+        let flipped = Mat3::from_scale(Vec2::new(-1.0, 1.0));
+        assert!(transform_parts(flipped, CENTER_PIVOT).is_err());
+
+        let shear = glam::Vec3::new(100.0, 1.0, 0.0);
+        let sheared = Mat3::from_cols(glam::Vec3::X, shear, glam::Vec3::Z);
+        assert!(transform_parts(sheared, CENTER_PIVOT).is_err());
+    }
+
+    #[test]
+    fn test_rotation_about_pivot() {
+        let mut shape = Shape::new(1, 1, Vec2::ZERO, Vec2::new(10.0, 10.0));
+        shape.rotation = std::f32::consts::FRAC_PI_2;
+
+        let centre = shape.local_transform().transform_point2(CENTER_PIVOT);
+        assert!(centre.abs_diff_eq(Vec2::splat(5.0), 1e-5));
+
+        shape.rotation = std::f32::consts::FRAC_PI_4;
+        let (min, max) = shape.bounding_box();
+        let half_diagonal = 50.0f32.sqrt();
+        assert!(min.abs_diff_eq(Vec2::splat(5.0 - half_diagonal), 1e-4));
+        assert!(max.abs_diff_eq(Vec2::splat(5.0 + half_diagonal), 1e-4));
+    }
 }
@@ -8,24 +8,40 @@ use rstar::{RTree, RTreeObject, AABB};
 /// MANDATE: Type alias for clarity.
 pub type ShapeId = u64;
 
+/// Kind of object an entry stands for; IDs are unique per kind.
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryKind {
+    #[default]
+    Shape,
+    Artboard,
+}
+
 /// Spatial entry for R-tree.
 /// MANDATE: Bounded data structure.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SpatialEntry {
     pub id: ShapeId,
+    pub kind: EntryKind,
     pub min: Vec2,
     pub max: Vec2,
 }
 
 impl SpatialEntry {
-    /// Create new spatial entry.
+    /// Create new shape entry.
     /// MANDATE: ≤60 SLOC, validated bounds.
     pub fn new(id: ShapeId, min: Vec2, max: Vec2) -> Self {
+        Self::with_kind(id, EntryKind::Shape, min, max)
+    }
+
+    /// Create new entry of any kind.
+    /// MANDATE: ≤60 SLOC, validated bounds.
+    pub fn with_kind(id: u64, kind: EntryKind, min: Vec2, max: Vec2) -> Self {
         // MANDATE: Assertions
         assert!(min.x <= max.x);
         assert!(min.y <= max.y);
 
-        Self { id, min, max }
+        Self { id, kind, min, max }
     }
 
     /// Area of bounding box.
@@ -50,8 +66,9 @@ impl RTreeObject for SpatialEntry {
     }
 }
 
-/// Spatial index for shapes.
+/// Spatial index for shapes and artboards.
 /// MANDATE: O(log n) queries.
+#[derive(Clone)]
 pub struct SpatialIndex {
     tree: RTree<SpatialEntry>,
 }
@@ -79,6 +96,12 @@ impl SpatialIndex {
     /// Query shapes in rectangle.
     /// MANDATE: ≤60 SLOC, bounded output.
     pub fn query_rect(&self, min: Vec2, max: Vec2) -> Vec<ShapeId> {
+        self.query_rect_kind(min, max, EntryKind::Shape)
+    }
+
+    /// Query entries of one kind in rectangle.
+    /// MANDATE: ≤60 SLOC, bounded output.
+    pub fn query_rect_kind(&self, min: Vec2, max: Vec2, kind: EntryKind) -> Vec<u64> {
         // MANDATE: Input validation
         assert!(min.x <= max.x);
         assert!(min.y <= max.y);
@@ -87,20 +110,28 @@ impl SpatialIndex {
 
         self.tree
             .locate_in_envelope_intersecting(&envelope)
+            .filter(|entry| entry.kind == kind)
             .map(|entry| entry.id)
             .collect()
     }
 
-    /// Query shapes at point.
+    /// Query shapes whose bounding box contains point.
+    /// Broad phase only: see `selection` for exact shape tests.
     /// MANDATE: ≤60 SLOC, bounded output.
     pub fn query_point(&self, point: Vec2) -> Vec<ShapeId> {
+        self.query_point_kind(point, EntryKind::Shape)
+    }
+
+    /// Query entries of one kind whose bounding box contains point.
+    /// MANDATE: ≤60 SLOC, bounded output.
+    pub fn query_point_kind(&self, point: Vec2, kind: EntryKind) -> Vec<u64> {
         // Use small epsilon to ensure proper intersection with bounding boxes
         // R-tree intersection requires non-zero area for reliable queries
         const EPSILON: f32 = 0.001;
         let min = Vec2::new(point.x - EPSILON, point.y - EPSILON);
         let max = Vec2::new(point.x + EPSILON, point.y + EPSILON);
 
-        self.query_rect(min, max)
+        self.query_rect_kind(min, max, kind)
     }
 
     /// Insert entry.
@@ -109,25 +140,33 @@ impl SpatialIndex {
         self.tree.insert(entry);
     }
 
-    /// Remove entry by ID.
+    /// Remove shape entry by ID.
     /// MANDATE: ≤60 SLOC, bounded operation.
     pub fn remove(&mut self, id: ShapeId) -> bool {
         let to_remove: Vec<_> = self
             .tree
             .iter()
-            .filter(|e| e.id == id)
+            .filter(|e| e.id == id && e.kind == EntryKind::Shape)
             .copied()
             .collect();
 
         // MANDATE: Assertions
         assert!(to_remove.len() <= 1);
 
-        for entry in to_remove {
-            self.tree.remove(&entry);
-            return true;
+        match to_remove.first() {
+            Some(entry) => self.tree.remove(entry).is_some(),
+            None => false,
         }
+    }
+
+    /// Remove exact entry.
+    /// MANDATE: ≤60 SLOC, O(log n) lookup by envelope.
+    pub fn remove_entry(&mut self, entry: &SpatialEntry) -> bool {
+        // MANDATE: Assertions
+        assert!(entry.min.x <= entry.max.x);
+        assert!(entry.min.y <= entry.max.y);
 
-        false
+        self.tree.remove(entry).is_some()
     }
 
     /// Count entries.
@@ -222,4 +261,44 @@ mod tests {
         index.insert(SpatialEntry::new(1, Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)));
         assert!(!index.is_empty());
     }
+
+    #[test]
+    fn test_spatial_index_kinds() {
+        let mut index = SpatialIndex::new();
+        index.insert(SpatialEntry::new(
+            1,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 1.0),
+        ));
+        let board = SpatialEntry::with_kind(1, EntryKind::Artboard, Vec2::ZERO, Vec2::splat(10.0));
+        index.insert(board);
+
+        // Same ID, different kinds: shape queries never see artboards
+        assert_eq!(
+            index.query_point(Vec2::new(5.0, 5.0)),
+            Vec::<ShapeId>::new()
+        );
+        assert_eq!(
+            index.query_point_kind(Vec2::new(5.0, 5.0), EntryKind::Artboard),
+            vec![1]
+        );
+        assert_eq!(index.query_rect(Vec2::ZERO, Vec2::ONE), vec![1]);
+
+        assert!(index.remove(1));
+        assert!(!index.remove(1));
+        assert!(index.remove_entry(&board));
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_spatial_index_remove_entry() {
+        let mut index = SpatialIndex::new();
+        let entry = SpatialEntry::new(1, Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0));
+        index.insert(entry);
+
+        let stale = SpatialEntry::new(1, Vec2::new(5.0, 5.0), Vec2::new(6.0, 6.0));
+        assert!(!index.remove_entry(&stale));
+        assert!(index.remove_entry(&entry));
+        assert!(index.is_empty());
+    }
 }
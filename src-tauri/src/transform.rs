@@ -0,0 +1,409 @@
+// MANDATE: Transform tool sessions: move, 8-handle resize and rotate
+#![deny(warnings)]
+
+use crate::batch_ops::{TransformBatch, TransformTarget, TransformUpdate};
+use crate::document::{Document, DocumentError};
+use crate::selection::{Bounds, Selection, SelectionItem};
+use glam::{Mat3, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Rotation snap increment with Shift held (15 degrees).
+/// MANDATE: Fixed constant.
+pub const SNAP_ANGLE: f32 = std::f32::consts::PI / 12.0;
+
+/// Smallest scale factor, so resizing never collapses or mirrors objects.
+/// MANDATE: Bounded value.
+const MIN_SCALE: f32 = 1e-3;
+
+/// Handle being dragged. Edges and corners refer to the selection bounds
+/// in world space, where y grows upward (top = max y).
+/// MANDATE: Deterministic dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Handle {
+    Move,
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+    Rotate,
+}
+
+/// Modifier keys held during a drag.
+/// Shift constrains (axis lock, aspect lock, angle snap); Ctrl resizes about the centre.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifiers {
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub ctrl: bool,
+}
+
+/// Object captured at drag start.
+/// MANDATE: `frame` maps the object's parent space to world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Target {
+    object: TransformTarget,
+    local: Mat3,
+    frame: Mat3,
+}
+
+/// One drag of a transform handle over the selection captured at its start.
+/// Points are world coordinates, e.g. from `Viewport::screen_to_world`.
+/// MANDATE: Every update is computed from the start state, so frames never accumulate error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformSession {
+    handle: Handle,
+    start: Vec2,
+    bounds: Bounds,
+    targets: Vec<Target>,
+}
+
+impl TransformSession {
+    /// Capture selection for a drag starting at world point.
+    /// MANDATE: ≤60 SLOC, objects under a selected layer move with it, not twice.
+    pub fn begin(
+        doc: &Document,
+        selection: &Selection,
+        handle: Handle,
+        start: Vec2,
+    ) -> Result<Self, DocumentError> {
+        // MANDATE: Input validation
+        if !start.is_finite() {
+            return Err(DocumentError::InvalidValue("drag start not finite".into()));
+        }
+        let bounds = selection
+            .bounds(doc)
+            .ok_or_else(|| DocumentError::InvalidValue("nothing to transform".into()))?;
+
+        let layers = doc.layers();
+        let selected_layers = selection.layers();
+        let covered = |layer_id| {
+            selected_layers.contains(&layer_id)
+                || layers
+                    .ancestors(layer_id)
+                    .iter()
+                    .any(|a| selected_layers.contains(a))
+        };
+
+        let mut targets = Vec::with_capacity(selection.len());
+        for item in selection.items() {
+            let target = match *item {
+                SelectionItem::Shape(id) => doc
+                    .shape(id)
+                    .filter(|shape| !covered(shape.layer_id))
+                    .map(|shape| Target {
+                        object: TransformTarget::Shape(id),
+                        local: shape.local_transform(),
+                        frame: layers.world_transform(shape.layer_id),
+                    }),
+                SelectionItem::Layer(id) => layers
+                    .get_layer(id)
+                    .filter(|layer| !layer.parent.is_some_and(covered))
+                    .map(|layer| Target {
+                        object: TransformTarget::Layer(id),
+                        local: layer.transform,
+                        frame: layer
+                            .parent
+                            .map_or(Mat3::IDENTITY, |p| layers.world_transform(p)),
+                    }),
+            };
+            targets.extend(target);
+        }
+
+        // MANDATE: Assertions
+        assert!(targets.len() <= selection.len());
+        Ok(Self {
+            handle,
+            start,
+            bounds,
+            targets,
+        })
+    }
+
+    /// Handle being dragged.
+    /// MANDATE: ≤60 SLOC.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// World-space transform taking the start state to the state at `current`.
+    /// MANDATE: ≤60 SLOC, finite result.
+    pub fn delta(&self, current: Vec2, modifiers: Modifiers) -> Mat3 {
+        let drag = current - self.start;
+        match self.handle {
+            Handle::Move => Mat3::from_translation(constrain_axis(drag, modifiers.shift)),
+            Handle::Rotate => {
+                let centre = (self.bounds.min + self.bounds.max) * 0.5;
+                let from = self.start - centre;
+                let to = current - centre;
+                let mut angle = from.angle_to(to);
+                if modifiers.shift {
+                    angle = (angle / SNAP_ANGLE).round() * SNAP_ANGLE;
+                }
+                about(centre, Mat3::from_angle(angle))
+            }
+            handle => self.resize(handle, drag, modifiers),
+        }
+    }
+
+    /// Transform batch placing every captured object at its state for `current`.
+    /// MANDATE: ≤60 SLOC, validated input, bounded by the batch limit.
+    pub fn update(
+        &self,
+        current: Vec2,
+        modifiers: Modifiers,
+    ) -> Result<TransformBatch, DocumentError> {
+        // MANDATE: Input validation
+        if !current.is_finite() {
+            return Err(DocumentError::InvalidValue("drag point not finite".into()));
+        }
+
+        let delta = self.delta(current, modifiers);
+        let mut batch = TransformBatch::new();
+        for target in &self.targets {
+            // Untouched objects keep their exact start transform
+            let transform = if delta == Mat3::IDENTITY {
+                target.local
+            } else {
+                target.frame.inverse() * delta * target.frame * target.local
+            };
+            let update = TransformUpdate {
+                target: target.object,
+                transform,
+            };
+            batch.add(update).map_err(DocumentError::CapacityExceeded)?;
+        }
+
+        // MANDATE: Assertions
+        assert_eq!(batch.len(), self.targets.len());
+        Ok(batch)
+    }
+
+    /// Scale about the anchor opposite the handle, or the centre with Ctrl.
+    /// MANDATE: ≤60 SLOC, scale clamped positive.
+    fn resize(&self, handle: Handle, drag: Vec2, modifiers: Modifiers) -> Mat3 {
+        let Bounds { min, max } = self.bounds;
+        let centre = (min + max) * 0.5;
+        // Handle direction from the centre: -1 left/bottom, 0 middle, +1 right/top
+        let side = handle_side(handle);
+        let grip = centre + side * (max - min) * 0.5;
+        let anchor = if modifiers.ctrl {
+            centre
+        } else {
+            centre - side * (max - min) * 0.5
+        };
+
+        let reach = grip - anchor;
+        let moved = grip + drag - anchor;
+        let mut scale = Vec2::ONE;
+        for axis in 0..2 {
+            if side[axis] != 0.0 && reach[axis].abs() > f32::EPSILON {
+                scale[axis] = (moved[axis] / reach[axis]).max(MIN_SCALE);
+            }
+        }
+
+        if modifiers.shift {
+            let uniform = match (side.x != 0.0, side.y != 0.0) {
+                (true, true) => scale.x.max(scale.y),
+                (true, false) => scale.x,
+                _ => scale.y,
+            };
+            scale = Vec2::splat(uniform);
+        }
+
+        // MANDATE: Assertions
+        assert!(scale.x > 0.0 && scale.y > 0.0);
+        about(anchor, Mat3::from_scale(scale))
+    }
+}
+
+/// Direction of handle from the bounds centre.
+/// MANDATE: ≤60 SLOC.
+fn handle_side(handle: Handle) -> Vec2 {
+    match handle {
+        Handle::TopLeft => Vec2::new(-1.0, 1.0),
+        Handle::Top => Vec2::new(0.0, 1.0),
+        Handle::TopRight => Vec2::new(1.0, 1.0),
+        Handle::Right => Vec2::new(1.0, 0.0),
+        Handle::BottomRight => Vec2::new(1.0, -1.0),
+        Handle::Bottom => Vec2::new(0.0, -1.0),
+        Handle::BottomLeft => Vec2::new(-1.0, -1.0),
+        Handle::Left => Vec2::new(-1.0, 0.0),
+        Handle::Move | Handle::Rotate => Vec2::ZERO,
+    }
+}
+
+/// Keep only the dominant axis of a move when constrained.
+/// MANDATE: ≤60 SLOC.
+fn constrain_axis(drag: Vec2, constrain: bool) -> Vec2 {
+    if !constrain {
+        drag
+    } else if drag.x.abs() >= drag.y.abs() {
+        Vec2::new(drag.x, 0.0)
+    } else {
+        Vec2::new(0.0, drag.y)
+    }
+}
+
+/// Apply linear transform about a fixed point.
+/// MANDATE: ≤60 SLOC.
+fn about(point: Vec2, linear: Mat3) -> Mat3 {
+    Mat3::from_translation(point) * linear * Mat3::from_translation(-point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch_ops::apply_transforms;
+    use crate::shape::ShapeId;
+
+    /// Two 10x10 shapes spanning (0, 0)..(30, 10), both selected.
+    fn setup() -> (Document, Selection, [ShapeId; 2]) {
+        let mut doc = Document::new();
+        let layer = doc.add_layer("Layer".to_string()).unwrap();
+        let a = doc.add_shape(layer, Vec2::ZERO, Vec2::splat(10.0)).unwrap();
+        let b = doc
+            .add_shape(layer, Vec2::new(20.0, 0.0), Vec2::splat(10.0))
+            .unwrap();
+        let mut selection = Selection::new();
+        selection.add(&[SelectionItem::Shape(a), SelectionItem::Shape(b)]);
+        (doc, selection, [a, b])
+    }
+
+    fn drag(
+        doc: &mut Document,
+        selection: &Selection,
+        handle: Handle,
+        (start, current): (Vec2, Vec2),
+        modifiers: Modifiers,
+    ) {
+        let session = TransformSession::begin(doc, selection, handle, start).unwrap();
+        let batch = session.update(current, modifiers).unwrap();
+        apply_transforms(doc, &batch).unwrap();
+    }
+
+    fn bounds(doc: &Document, selection: &Selection) -> (Vec2, Vec2) {
+        let bounds = selection.bounds(doc).unwrap();
+        (bounds.min, bounds.max)
+    }
+
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ctrl: false,
+    };
+    const CTRL: Modifiers = Modifiers {
+        shift: false,
+        ctrl: true,
+    };
+
+    #[test]
+    fn test_move_and_axis_lock() {
+        let (mut doc, selection, [a, _]) = setup();
+        let points = (Vec2::ZERO, Vec2::new(5.0, 2.0));
+
+        drag(&mut doc, &selection, Handle::Move, points, SHIFT);
+        assert_eq!(doc.shape(a).unwrap().position, Vec2::new(5.0, 0.0));
+
+        drag(
+            &mut doc,
+            &selection,
+            Handle::Move,
+            points,
+            Modifiers::default(),
+        );
+        assert_eq!(doc.shape(a).unwrap().position, Vec2::new(10.0, 2.0));
+    }
+
+    #[test]
+    fn test_corner_resize_anchors_opposite_corner() {
+        let (mut doc, selection, [_, b]) = setup();
+        let points = (Vec2::new(30.0, 0.0), Vec2::new(60.0, -10.0));
+
+        drag(
+            &mut doc,
+            &selection,
+            Handle::BottomRight,
+            points,
+            Modifiers::default(),
+        );
+
+        assert_eq!(
+            bounds(&doc, &selection),
+            (Vec2::new(0.0, -10.0), Vec2::new(60.0, 10.0))
+        );
+        let shape = doc.shape(b).unwrap();
+        assert!(shape.size.abs_diff_eq(Vec2::new(20.0, 20.0), 1e-4));
+    }
+
+    #[test]
+    fn test_aspect_lock_and_centre_anchor() {
+        let (mut doc, selection, _) = setup();
+        let points = (Vec2::new(30.0, 5.0), Vec2::new(60.0, 5.0));
+
+        drag(&mut doc, &selection, Handle::Right, points, SHIFT);
+        let (min, max) = bounds(&doc, &selection);
+        assert!(min.abs_diff_eq(Vec2::new(0.0, -5.0), 1e-4));
+        assert!(max.abs_diff_eq(Vec2::new(60.0, 15.0), 1e-4));
+
+        let (mut doc, selection, _) = setup();
+        drag(&mut doc, &selection, Handle::Right, points, CTRL);
+        let (min, max) = bounds(&doc, &selection);
+        assert!(min.abs_diff_eq(Vec2::new(-30.0, 0.0), 1e-4));
+        assert!(max.abs_diff_eq(Vec2::new(60.0, 10.0), 1e-4));
+    }
+
+    #[test]
+    fn test_resize_never_mirrors() {
+        let (doc, selection, _) = setup();
+        let session = TransformSession::begin(&doc, &selection, Handle::Left, Vec2::ZERO).unwrap();
+
+        let delta = session.delta(Vec2::new(100.0, 0.0), Modifiers::default());
+        assert!(delta.determinant() > 0.0);
+        assert!(session.update(Vec2::NAN, Modifiers::default()).is_err());
+    }
+
+    #[test]
+    fn test_rotate_snaps_to_fifteen_degrees() {
+        let (mut doc, selection, [a, _]) = setup();
+        let centre = Vec2::new(15.0, 5.0);
+        let start = centre + Vec2::new(0.0, 10.0);
+        let current = centre + Vec2::from_angle(0.3).rotate(Vec2::new(0.0, 10.0));
+
+        drag(
+            &mut doc,
+            &selection,
+            Handle::Rotate,
+            (start, current),
+            SHIFT,
+        );
+
+        let rotation = doc.shape(a).unwrap().rotation;
+        assert!((rotation - SNAP_ANGLE).abs() < 1e-5);
+        let (min, max) = bounds(&doc, &selection);
+        assert!(((min + max) * 0.5).abs_diff_eq(centre, 1e-3));
+    }
+
+    #[test]
+    fn test_selected_layer_moves_its_shapes_once() {
+        let (mut doc, mut selection, [a, _]) = setup();
+        let layer = doc.shape(a).unwrap().layer_id;
+        selection.add(&[SelectionItem::Layer(layer)]);
+
+        let session = TransformSession::begin(&doc, &selection, Handle::Move, Vec2::ZERO).unwrap();
+        let batch = session
+            .update(Vec2::new(4.0, 0.0), Modifiers::default())
+            .unwrap();
+        apply_transforms(&mut doc, &batch).unwrap();
+
+        assert_eq!(batch.len(), 1);
+        assert_eq!(doc.world_bounds(a).unwrap().0, Vec2::new(4.0, 0.0));
+        assert!(
+            TransformSession::begin(&doc, &Selection::new(), Handle::Move, Vec2::ZERO).is_err()
+        );
+    }
+}
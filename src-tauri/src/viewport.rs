@@ -34,6 +34,17 @@ impl Viewport {
         }
     }
 
+    /// Set screen size after a window resize, keeping pan and zoom.
+    /// MANDATE: ≤60 SLOC, validated inputs.
+    pub fn resize(&mut self, screen_width: f32, screen_height: f32) {
+        // MANDATE: Input validation
+        assert!(screen_width > 0.0 && screen_width.is_finite());
+        assert!(screen_height > 0.0 && screen_height.is_finite());
+
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+    }
+
     /// Pan by screen delta.
     /// MANDATE: ≤60 SLOC, bounded operation.
     pub fn pan(&mut self, delta_x: f32, delta_y: f32) {
@@ -128,6 +139,15 @@ mod tests {
         assert_eq!(vp.offset, Vec2::ZERO);
     }
 
+    #[test]
+    fn test_viewport_resize() {
+        let mut vp = Viewport::new(800.0, 600.0);
+        vp.pan(10.0, 0.0);
+        vp.resize(1200.0, 800.0);
+        assert_eq!((vp.screen_width, vp.screen_height), (1200.0, 800.0));
+        assert_eq!(vp.offset, Vec2::new(10.0, 0.0));
+    }
+
     #[test]
     fn test_viewport_pan() {
         let mut vp = Viewport::new(800.0, 600.0);